@@ -0,0 +1,56 @@
+#![allow(clippy::needless_return)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use lib_lexin::{Escapes, Lexer, Section};
+
+// a few kilobytes of source mixing every token kind, repeated to get a measurable input
+fn source(repeat: usize) -> String {
+    let chunk = concat!(
+        "fn main(argc, argv) {\n",
+        "    let name = \"hello \\\"world\\\"\"; // greeting\n",
+        "    let total = 1_000 + 2.5e3 * argc;\n",
+        "    if total > 10 { return café(name, 'x'); }\n",
+        "}\n",
+    );
+    return chunk.repeat(repeat);
+}
+
+fn lexer() -> Lexer {
+    let mut string = Section::new("string", "\"", "\"");
+    string.escapes = Escapes::Decoded('\\');
+
+    return Lexer::new(
+        &["fn".to_string(), "let".to_string(), "if".to_string(), "return".to_string()],
+        &[string, Section::new("char", "'", "'"), Section::new("comment", "//", "\n")],
+        &[
+            ('(', "openparen".to_string()), (')', "closeparen".to_string()),
+            ('{', "openbrace".to_string()), ('}', "closebrace".to_string()),
+            (',', "comma".to_string()), (';', "semicolon".to_string()),
+            ('=', "equal".to_string()), ('+', "plus".to_string()),
+            ('*', "star".to_string()), ('>', "greater".to_string()),
+        ],
+        false,
+    );
+}
+
+fn tokenize(c: &mut Criterion) {
+    let source = source(500);
+    let mut lexer = lexer();
+    lexer.load_str(&source);
+
+    let mut group = c.benchmark_group("tokenize");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("tokenize", |b| b.iter(|| black_box(lexer.tokenize().unwrap())));
+    let mut tokens = Vec::new();
+    group.bench_function("tokenize_into", |b| b.iter(|| {
+        lexer.tokenize_into(&mut tokens).unwrap();
+        black_box(tokens.len())
+    }));
+    group.bench_function("tokenize_ref", |b| b.iter(|| black_box(lexer.tokenize_ref().unwrap().len())));
+    group.bench_function("tokenize_bytes", |b| b.iter(|| black_box(lexer.tokenize_bytes().unwrap().len())));
+    group.bench_function("tokenize_reader", |b| b.iter(|| black_box(lexer.tokenize_reader(source.as_bytes()).count())));
+    group.finish();
+}
+
+criterion_group!(benches, tokenize);
+criterion_main!(benches);
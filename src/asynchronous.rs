@@ -0,0 +1,125 @@
+use crate::{FileId, Lexer, Loc, PushLexer, Token};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const CHUNK_SIZE: usize = 8192;
+
+// the async counterpart of TokenReader. lex errors and invalid utf-8 come out as
+// io::ErrorKind::InvalidData with the LexError or Utf8Error as the inner error
+pub struct AsyncTokenReader<'a, R: AsyncRead + Unpin> {
+    reader: R,
+    push: PushLexer<'a>,
+    tokens: VecDeque<Token>,
+    finished: bool,
+}
+
+fn invalid_data(error: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, error);
+}
+
+impl<R: AsyncRead + Unpin> AsyncTokenReader<'_, R> {
+    // lexes a chunk, or what is left once the reader is done
+    fn push(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.push.extend(chunk);
+        self.push.decode(self.finished).map_err(invalid_data)?;
+        let tokens = self.push.lex(self.finished).map_err(invalid_data)?;
+        self.tokens.extend(tokens);
+        return Ok(());
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for AsyncTokenReader<'_, R> {
+    type Item = io::Result<Token>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<io::Result<Token>>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(token) = this.tokens.pop_front() {
+                return Poll::Ready(Some(Ok(token)));
+            } else if this.finished {
+                return Poll::Ready(None);
+            }
+
+            let mut chunk = [0; CHUNK_SIZE];
+            let mut buffer = ReadBuf::new(&mut chunk);
+            let result = match Pin::new(&mut this.reader).poll_read(cx, &mut buffer) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            this.finished = result.is_err() || buffer.filled().is_empty();
+            if let Err(err) = result.and_then(|_| this.push(buffer.filled())) {
+                this.finished = true;
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+    }
+}
+
+impl Lexer {
+    // reads the file without blocking the runtime, and no more than one byte past Limits::max_input
+    pub async fn load_file_async(&mut self, filename: &str) -> io::Result<()> {
+        let mut buffer = Vec::new();
+        let limit = self.limits.max_input.map_or(u64::MAX, |max_input| max_input as u64 + 1);
+        tokio::fs::File::open(filename).await?.take(limit).read_to_end(&mut buffer).await?;
+        self.check_input_limit(buffer.len(), Loc::new(FileId::new(filename))).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        self.unmap();
+        self.buffer = self.decode_utf16(buffer)?;
+        self.file = FileId::new(filename);
+        return Ok(());
+    }
+
+    pub fn tokenize_async<R: AsyncRead + Unpin>(&self, reader: R) -> AsyncTokenReader<'_, R> {
+        return AsyncTokenReader {
+            reader,
+            push: self.push_lexer(),
+            tokens: VecDeque::new(),
+            finished: false,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LexError, Lexer, Section, Token};
+
+    use futures_core::Stream;
+
+    use std::pin::Pin;
+
+    async fn collect(mut stream: impl Stream<Item = std::io::Result<Token>> + Unpin) -> std::io::Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        while let Some(token) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            tokens.push(token?);
+        }
+        return Ok(tokens);
+    }
+
+    #[test]
+    fn async_test() -> Result<(), Box<dyn std::error::Error>> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let path = std::env::temp_dir().join("lib_lexin_async_test.txt");
+        std::fs::write(&path, "let x = \"a\nb\"\n")?;
+
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        runtime.block_on(lexer.load_file_async(path.to_str().unwrap()))?;
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].loc().file.name(), path.to_str());
+
+        let streamed = runtime.block_on(collect(lexer.tokenize_async(lexer.buffer.as_slice())))?;
+        assert_eq!(streamed, tokens);
+
+        lexer.sections[0].unterminated = crate::Unterminated::Error;
+        let error = runtime.block_on(collect(lexer.tokenize_async("\"open".as_bytes()))).unwrap_err();
+        assert!(matches!(error.get_ref().and_then(|error| error.downcast_ref::<LexError>()), Some(LexError::UnterminatedSection(..))));
+
+        std::fs::remove_file(path)?;
+        return Ok(());
+    }
+}
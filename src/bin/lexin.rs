@@ -0,0 +1,137 @@
+#![allow(clippy::needless_return)]
+
+use lib_lexin::{Lexer, Token};
+
+use std::error::Error;
+use std::io::{self, Read, Write};
+
+const USAGE: &str = "usage: lexin <lexer.toml> [--json] [--filter key=value]... [file]...
+
+prints the tokens of every file, or of stdin without files, as a table or as json lines.
+a filter keeps tokens whose kind, name or text equals the value, kind ignores case";
+
+#[derive(Debug, PartialEq)]
+enum Filter {
+    Kind(String),
+    Name(String),
+    Text(String),
+}
+
+impl Filter {
+    fn parse(filter: &str) -> Result<Filter, String> {
+        return match filter.split_once('=') {
+            Some(("kind", kind)) => Ok(Filter::Kind(kind.to_lowercase())),
+            Some(("name", name)) => Ok(Filter::Name(name.to_string())),
+            Some(("text", text)) => Ok(Filter::Text(text.to_string())),
+            _ => Err(format!("invalid filter: {}, expected kind=, name= or text=", filter)),
+        };
+    }
+
+    fn matches(&self, token: &Token) -> bool {
+        return match self {
+            Filter::Kind(kind) => token.kind().to_string().to_lowercase() == *kind,
+            Filter::Name(name) => token.name() == Some(name.as_str()),
+            Filter::Text(text) => token.as_string() == *text,
+        };
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    config: String,
+    json: bool,
+    filters: Vec<Filter>,
+    files: Vec<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => options.json = true,
+                "--filter" => options.filters.push(Filter::parse(&args.next().ok_or("--filter expects key=value")?)?),
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                _ if options.config.is_empty() => options.config = arg,
+                _ => options.files.push(arg),
+            }
+        }
+
+        if options.config.is_empty() {
+            return Err(USAGE.to_string());
+        }
+        return Ok(options);
+    }
+}
+
+fn print_tokens(file: &str, tokens: &[Token], options: &Options, output: &mut impl Write) -> io::Result<()> {
+    for token in tokens.iter().filter(|token| options.filters.iter().all(|filter| filter.matches(token))) {
+        let loc = token.loc();
+        if options.json {
+            let line = serde_json::json!({
+                "file": file,
+                "line": loc.line,
+                "col": loc.col,
+                "kind": token.kind().to_string(),
+                "name": token.name(),
+                "text": token.as_string(),
+            });
+            writeln!(output, "{}", line)?;
+        } else {
+            let position = format!("{}:{}:{}", file, loc.line, loc.col);
+            writeln!(output, "{:<24} {:<12} {:<12} {:?}", position, token.kind().to_string(), token.name().unwrap_or("-"), token.as_string())?;
+        }
+    }
+    return Ok(());
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut lexer = Lexer::from_config_file(&options.config)?;
+    let mut output = io::stdout().lock();
+
+    if options.files.is_empty() {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        lexer.load_str(&input);
+        print_tokens("<stdin>", &lexer.tokenize()?, options, &mut output)?;
+    }
+
+    for file in &options.files {
+        lexer.load_file(file)?;
+        print_tokens(file, &lexer.tokenize()?, options, &mut output)?;
+    }
+    return Ok(());
+}
+
+fn main() {
+    let result = Options::parse(std::env::args().skip(1)).map_err(Box::<dyn Error>::from).and_then(|options| run(&options));
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_test() -> Result<(), Box<dyn Error>> {
+        let args = ["lexer.toml", "--filter", "kind=Keyword", "a.txt", "--json"].map(String::from);
+        let options = Options::parse(args.into_iter())?;
+        assert_eq!(options, Options {
+            config: "lexer.toml".to_string(),
+            json: true,
+            filters: vec![Filter::Kind("keyword".to_string())],
+            files: vec!["a.txt".to_string()],
+        });
+        assert!(Options::parse(["--filter".to_string(), "size=1".to_string()].into_iter()).is_err());
+
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = 1");
+        let mut output = Vec::new();
+        print_tokens("a.txt", &lexer.tokenize()?, &options, &mut output)?;
+        assert_eq!(String::from_utf8(output)?, "{\"col\":3,\"file\":\"a.txt\",\"kind\":\"Keyword\",\"line\":1,\"name\":null,\"text\":\"let\"}\n");
+        return Ok(());
+    }
+}
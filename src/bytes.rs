@@ -0,0 +1,188 @@
+use crate::{LexError, Lexer, Loc, Run, Section, TokenRef};
+
+use std::borrow::Cow;
+
+// tokens lexed from raw bytes, columns count bytes instead of chars
+#[derive(Debug, PartialEq, Clone)]
+pub enum ByteToken<'src> {
+    Keyword(Cow<'src, str>, Loc),
+    Section(&'src str, &'src [u8], Loc),
+    Integer(usize, Loc),
+    BigInteger(Cow<'src, str>, Loc),
+    Float(f64, Loc),
+    Symbol(char, &'src str, Loc),
+    Ident(&'src [u8], Loc),
+    Eof(Loc),
+}
+
+impl ByteToken<'_> {
+    pub fn loc(&self) -> Loc {
+        return match self {
+            ByteToken::Keyword(_, loc) => *loc,
+            ByteToken::Section(_, _, loc) => *loc,
+            ByteToken::Integer(_, loc) => *loc,
+            ByteToken::BigInteger(_, loc) => *loc,
+            ByteToken::Float(_, loc) => *loc,
+            ByteToken::Symbol(_, _, loc) => *loc,
+            ByteToken::Ident(_, loc) => *loc,
+            ByteToken::Eof(loc) => *loc,
+        };
+    }
+}
+
+fn advance_bytes(loc: &mut Loc, bytes: &[u8]) {
+    for byte in bytes {
+        if *byte == b'\n' {
+            loc.line += 1;
+            loc.col = 1;
+        } else {
+            loc.col += 1;
+        }
+    }
+    loc.offset += bytes.len();
+}
+
+impl Lexer {
+    // the section starting at rest with the longest start delimiter
+    fn byte_section(&self, run: &Run, rest: &[u8]) -> Option<&Section> {
+        return self.rule_set(run).sections.iter()
+            .filter(|section| !section.start.is_empty() && rest.starts_with(section.start.as_bytes()))
+            .max_by_key(|section| section.start.len());
+    }
+
+    fn byte_symbol<'a>(&'a self, run: &Run, rest: &[u8]) -> Option<(char, &'a str)> {
+        // the character rest starts with, if it starts with valid utf-8
+        let head = &rest[..rest.len().min(4)];
+        let valid = std::str::from_utf8(head).map_or_else(|error| &head[..error.valid_up_to()], |_| head);
+        let character = std::str::from_utf8(valid).ok()?.chars().next()?;
+        return self.rule_index(run).symbols.get(&character).map(|position| (character, self.rule_set(run).symbols[*position].1.as_str()));
+    }
+
+    fn flush_bytes<'a>(&'a self, run: &Run, tokens: &mut Vec<ByteToken<'a>>, token: &'a [u8], loc: Loc) {
+        let Ok(text) = std::str::from_utf8(token) else {
+            tokens.push(ByteToken::Ident(token, loc));
+            return;
+        };
+
+        let lexed = match self.lex_token(run, text, loc) {
+            Some(TokenRef::Keyword(keyword, loc)) => ByteToken::Keyword(keyword, loc),
+            Some(TokenRef::Integer(integer, loc)) => ByteToken::Integer(integer, loc),
+            Some(TokenRef::BigInteger(digits, loc)) => ByteToken::BigInteger(digits, loc),
+            Some(TokenRef::Float(float, loc)) => ByteToken::Float(float, loc),
+            Some(TokenRef::Symbol(symbol, name, loc)) => ByteToken::Symbol(symbol, name, loc),
+            // there is no error to report an overflowing integer with, it stays as written
+            Some(TokenRef::Ident(ident, loc) | TokenRef::Error(ident, loc)) => ByteToken::Ident(ident.as_bytes(), loc),
+            _ => return,
+        };
+        tokens.push(lexed);
+    }
+
+    // lexes the loaded input without utf-8 validation, sections capture their bodies as raw bytes
+    // with backslash escapes skipped but not decoded. pragmas, rules, char literals and modes are not
+    // applied, the limits are
+    pub fn tokenize_bytes(&self) -> Result<Vec<ByteToken<'_>>, LexError> {
+        let source = self.source_bytes();
+        let run = self.new_run();
+        let mut tokens = Vec::new();
+        let mut token = 0..0;
+        let mut loc = Loc::new(self.file);
+
+        self.check_input_limit(source.len(), loc)?;
+        let mut index = 0;
+        while index < source.len() {
+            self.check_scan_limits(None, index, tokens.len(), loc)?;
+            let rest = &source[index..];
+            if let Some(section) = self.byte_section(&run, rest) {
+                self.flush_bytes(&run, &mut tokens, &source[token.clone()], loc);
+                token = 0..0;
+
+                let section_start = (index, loc);
+                advance_bytes(&mut loc, section.start.as_bytes());
+                let start = index + section.start.len();
+                let mut end = start;
+                while end < source.len() && (section.end.is_empty() || !source[end..].starts_with(section.end.as_bytes())) {
+                    self.check_scan_limits(Some(section_start), end, tokens.len(), loc)?;
+                    let length = if section.escape().is_some_and(|escape| escape.is_ascii() && source[end] == escape as u8) { 2.min(source.len() - end) } else { 1 };
+                    advance_bytes(&mut loc, &source[end..end + length]);
+                    end += length;
+                }
+                self.check_scan_limits(Some(section_start), end, tokens.len(), loc)?;
+
+                tokens.push(ByteToken::Section(&section.name, &source[start..end], loc));
+                let length = section.end.len().min(source.len() - end);
+                advance_bytes(&mut loc, &source[end..end + length]);
+                index = end + length;
+                continue;
+            }
+
+            let symbol = self.byte_symbol(&run, rest);
+            // only ascii whitespace separates tokens here, other characters are not decoded
+            let whitespace = rest[0].is_ascii() && self.whitespace.contains(&(rest[0] as char));
+            if rest[0] == b'\n' || whitespace || symbol.is_some() {
+                if !token.is_empty() || !whitespace {
+                    self.flush_bytes(&run, &mut tokens, &source[token.clone()], loc);
+                }
+                if whitespace {
+                    self.flush_bytes(&run, &mut tokens, &[], loc);
+                }
+                token = 0..0;
+            } else {
+                if token.is_empty() {
+                    token = index..index;
+                }
+                token.end = index + 1;
+                self.check_token_limit(&token, loc)?;
+            }
+
+            let length = match symbol {
+                Some((symbol, name)) => {
+                    tokens.push(ByteToken::Symbol(symbol, name, loc));
+                    symbol.len_utf8()
+                },
+                None => 1,
+            };
+            advance_bytes(&mut loc, &rest[..length]);
+            index += length;
+        }
+
+        if !token.is_empty() {
+            self.flush_bytes(&run, &mut tokens, &source[token], loc);
+        }
+
+        if self.emit_eof {
+            tokens.push(ByteToken::Eof(loc));
+        }
+        self.check_scan_limits(None, index, tokens.len(), loc)?;
+        return Ok(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ByteToken, Lexer, Section};
+
+    #[test]
+    fn bytes_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["obj".to_string()], &[Section::new("stream", "stream", "endstream")], &[('<', "lt".to_string()), ('>', "gt".to_string())], false);
+        lexer.load_reader(&b"1 0 obj <\xff\xfe>\nstream\x00\x9f\\endstream\xc3 12.5"[..])?;
+
+        let tokens = lexer.tokenize_bytes()?;
+        assert_eq!(tokens[0], ByteToken::Integer(1, tokens[0].loc()));
+        assert_eq!(tokens[1], ByteToken::Integer(0, tokens[1].loc()));
+        assert!(matches!(&tokens[2], ByteToken::Keyword(keyword, _) if keyword == "obj"));
+        assert!(matches!(tokens[3], ByteToken::Symbol('<', "lt", _)));
+        assert_eq!(tokens[4], ByteToken::Ident(b"\xff\xfe", tokens[4].loc()));
+        assert!(matches!(tokens[5], ByteToken::Symbol('>', "gt", _)));
+        assert_eq!(tokens[6], ByteToken::Section("stream", b"\x00\x9f\\endstream\xc3 12.5", tokens[6].loc()));
+        assert_eq!((tokens[6].loc().line, tokens[6].loc().col), (2, 25));
+        assert_eq!(tokens.len(), 7);
+
+        lexer.load_reader(&b"stream\x00endstream \xc3 12.5"[..])?;
+        let tokens = lexer.tokenize_bytes()?;
+        assert_eq!(tokens[0], ByteToken::Section("stream", b"\x00", tokens[0].loc()));
+        assert_eq!(tokens[0].loc().col, 8);
+        assert_eq!(tokens[1], ByteToken::Ident(b"\xc3", tokens[1].loc()));
+        assert_eq!(tokens[2], ByteToken::Float(12.5, tokens[2].loc()));
+        return Ok(());
+    }
+}
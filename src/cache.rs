@@ -0,0 +1,294 @@
+use crate::checksum::Fnv;
+use crate::{FileId, Fragment, Lexer, Loc, Token, TokenKind};
+
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"LXC\0";
+const VERSION: u8 = 1;
+
+// indexed by the kind tag written before every token
+const KINDS: [TokenKind; 18] = [
+    TokenKind::Keyword,
+    TokenKind::Section,
+    TokenKind::Integer,
+    TokenKind::Float,
+    TokenKind::Char,
+    TokenKind::Symbol,
+    TokenKind::Ident,
+    TokenKind::Pragma,
+    TokenKind::Custom,
+    TokenKind::Comment,
+    TokenKind::Error,
+    TokenKind::Indent,
+    TokenKind::Dedent,
+    TokenKind::Newline,
+    TokenKind::Interpolated,
+    TokenKind::Eof,
+    TokenKind::Shebang,
+    TokenKind::BigInteger,
+];
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, format!("invalid token cache: {}", message));
+}
+
+struct Encoder {
+    output: Vec<u8>,
+    // file names are written once and referred to by index afterwards
+    files: Vec<FileId>,
+}
+
+impl Encoder {
+    fn number(&mut self, number: u64) {
+        self.output.extend_from_slice(&number.to_le_bytes());
+    }
+
+    fn string(&mut self, string: &str) {
+        self.number(string.len() as u64);
+        self.output.extend_from_slice(string.as_bytes());
+    }
+
+    fn loc(&mut self, loc: Loc) {
+        match self.files.iter().position(|file| *file == loc.file) {
+            Some(index) => self.number(index as u64),
+            None => {
+                self.number(self.files.len() as u64);
+                self.files.push(loc.file);
+                self.string(loc.file.name().unwrap_or_default());
+                self.output.push(loc.file.name().is_some() as u8);
+            },
+        }
+        self.number(loc.line as u64);
+        self.number(loc.col as u64);
+        self.number(loc.offset as u64);
+    }
+
+    fn tokens(&mut self, tokens: &[Token]) {
+        self.number(tokens.len() as u64);
+        for token in tokens {
+            self.token(token);
+        }
+    }
+
+    fn token(&mut self, token: &Token) {
+        self.output.push(KINDS.iter().position(|kind| *kind == token.kind()).unwrap_or_default() as u8);
+        match token {
+            Token::Keyword(text, _) | Token::Ident(text, _) | Token::Pragma(text, _) | Token::Shebang(text, _) | Token::BigInteger(text, _) | Token::Error(text, _) => self.string(text),
+            Token::Section(name, text, _) | Token::Custom(name, text, _) | Token::Comment(name, text, _) => {
+                self.string(name);
+                self.string(text);
+            },
+            Token::Integer(integer, _) => self.number(*integer as u64),
+            Token::Float(float, _) => self.number(float.to_bits()),
+            Token::Char(character, _) => self.number(*character as u64),
+            Token::Symbol(symbol, name, _) => {
+                self.number(*symbol as u64);
+                self.string(name);
+            },
+            Token::Interpolated(name, fragments, _) => {
+                self.string(name);
+                self.number(fragments.len() as u64);
+                for fragment in fragments {
+                    match fragment {
+                        Fragment::Text(text) => {
+                            self.output.push(0);
+                            self.string(text);
+                        },
+                        Fragment::Tokens(tokens) => {
+                            self.output.push(1);
+                            self.tokens(tokens);
+                        },
+                    }
+                }
+            },
+            Token::Indent(_) | Token::Dedent(_) | Token::Newline(_) | Token::Eof(_) => {},
+        }
+        self.loc(token.loc());
+    }
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    files: Vec<FileId>,
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, length: usize) -> io::Result<&[u8]> {
+        if length > self.input.len() {
+            return Err(invalid("unexpected end of data"));
+        }
+
+        let (taken, rest) = self.input.split_at(length);
+        self.input = rest;
+        return Ok(taken);
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn number(&mut self) -> io::Result<u64> {
+        let bytes = self.take(8)?;
+        return Ok(u64::from_le_bytes(bytes.try_into().expect("took 8 bytes")));
+    }
+
+    fn length(&mut self) -> io::Result<usize> {
+        return usize::try_from(self.number()?).map_err(|_| invalid("length out of range"));
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.length()?;
+        let bytes = self.take(length)?;
+        return String::from_utf8(bytes.to_vec()).map_err(|_| invalid("string is not utf-8"));
+    }
+
+    fn character(&mut self) -> io::Result<char> {
+        return u32::try_from(self.number()?).ok().and_then(char::from_u32).ok_or_else(|| invalid("invalid char"));
+    }
+
+    fn loc(&mut self) -> io::Result<Loc> {
+        let index = self.length()?;
+        if index == self.files.len() {
+            let name = self.string()?;
+            let file = if self.byte()? != 0 { FileId::new(&name) } else { FileId::default() };
+            self.files.push(file);
+        }
+
+        return Ok(Loc {
+            file: *self.files.get(index).ok_or_else(|| invalid("unknown file"))?,
+            line: u32::try_from(self.number()?).map_err(|_| invalid("line out of range"))?,
+            col: u32::try_from(self.number()?).map_err(|_| invalid("col out of range"))?,
+            offset: self.length()?,
+        });
+    }
+
+    fn tokens(&mut self) -> io::Result<Vec<Token>> {
+        let length = self.length()?;
+        // every token takes at least its tag and loc, so a corrupt length can't reserve too much
+        let mut tokens = Vec::with_capacity(length.min(self.input.len() / 33));
+        for _ in 0..length {
+            tokens.push(self.token()?);
+        }
+        return Ok(tokens);
+    }
+
+    fn token(&mut self) -> io::Result<Token> {
+        let kind = *KINDS.get(self.byte()? as usize).ok_or_else(|| invalid("unknown token kind"))?;
+        let token = match kind {
+            TokenKind::Keyword => Token::Keyword(self.string()?, self.loc()?),
+            TokenKind::Ident => Token::Ident(self.string()?, self.loc()?),
+            TokenKind::Pragma => Token::Pragma(self.string()?, self.loc()?),
+            TokenKind::Shebang => Token::Shebang(self.string()?, self.loc()?),
+            TokenKind::Error => Token::Error(self.string()?, self.loc()?),
+            TokenKind::BigInteger => Token::BigInteger(self.string()?, self.loc()?),
+            TokenKind::Section => Token::Section(self.string()?, self.string()?, self.loc()?),
+            TokenKind::Custom => Token::Custom(self.string()?, self.string()?, self.loc()?),
+            TokenKind::Comment => Token::Comment(self.string()?, self.string()?, self.loc()?),
+            TokenKind::Integer => Token::Integer(self.length()?, self.loc()?),
+            TokenKind::Float => Token::Float(f64::from_bits(self.number()?), self.loc()?),
+            TokenKind::Char => Token::Char(self.character()?, self.loc()?),
+            TokenKind::Symbol => Token::Symbol(self.character()?, self.string()?, self.loc()?),
+            TokenKind::Interpolated => {
+                let name = self.string()?;
+                let mut fragments = Vec::new();
+                for _ in 0..self.length()? {
+                    fragments.push(match self.byte()? {
+                        0 => Fragment::Text(self.string()?),
+                        1 => Fragment::Tokens(self.tokens()?),
+                        _ => return Err(invalid("unknown fragment")),
+                    });
+                }
+                Token::Interpolated(name, fragments, self.loc()?)
+            },
+            TokenKind::Indent => Token::Indent(self.loc()?),
+            TokenKind::Dedent => Token::Dedent(self.loc()?),
+            TokenKind::Newline => Token::Newline(self.loc()?),
+            TokenKind::Eof => Token::Eof(self.loc()?),
+        };
+        return Ok(token);
+    }
+}
+
+impl Lexer {
+    // the key a cache is written under, covers the loaded input and every option in config_hash
+    fn cache_hash(&self) -> u64 {
+        let mut hasher = Fnv::new();
+        hasher.number(self.config_hash());
+        hasher.bytes(self.source_bytes());
+        return hasher.0;
+    }
+
+    // writes tokens lexed from the loaded input in a compact binary format, see read_cache
+    pub fn write_cache(&self, tokens: &[Token], mut writer: impl Write) -> io::Result<()> {
+        let mut encoder = Encoder {
+            output: Vec::new(),
+            files: Vec::new(),
+        };
+        encoder.output.extend_from_slice(MAGIC);
+        encoder.output.push(VERSION);
+        encoder.number(self.cache_hash());
+        encoder.tokens(tokens);
+        return writer.write_all(&encoder.output);
+    }
+
+    // reads tokens written by write_cache. None when the cache was written for other input, another
+    // config or another version of the format, in which case the input has to be lexed again. a
+    // lexer with hooks like map_token only reads caches of another run with hooks_version set
+    pub fn read_cache(&self, mut reader: impl Read) -> io::Result<Option<Vec<Token>>> {
+        let mut input = Vec::new();
+        reader.read_to_end(&mut input)?;
+
+        let mut decoder = Decoder {
+            input: &input,
+            files: Vec::new(),
+        };
+        if decoder.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("missing header"));
+        }
+        if decoder.byte()? != VERSION || decoder.number()? != self.cache_hash() {
+            return Ok(None);
+        }
+
+        let tokens = decoder.tokens()?;
+        if !decoder.input.is_empty() {
+            return Err(invalid("trailing data"));
+        }
+        return Ok(Some(tokens));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section, Token};
+
+    #[test]
+    fn cache_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"").interpolate("{", "}")], &[('=', "equal".to_string())], false);
+        lexer.char_quote = Some('\'');
+        lexer.load_str("let a = \"x{b = 1.5}\" 'c' 12");
+
+        let tokens = lexer.tokenize()?;
+        let mut cache = Vec::new();
+        lexer.write_cache(&tokens, &mut cache)?;
+        assert_eq!(lexer.read_cache(cache.as_slice())?, Some(tokens));
+        assert!(lexer.read_cache(&cache[..cache.len() - 1]).is_err());
+        assert!(lexer.read_cache(&b"not a cache"[..]).is_err());
+
+        // tokens from another map_token are not read back
+        lexer.map_token = Some(|token| (!matches!(token, Token::Integer(..))).then_some(token));
+        assert_eq!(lexer.read_cache(cache.as_slice())?, None);
+
+        // the same version stands for the same hooks
+        lexer.hooks_version = Some(1);
+        let mut cache = Vec::new();
+        lexer.write_cache(&lexer.tokenize()?, &mut cache)?;
+        lexer.map_token = Some(|token| (!matches!(token, Token::Integer(..))).then_some(token));
+        assert_eq!(lexer.read_cache(cache.as_slice())?.map(|tokens| tokens.len()), Some(5));
+        lexer.hooks_version = Some(2);
+        assert_eq!(lexer.read_cache(cache.as_slice())?, None);
+
+        lexer.load_str("let a = \"x{b = 1.5}\" 'c' 13");
+        assert_eq!(lexer.read_cache(cache.as_slice())?, None);
+        return Ok(());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{Lexer, Token};
+
+use std::sync::mpsc;
+
+// a channel sender tokens can be pushed into, send blocks while a bounded channel is full
+pub trait TokenSender {
+    fn send_token(&self, token: Token) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+impl TokenSender for mpsc::Sender<Token> {
+    fn send_token(&self, token: Token) -> Result<(), Box<dyn std::error::Error>> {
+        return Ok(self.send(token)?);
+    }
+}
+
+impl TokenSender for mpsc::SyncSender<Token> {
+    fn send_token(&self, token: Token) -> Result<(), Box<dyn std::error::Error>> {
+        return Ok(self.send(token)?);
+    }
+}
+
+#[cfg(feature = "crossbeam")]
+impl TokenSender for crossbeam_channel::Sender<Token> {
+    fn send_token(&self, token: Token) -> Result<(), Box<dyn std::error::Error>> {
+        return Ok(self.send(token)?);
+    }
+}
+
+// uses blocking_send, so this must not be called from within an async context
+#[cfg(feature = "tokio")]
+impl TokenSender for tokio::sync::mpsc::Sender<Token> {
+    fn send_token(&self, token: Token) -> Result<(), Box<dyn std::error::Error>> {
+        return Ok(self.blocking_send(token)?);
+    }
+}
+
+impl Lexer {
+    // tokens are sent as each batch of lines is lexed, so a consumer on another thread can start right away
+    pub fn tokenize_to_channel(&self, sender: &impl TokenSender) -> Result<(), Box<dyn std::error::Error>> {
+        for token in self.tokenize_reader(self.source_bytes()) {
+            sender.send_token(token?)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Lexer;
+
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn channel_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[], false);
+        lexer.load_str(&format!("{} ", "let x\n".repeat(100)));
+
+        let (sender, receiver) = mpsc::sync_channel(4);
+        let consumer = thread::spawn(move || receiver.iter().count());
+
+        lexer.tokenize_to_channel(&sender)?;
+        drop(sender);
+        assert_eq!(consumer.join().unwrap(), 200);
+        return Ok(());
+    }
+}
@@ -0,0 +1,305 @@
+use crate::{is_ident_char, Escapes, Fragment, Lexer, ModeAction, Section, Token};
+
+use std::sync::Arc;
+
+// 64 bit FNV-1a, used instead of DefaultHasher since its output is not guaranteed to be stable
+pub(crate) struct Fnv(pub(crate) u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Fnv {
+        return Fnv(0xcbf29ce484222325);
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub(crate) fn number(&mut self, number: u64) {
+        self.bytes(&number.to_le_bytes());
+    }
+
+    // strings are length prefixed so ("ab", "c") and ("a", "bc") hash differently
+    fn string(&mut self, string: &str) {
+        self.number(string.len() as u64);
+        self.bytes(string.as_bytes());
+    }
+
+    fn rules(&mut self, keywords: &[String], sections: &[Section], symbols: &[(char, String)]) {
+        self.number(keywords.len() as u64);
+        for keyword in keywords {
+            self.string(keyword);
+        }
+
+        self.number(sections.len() as u64);
+        for section in sections {
+            self.string(&section.name);
+            self.string(&section.start);
+            self.string(&section.end);
+            let (escapes, escape) = match section.escapes {
+                Escapes::Off => (0, None),
+                Escapes::Kept(escape) => (1, Some(escape)),
+                Escapes::Stripped(escape) => (2, Some(escape)),
+                Escapes::Decoded(escape) => (3, Some(escape)),
+            };
+            self.number(escapes);
+            self.number(escape.map_or(u64::MAX, u64::from));
+            self.string(section.language.as_deref().unwrap_or_default());
+            self.number(section.language.is_some() as u64);
+            self.number(section.unterminated as u64);
+            let (open, close) = section.interpolation.clone().unwrap_or_default();
+            self.string(&open);
+            self.string(&close);
+            self.number(section.interpolation.is_some() as u64);
+            self.number(section.heredoc.map_or(0, |heredoc| heredoc as u64 + 1));
+            self.number(section.line as u64);
+            self.number(section.nested.len() as u64);
+            for nested in &section.nested {
+                self.string(nested);
+            }
+            self.number(section.allow_newlines as u64);
+            self.number(section.keep_delimiters as u64);
+            self.number(section.sub_lexer.is_some() as u64);
+        }
+
+        self.number(symbols.len() as u64);
+        for (symbol, name) in symbols {
+            self.number(*symbol as u64);
+            self.string(name);
+        }
+    }
+}
+
+pub fn token_hash(tokens: &[Token], include_locs: bool) -> u64 {
+    let mut hasher = Fnv::new();
+    for token in tokens {
+        hasher.number(token.kind() as u64);
+        match token {
+            Token::Keyword(keyword, _) => hasher.string(keyword),
+            Token::Section(name, value, _) => {
+                hasher.string(name);
+                hasher.string(value);
+            },
+            Token::Integer(integer, _) => hasher.number(*integer as u64),
+            Token::Float(float, _) => hasher.number(float.to_bits()),
+            Token::Symbol(value, name, _) => {
+                hasher.number(*value as u64);
+                hasher.string(name);
+            },
+            Token::Ident(ident, _) => hasher.string(ident),
+            Token::Pragma(pragma, _) | Token::Shebang(pragma, _) => hasher.string(pragma),
+            Token::Error(text, _) | Token::BigInteger(text, _) => hasher.string(text),
+            Token::Char(character, _) => hasher.number(*character as u64),
+            Token::Custom(name, text, _) | Token::Comment(name, text, _) => {
+                hasher.string(name);
+                hasher.string(text);
+            },
+            Token::Interpolated(name, fragments, _) => {
+                hasher.string(name);
+                hasher.number(fragments.len() as u64);
+                for fragment in fragments {
+                    match fragment {
+                        Fragment::Text(text) => hasher.string(text),
+                        Fragment::Tokens(tokens) => hasher.number(token_hash(tokens, include_locs)),
+                    }
+                }
+            },
+            Token::Indent(_) | Token::Dedent(_) | Token::Newline(_) | Token::Eof(_) => {},
+        }
+
+        if include_locs {
+            let loc = token.loc();
+            hasher.number(loc.line as u64);
+            hasher.number(loc.col as u64);
+        }
+    }
+    return hasher.0;
+}
+
+impl Lexer {
+    // hashes every option that affects tokenization, but not the loaded input, the file or the
+    // debug_hook. functions can't be hashed by what they do, so ident_char, map_token, map_number,
+    // the rule matchers and the sub lexers are hashed by address, which only tells them apart within
+    // one run of the program. with hooks_version set the version is hashed in their place, so the
+    // hash stays the same across runs for as long as the caller keeps the version up to date
+    pub fn config_hash(&self) -> u64 {
+        let mut hasher = Fnv::new();
+
+        hasher.rules(&self.keywords, &self.sections, &self.symbols);
+
+        hasher.number(self.keyword_tags.len() as u64);
+        for (keyword, tag) in &self.keyword_tags {
+            hasher.string(keyword);
+            // typed tags can't be hashed, only that they are there
+            hasher.string(tag.as_str().unwrap_or_default());
+            hasher.number(tag.as_str().is_some() as u64);
+        }
+
+        hasher.number(self.keyword_aliases.len() as u64);
+        for (alias, keyword) in &self.keyword_aliases {
+            hasher.string(alias);
+            hasher.string(keyword);
+        }
+
+        hasher.number(self.symbol_info.len() as u64);
+        for (symbol, info) in &self.symbol_info {
+            hasher.string(symbol);
+            hasher.number(info.precedence as u64);
+            hasher.number(info.associativity as u64);
+            hasher.number(info.arity as u64);
+        }
+
+        hasher.number(self.symbol_pairs.len() as u64);
+        for (open, close) in &self.symbol_pairs {
+            hasher.string(open);
+            hasher.string(close);
+        }
+        hasher.number(self.check_pairs as u64);
+
+        hasher.number(self.allow_whitespace as u64);
+        hasher.number(self.whitespace.len() as u64);
+        for character in &self.whitespace {
+            hasher.number(*character as u64);
+        }
+        hasher.number(self.ident_symbols.len() as u64);
+        for (symbol, position) in &self.ident_symbols {
+            hasher.number(*symbol as u64);
+            hasher.number(*position as u64);
+        }
+        hasher.number(self.allow_exponent as u64);
+        hasher.number(self.allow_digit_separators as u64);
+        hasher.number(self.allow_numbers as u64);
+        hasher.number(self.allow_leading_dot as u64);
+        hasher.number(self.allow_trailing_dot as u64);
+        hasher.number(self.decimal_separator as u64);
+        hasher.number(self.number_suffixes.len() as u64);
+        for suffix in &self.number_suffixes {
+            hasher.string(suffix);
+        }
+        hasher.number(self.integer_overflow as u64);
+        hasher.number(self.emit_eof as u64);
+        hasher.number(self.tab_width as u64);
+        hasher.number(self.expand_tabs as u64);
+        hasher.number(self.column_unit as u64);
+        hasher.number(self.case_insensitive_keywords as u64);
+        hasher.number(self.split_keywords as u64);
+        hasher.number(self.shebang as u64);
+        hasher.number(self.emit_shebang as u64);
+        hasher.number(self.transcode_utf16 as u64);
+        hasher.number(self.offside as u64);
+        hasher.number(self.emit_newlines as u64);
+        hasher.number(self.strict as u64);
+        for limit in [self.limits.max_input, self.limits.max_token_length, self.limits.max_section_length, self.limits.max_tokens] {
+            hasher.number(limit.map_or(u64::MAX, |limit| limit as u64));
+        }
+        hasher.number(self.emit_unknown as u64);
+        hasher.number(self.xid_idents as u64);
+        hasher.number(self.normalize_idents as u64);
+        hasher.number(self.char_quote.map_or(u64::MAX, u64::from));
+        hasher.string(self.pragma_prefix.as_deref().unwrap_or_default());
+        hasher.number(self.pragma_prefix.is_some() as u64);
+        hasher.string(self.line_directive.as_deref().unwrap_or_default());
+        hasher.number(self.line_directive.is_some() as u64);
+
+        hasher.number(self.modes.len() as u64);
+        for mode in &self.modes {
+            hasher.string(&mode.name);
+            hasher.rules(&mode.keywords, &mode.sections, &mode.symbols);
+        }
+
+        hasher.number(self.transitions.len() as u64);
+        for transition in &self.transitions {
+            hasher.string(transition.mode.as_deref().unwrap_or_default());
+            hasher.number(transition.mode.is_some() as u64);
+            hasher.string(&transition.trigger);
+            match &transition.action {
+                ModeAction::Push(mode) => hasher.string(mode),
+                ModeAction::Pop => hasher.number(u64::MAX),
+            }
+        }
+
+        hasher.number(self.rules.len() as u64);
+        for rule in &self.rules {
+            hasher.string(&rule.name);
+        }
+
+        // the address of the default ident_char changes from run to run, so it is left out
+        let default_ident_char = std::ptr::fn_addr_eq(self.ident_char, is_ident_char as fn(char) -> bool);
+        hasher.number(default_ident_char as u64);
+        hasher.number(self.map_token.is_some() as u64);
+        hasher.number(self.map_number.is_some() as u64);
+        match self.hooks_version {
+            Some(version) => hasher.number(version),
+            None => {
+                hasher.number(if default_ident_char { 0 } else { self.ident_char as usize as u64 });
+                hasher.number(self.map_token.map_or(0, |map_token| map_token as usize as u64));
+                hasher.number(self.map_number.map_or(0, |map_number| map_number as usize as u64));
+                for rule in &self.rules {
+                    hasher.number(Arc::as_ptr(&rule.matcher) as *const () as usize as u64);
+                }
+                let sections = self.sections.iter().chain(self.modes.iter().flat_map(|mode| &mode.sections));
+                for sub_lexer in sections.filter_map(|section| section.sub_lexer) {
+                    hasher.number(sub_lexer as usize as u64);
+                }
+            },
+        }
+        hasher.number(self.hooks_version.is_some() as u64);
+
+        hasher.number(self.comment_sections.len() as u64);
+        for comment in &self.comment_sections {
+            hasher.string(comment);
+        }
+
+        hasher.number(self.languages.len() as u64);
+        for (name, lexer) in &self.languages {
+            hasher.string(name);
+            hasher.number(lexer.config_hash());
+        }
+        return hasher.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+
+        lexer.load_str("let x = 1\n ");
+        let compact = lexer.tokenize()?;
+        lexer.load_str("let   x  =  1\n\n ");
+        let spaced = lexer.tokenize()?;
+
+        assert_eq!(token_hash(&compact, false), token_hash(&spaced, false));
+        assert_ne!(token_hash(&compact, true), token_hash(&spaced, true));
+
+        let hash = lexer.config_hash();
+        lexer.allow_exponent = false;
+        assert_ne!(hash, lexer.config_hash());
+
+        // hooks are part of the config as well
+        let hash = lexer.config_hash();
+        lexer.ident_char = |character| character.is_ascii_alphanumeric() || character == '-';
+        let ident_char = lexer.config_hash();
+        assert_ne!(hash, ident_char);
+        lexer.map_token = Some(Some);
+        let map_token = lexer.config_hash();
+        assert_ne!(ident_char, map_token);
+        lexer.map_number = Some(|_, _| None);
+        assert_ne!(map_token, lexer.config_hash());
+        assert_eq!(lexer.clone().config_hash(), lexer.config_hash());
+
+        // the version stands for the hooks, but not for whether they are set
+        lexer.hooks_version = Some(1);
+        let version = lexer.config_hash();
+        lexer.ident_char = |character| character.is_ascii_alphabetic();
+        assert_eq!(version, lexer.config_hash());
+        lexer.map_number = None;
+        assert_ne!(version, lexer.config_hash());
+        return Ok(());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Escapes, Lexer, Section};
+
+use std::fs;
+
+// parses a line based lexer spec, blank lines and lines starting with '#' are ignored
+//
+// keyword fn
+// symbol + Plus
+// section string " "
+pub fn parse_spec(spec: &str) -> Result<Lexer, Box<dyn std::error::Error>> {
+    let mut lexer = Lexer::new(&[], &[], &[], false);
+
+    for (index, line) in spec.lines().enumerate() {
+        let parts = line.split_whitespace().collect::<Vec<&str>>();
+        match parts.as_slice() {
+            [] => {},
+            [comment, ..] if comment.starts_with('#') => {},
+            ["keyword", keyword] => lexer.keywords.push(keyword.to_string()),
+            ["symbol", symbol, name] if symbol.chars().count() == 1 => {
+                lexer.symbols.push((symbol.chars().next().unwrap_or_default(), name.to_string()));
+            },
+            ["section", name, start, end] => lexer.sections.push(Section::new(name, start, end)),
+            ["whitespace", "on"] => lexer.allow_whitespace = true,
+            _ => return Err(format!("line {}: invalid spec: {}", index + 1, line).into()),
+        }
+    }
+    return Ok(lexer);
+}
+
+fn variant_name(name: &str) -> String {
+    let mut variant = String::new();
+    let mut upper = true;
+    for character in name.chars() {
+        if character.is_ascii_alphanumeric() {
+            if upper {
+                variant.extend(character.to_uppercase());
+            } else {
+                variant.push(character);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+
+    if variant.is_empty() || variant.starts_with(|c: char| c.is_ascii_digit()) {
+        variant.insert(0, '_');
+    }
+    if variant == "Self" {
+        variant.push('_');
+    }
+    return variant;
+}
+
+fn unique_variants<'a>(names: impl Iterator<Item = &'a str>) -> Vec<(String, &'a str)> {
+    let mut variants: Vec<(String, &str)> = Vec::new();
+    for name in names {
+        let mut variant = variant_name(name);
+        while variants.iter().any(|(existing, _)| *existing == variant) {
+            variant.push('_');
+        }
+        variants.push((variant, name));
+    }
+    return variants;
+}
+
+fn generate_enum(output: &mut String, enum_name: &str, variants: &[(String, &str)], pattern: &str) {
+    output.push_str("#[allow(dead_code)]\n#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\n");
+    output.push_str(&format!("pub enum {} {{\n", enum_name));
+    for (variant, _) in variants {
+        output.push_str(&format!("    {},\n", variant));
+    }
+    output.push_str("}\n\n");
+
+    output.push_str(&format!("#[allow(dead_code)]\nimpl {} {{\n", enum_name));
+    output.push_str("    pub fn name(&self) -> &'static str {\n        return match self {\n");
+    for (variant, name) in variants {
+        output.push_str(&format!("            {}::{} => {:?},\n", enum_name, variant, name));
+    }
+    output.push_str("        };\n    }\n\n");
+
+    output.push_str(&format!("    pub fn from_name(name: &str) -> Option<{}> {{\n        return match name {{\n", enum_name));
+    for (variant, name) in variants {
+        output.push_str(&format!("            {:?} => Some({}::{}),\n", name, enum_name, variant));
+    }
+    output.push_str("            _ => None,\n        };\n    }\n\n");
+
+    output.push_str(&format!("    pub fn from_token(token: &lib_lexin::Token) -> Option<{}> {{\n", enum_name));
+    output.push_str(&format!("        if let {} = token {{\n            return {}::from_name(name);\n        }}\n        return None;\n    }}\n}}\n\n", pattern, enum_name));
+}
+
+// generates a module with typed Kw and Sym enums and a lexer() constructor
+pub fn generate(lexer: &Lexer) -> String {
+    let mut output = String::from("// @generated by lib_lexin::codegen, do not edit\n\n");
+
+    let keywords = unique_variants(lexer.keywords.iter().map(String::as_str));
+    let symbols = unique_variants(lexer.symbols.iter().map(|(_, name)| name.as_str()));
+    generate_enum(&mut output, "Kw", &keywords, "lib_lexin::Token::Keyword(name, _)");
+    generate_enum(&mut output, "Sym", &symbols, "lib_lexin::Token::Symbol(_, name, _)");
+
+    let escapes = lexer.sections.iter().enumerate().filter(|(_, section)| section.escapes != Escapes::default()).map(|(index, section)| (index, section.escapes)).collect::<Vec<(usize, Escapes)>>();
+    output.push_str(&format!("#[allow(dead_code)]\npub fn lexer() -> lib_lexin::Lexer {{\n    let {}lexer = lib_lexin::Lexer::new(\n        &[", if escapes.is_empty() { "" } else { "mut " }));
+    output.push_str(&lexer.keywords.iter().map(|keyword| format!("{:?}.to_string()", keyword)).collect::<Vec<String>>().join(", "));
+    output.push_str("],\n        &[");
+    output.push_str(&lexer.sections.iter().map(|section| format!("lib_lexin::Section::new({:?}, {:?}, {:?})", section.name, section.start, section.end)).collect::<Vec<String>>().join(", "));
+    output.push_str("],\n        &[");
+    output.push_str(&lexer.symbols.iter().map(|(symbol, name)| format!("({:?}, {:?}.to_string())", symbol, name)).collect::<Vec<String>>().join(", "));
+    output.push_str(&format!("],\n        {},\n    );\n", lexer.allow_whitespace));
+    for (index, escapes) in escapes {
+        output.push_str(&format!("    lexer.sections[{}].escapes = lib_lexin::Escapes::{:?};\n", index, escapes));
+    }
+    output.push_str("    return lexer;\n}\n");
+    return output;
+}
+
+// reads a spec file and writes the generated module, meant to be called from build.rs
+pub fn generate_file(spec_path: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let lexer = parse_spec(&fs::read_to_string(spec_path)?)?;
+    fs::write(output_path, generate(&lexer))?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codegen_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = parse_spec("# test\nkeyword fn\nkeyword self\nsymbol + Plus\nsymbol ( open-brace\nsection string \" \"\n")?;
+        let output = generate(&lexer);
+
+        assert!(output.contains("pub enum Kw {\n    Fn,\n    Self_,\n}"));
+        assert!(output.contains("pub enum Sym {\n    Plus,\n    OpenBrace,\n}"));
+        assert!(output.contains("            \"open-brace\" => Some(Sym::OpenBrace),"));
+        assert!(output.contains("lib_lexin::Section::new(\"string\", \"\\\"\", \"\\\"\")"));
+        assert!(parse_spec("symbol ++ PlusPlus").is_err());
+        return Ok(());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{Fragment, Lexer, Loc, Token, TokenKind, TokenRef};
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+const NO_NAME: u32 = u32::MAX;
+
+// struct-of-arrays token storage, strings are interned and referenced by id
+#[derive(Debug, Clone, Default)]
+pub struct CompactTokens {
+    kinds: Vec<TokenKind>,
+    locs: Vec<Loc>,
+    // the byte range of each token, only known when lexed with Lexer::tokenize_compact
+    spans: Vec<Range<usize>>,
+    payloads: Vec<u64>,
+    names: Vec<u32>,
+    strings: Vec<String>,
+    // the fragments of interpolated sections, indexed by their payload
+    fragments: Vec<Vec<Fragment>>,
+    ids: HashMap<String, u32>,
+}
+
+impl CompactTokens {
+    pub fn new() -> CompactTokens {
+        return CompactTokens::default();
+    }
+
+    fn intern(&mut self, string: &str) -> u32 {
+        if let Some(id) = self.ids.get(string) {
+            return *id;
+        }
+
+        let id = self.strings.len() as u32;
+        self.strings.push(string.to_string());
+        self.ids.insert(string.to_string(), id);
+        return id;
+    }
+
+    pub fn push(&mut self, token: &Token) {
+        let (payload, name) = match token {
+            Token::Keyword(keyword, _) => (self.intern(keyword) as u64, NO_NAME),
+            Token::Section(name, value, _) => (self.intern(value) as u64, self.intern(name)),
+            Token::Integer(integer, _) => (*integer as u64, NO_NAME),
+            Token::Float(float, _) => (float.to_bits(), NO_NAME),
+            Token::Symbol(value, name, _) => (*value as u64, self.intern(name)),
+            Token::Ident(ident, _) => (self.intern(ident) as u64, NO_NAME),
+            Token::Pragma(pragma, _) | Token::Shebang(pragma, _) => (self.intern(pragma) as u64, NO_NAME),
+            Token::Custom(name, text, _) => (self.intern(text) as u64, self.intern(name)),
+            Token::Comment(kind, text, _) => (self.intern(text) as u64, self.intern(kind)),
+            Token::Error(text, _) | Token::BigInteger(text, _) => (self.intern(text) as u64, NO_NAME),
+            Token::Char(character, _) => (*character as u64, NO_NAME),
+            Token::Interpolated(name, fragments, _) => {
+                self.fragments.push(fragments.clone());
+                ((self.fragments.len() - 1) as u64, self.intern(name))
+            },
+            Token::Indent(_) | Token::Dedent(_) | Token::Newline(_) | Token::Eof(_) => (0, NO_NAME),
+        };
+
+        self.kinds.push(token.kind());
+        self.locs.push(token.loc());
+        self.payloads.push(payload);
+        self.names.push(name);
+    }
+
+    // like push without building the token first, the text is interned straight from the source
+    fn push_ref(&mut self, token: TokenRef<'_>) {
+        let (kind, payload, name) = match &token {
+            TokenRef::Keyword(keyword, _) => (TokenKind::Keyword, self.intern(keyword) as u64, NO_NAME),
+            TokenRef::Section(name, value, _) => (TokenKind::Section, self.intern(value) as u64, self.intern(name)),
+            TokenRef::Ident(ident, _) => (TokenKind::Ident, self.intern(ident) as u64, NO_NAME),
+            TokenRef::Custom(name, text, _) => (TokenKind::Custom, self.intern(text) as u64, self.intern(name)),
+            TokenRef::Comment(kind, text, _) => (TokenKind::Comment, self.intern(text) as u64, self.intern(kind)),
+            TokenRef::Error(text, _) => (TokenKind::Error, self.intern(text) as u64, NO_NAME),
+            _ => return self.push(&Token::from(token)),
+        };
+
+        self.kinds.push(kind);
+        self.locs.push(token.loc());
+        self.payloads.push(payload);
+        self.names.push(name);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.kinds.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.kinds.is_empty();
+    }
+
+    pub fn kinds(&self) -> &[TokenKind] {
+        return &self.kinds;
+    }
+
+    pub fn locs(&self) -> &[Loc] {
+        return &self.locs;
+    }
+
+    pub fn kind(&self, index: usize) -> Option<TokenKind> {
+        return self.kinds.get(index).copied();
+    }
+
+    pub fn loc(&self, index: usize) -> Option<Loc> {
+        return self.locs.get(index).copied();
+    }
+
+    // empty unless the tokens were lexed with Lexer::tokenize_compact
+    pub fn spans(&self) -> &[Range<usize>] {
+        return &self.spans;
+    }
+
+    pub fn span(&self, index: usize) -> Option<Range<usize>> {
+        return self.spans.get(index).cloned();
+    }
+
+    // the interned text of keywords, idents, pragmas, shebangs, custom tokens, comments, errors and section bodies
+    pub fn text(&self, index: usize) -> Option<&str> {
+        return match self.kinds.get(index)? {
+            TokenKind::Keyword | TokenKind::Section | TokenKind::Ident | TokenKind::Pragma | TokenKind::Shebang | TokenKind::Custom | TokenKind::Comment | TokenKind::Error | TokenKind::BigInteger => Some(&self.strings[self.payloads[index] as usize]),
+            _ => None,
+        };
+    }
+
+    // the section, symbol, custom rule or comment kind name, interpolated sections included
+    pub fn name(&self, index: usize) -> Option<&str> {
+        return self.names.get(index).filter(|name| **name != NO_NAME).map(|name| self.strings[*name as usize].as_str());
+    }
+
+    pub fn get(&self, index: usize) -> Option<Token> {
+        let loc = self.loc(index)?;
+        let payload = self.payloads[index];
+        let text = || self.text(index).unwrap_or_default().to_string();
+        let name = || self.name(index).unwrap_or_default().to_string();
+
+        return Some(match self.kinds[index] {
+            TokenKind::Keyword => Token::Keyword(text(), loc),
+            TokenKind::Section => Token::Section(name(), text(), loc),
+            TokenKind::Integer => Token::Integer(payload as usize, loc),
+            TokenKind::Float => Token::Float(f64::from_bits(payload), loc),
+            TokenKind::Symbol => Token::Symbol(char::from_u32(payload as u32).unwrap_or_default(), name(), loc),
+            TokenKind::Ident => Token::Ident(text(), loc),
+            TokenKind::Pragma => Token::Pragma(text(), loc),
+            TokenKind::Shebang => Token::Shebang(text(), loc),
+            TokenKind::Custom => Token::Custom(name(), text(), loc),
+            TokenKind::Comment => Token::Comment(name(), text(), loc),
+            TokenKind::Error => Token::Error(text(), loc),
+            TokenKind::BigInteger => Token::BigInteger(text(), loc),
+            TokenKind::Char => Token::Char(char::from_u32(payload as u32).unwrap_or_default(), loc),
+            TokenKind::Indent => Token::Indent(loc),
+            TokenKind::Dedent => Token::Dedent(loc),
+            TokenKind::Newline => Token::Newline(loc),
+            TokenKind::Interpolated => Token::Interpolated(name(), self.fragments[payload as usize].clone(), loc),
+            TokenKind::Eof => Token::Eof(loc),
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Token> + '_ {
+        return (0..self.len()).filter_map(|index| self.get(index));
+    }
+
+    pub fn to_tokens(&self) -> Vec<Token> {
+        return self.iter().collect();
+    }
+}
+
+impl From<&[Token]> for CompactTokens {
+    fn from(tokens: &[Token]) -> CompactTokens {
+        let mut compact = CompactTokens::new();
+        for token in tokens {
+            compact.push(token);
+        }
+        return compact;
+    }
+}
+
+impl From<Vec<Token>> for CompactTokens {
+    fn from(tokens: Vec<Token>) -> CompactTokens {
+        return CompactTokens::from(tokens.as_slice());
+    }
+}
+
+impl From<&CompactTokens> for Vec<Token> {
+    fn from(compact: &CompactTokens) -> Vec<Token> {
+        return compact.to_tokens();
+    }
+}
+
+impl Lexer {
+    // lexes straight into compact storage with the byte range of every token, so no Token is
+    // allocated on the way unless map_token or normalize_idents is set
+    pub fn tokenize_compact(&self) -> Result<CompactTokens, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let (scanned, _) = self.scan_until(source, source.len(), &mut self.new_run(), &mut Loc::new(self.file), true)?;
+
+        let mut compact = CompactTokens::new();
+        for (token, span) in scanned.tokens.into_iter().zip(scanned.spans) {
+            if self.map_token.is_none() && !self.normalize_idents {
+                compact.push_ref(token);
+            } else if let Some(token) = self.finish_token(token) {
+                compact.push(&token);
+            } else {
+                continue;
+            }
+            compact.spans.push(span);
+        }
+        return Ok(compact);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompactTokens, Lexer, Loc, Section, Token, TokenKind};
+
+    #[test]
+    fn compact_test() {
+        let tokens = vec![
+            Token::Keyword("let".to_string(), Loc { line: 1, col: 1, offset: 0, ..Loc::default() }),
+            Token::Ident("x".to_string(), Loc { line: 1, col: 5, offset: 4, ..Loc::default() }),
+            Token::Symbol('=', "equal".to_string(), Loc { line: 1, col: 7, offset: 6, ..Loc::default() }),
+            Token::Float(1.5, Loc { line: 1, col: 9, offset: 8, ..Loc::default() }),
+            Token::Section("string".to_string(), "x".to_string(), Loc { line: 1, col: 13, offset: 12, ..Loc::default() }),
+            Token::Integer(42, Loc { line: 1, col: 17, offset: 16, ..Loc::default() }),
+        ];
+
+        let compact = CompactTokens::from(tokens.as_slice());
+        assert_eq!(compact.kinds()[2], TokenKind::Symbol);
+        assert_eq!(compact.text(4), Some("x"));
+        assert_eq!(compact.name(4), Some("string"));
+        assert_eq!(compact.to_tokens(), tokens);
+        assert_eq!(compact.span(0), None);
+    }
+
+    #[test]
+    fn tokenize_compact_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.load_str("let x = \"a b\"\nlet y = x");
+        let compact = lexer.tokenize_compact()?;
+        assert_eq!(compact.to_tokens(), lexer.tokenize()?);
+        assert_eq!(compact.text(3), Some("a b"));
+        assert_eq!(compact.span(3), Some(8..13));
+        assert_eq!(compact.spans().len(), compact.len());
+
+        lexer.map_token = Some(|token| (!matches!(token, Token::Keyword(..))).then_some(token));
+        let compact = lexer.tokenize_compact()?;
+        assert_eq!(compact.kind(0), Some(TokenKind::Ident));
+        assert_eq!(compact.span(0), Some(4..5));
+        return Ok(());
+    }
+}
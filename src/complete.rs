@@ -0,0 +1,84 @@
+use crate::{Lexer, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Keyword,
+    Symbol,
+}
+
+// label is what gets inserted, detail the symbol name or the first tag of the keyword
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: Option<String>,
+}
+
+fn starts_with(text: &str, prefix: &str, fold: bool) -> bool {
+    return match text.get(..prefix.len()) {
+        Some(start) if fold => start.eq_ignore_ascii_case(prefix),
+        Some(start) => start == prefix,
+        None => false,
+    };
+}
+
+impl Lexer {
+    // keywords and symbols of the base rules matching the word the cursor is at. offset is a byte
+    // offset into the input the tokens were lexed from, and the word is the identifier or keyword
+    // ending there. without one every keyword and symbol is suggested
+    pub fn completions_at(&self, tokens: &[Token], offset: usize) -> Vec<CompletionItem> {
+        // tokens are located at their last character, or just past it at the end of a line
+        let prefix = tokens.iter()
+            .rev()
+            .find(|token| token.loc().offset <= offset)
+            .filter(|token| offset - token.loc().offset <= 1)
+            .and_then(|token| match token {
+                Token::Ident(text, _) | Token::Keyword(text, _) => Some(text.as_str()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let mut items = Vec::new();
+        for keyword in self.keywords.iter().chain(&self.contextual_keywords) {
+            if starts_with(keyword, prefix, self.case_insensitive_keywords) && !items.iter().any(|item: &CompletionItem| item.label == *keyword) {
+                items.push(CompletionItem {
+                    label: keyword.clone(),
+                    kind: CompletionKind::Keyword,
+                    detail: self.keyword_tags.iter().filter(|(tagged, _)| tagged == keyword).find_map(|(_, tag)| tag.as_str()).map(str::to_string),
+                });
+            }
+        }
+
+        // a word is never completed to a symbol, symbols are offered after whitespace or another symbol
+        if prefix.is_empty() {
+            items.extend(self.symbols.iter().map(|(symbol, name)| CompletionItem {
+                label: symbol.to_string(),
+                kind: CompletionKind::Symbol,
+                detail: Some(name.clone()),
+            }));
+        }
+        return items;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompletionKind, Lexer};
+
+    #[test]
+    fn completions_at_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["return".to_string(), "repeat".to_string(), "let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.add_keyword_tagged("return", &["control"]);
+        lexer.load_str("let x = re");
+        let tokens = lexer.tokenize()?;
+
+        let items = lexer.completions_at(&tokens, 10);
+        assert_eq!(items.iter().map(|item| item.label.as_str()).collect::<Vec<&str>>(), ["return", "repeat"]);
+        assert_eq!(items[0].detail.as_deref(), Some("control"));
+
+        let items = lexer.completions_at(&tokens, 8);
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[3].kind, CompletionKind::Symbol);
+        return Ok(());
+    }
+}
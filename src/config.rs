@@ -0,0 +1,450 @@
+use crate::{ColumnUnit, IdentSymbol, IntegerOverflow, KeywordTag, LexMode, Lexer, Limits, ModeAction, ModeTransition, Section, SymbolInfo};
+
+use std::fmt;
+
+// the data part of a lexer, everything except custom rules, the identifier class and the token and
+// number hooks which are code. with the serde feature it can be stored as a data file and turned back into a lexer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LexerConfig {
+    pub keywords: Vec<String>,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<(char, String)>,
+    pub allow_whitespace: bool,
+    pub whitespace: Vec<char>,
+    pub ident_symbols: Vec<(char, IdentSymbol)>,
+    pub allow_exponent: bool,
+    pub allow_digit_separators: bool,
+    pub allow_numbers: bool,
+    pub allow_leading_dot: bool,
+    pub allow_trailing_dot: bool,
+    pub decimal_separator: char,
+    pub number_suffixes: Vec<String>,
+    pub integer_overflow: IntegerOverflow,
+    pub pragma_prefix: Option<String>,
+    pub line_directive: Option<String>,
+    pub keyword_tags: Vec<(String, String)>,
+    pub keyword_aliases: Vec<(String, String)>,
+    pub contextual_keywords: Vec<String>,
+    pub symbol_info: Vec<(String, SymbolInfo)>,
+    pub symbol_pairs: Vec<(String, String)>,
+    pub check_pairs: bool,
+    pub emit_eof: bool,
+    pub tab_width: usize,
+    pub expand_tabs: bool,
+    pub column_unit: ColumnUnit,
+    pub case_insensitive_keywords: bool,
+    pub split_keywords: bool,
+    pub comment_sections: Vec<String>,
+    pub shebang: bool,
+    pub emit_shebang: bool,
+    pub transcode_utf16: bool,
+    pub offside: bool,
+    pub emit_newlines: bool,
+    pub strict: bool,
+    pub limits: Limits,
+    pub emit_unknown: bool,
+    pub xid_idents: bool,
+    pub normalize_idents: bool,
+    pub char_quote: Option<char>,
+    pub modes: Vec<LexMode>,
+    pub transitions: Vec<ModeTransition>,
+    pub languages: Vec<(String, LexerConfig)>,
+}
+
+// the document formats a LexerConfig can be read from, each behind the feature of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "toml")]
+    Toml,
+}
+
+impl ConfigFormat {
+    // guesses the format from a file extension
+    pub fn from_path(path: &str) -> Option<ConfigFormat> {
+        return match std::path::Path::new(path).extension()?.to_str()? {
+            #[cfg(feature = "json")]
+            "json" => Some(ConfigFormat::Json),
+            #[cfg(feature = "toml")]
+            "toml" => Some(ConfigFormat::Toml),
+            _ => None,
+        };
+    }
+}
+
+// a rule that conflicts with another or can never match, see Lexer::validate
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    DuplicateKeyword(String),
+    EmptyKeyword,
+    // one character registered under two names, the first one wins, or the second with Lexer::extend
+    ConflictingSymbol(char, String, String),
+    // a symbol that is never lexed since a section starts with exactly that character
+    SymbolIsSection(char, String),
+    SymbolIsWhitespace(char),
+    EmptyStart(String),
+    EmptyEnd(String),
+    DuplicateSection(String),
+    // the start of the first section is a prefix of the start of the second
+    PrefixSection(String, String),
+    UnknownSection(String),
+    UnknownMode(String),
+    UnknownLanguage(String),
+    // defined differently by an overlay, see Lexer::extend
+    RedefinedSection(String),
+    RedefinedMode(String),
+    RedefinedRule(String),
+}
+
+impl ConfigError {
+    // warnings may well be intended, like \" and \"\"\" strings, the rest is always a mistake
+    pub fn is_warning(&self) -> bool {
+        return matches!(self, ConfigError::DuplicateKeyword(_) | ConfigError::PrefixSection(..) | ConfigError::RedefinedSection(_) | ConfigError::RedefinedMode(_) | ConfigError::RedefinedRule(_));
+    }
+
+    pub fn message(&self) -> String {
+        return match self {
+            ConfigError::DuplicateKeyword(keyword) => format!("keyword {:?} is registered more than once", keyword),
+            ConfigError::EmptyKeyword => "empty keyword".to_string(),
+            ConfigError::ConflictingSymbol(symbol, first, second) => format!("symbol {:?} is named both {} and {}", symbol, first, second),
+            ConfigError::SymbolIsSection(symbol, section) => format!("symbol {:?} is the start of section {}", symbol, section),
+            ConfigError::SymbolIsWhitespace(symbol) => format!("symbol {:?} is whitespace", symbol),
+            ConfigError::EmptyStart(section) => format!("section {} has an empty start", section),
+            ConfigError::EmptyEnd(section) => format!("section {} has an empty end", section),
+            ConfigError::DuplicateSection(section) => format!("section {} has the same delimiters as an earlier section", section),
+            ConfigError::PrefixSection(short, long) => format!("the start of section {} is a prefix of the start of section {}", short, long),
+            ConfigError::UnknownSection(section) => format!("unknown section: {}", section),
+            ConfigError::UnknownMode(mode) => format!("unknown mode: {}", mode),
+            ConfigError::UnknownLanguage(language) => format!("unknown language: {}", language),
+            ConfigError::RedefinedSection(section) => format!("section {} is redefined", section),
+            ConfigError::RedefinedMode(mode) => format!("mode {} is redefined", mode),
+            ConfigError::RedefinedRule(rule) => format!("rule {} is redefined", rule),
+        };
+    }
+
+    pub fn suggestion(&self) -> &'static str {
+        return match self {
+            ConfigError::DuplicateKeyword(_) | ConfigError::DuplicateSection(_) => "remove the later one",
+            ConfigError::EmptyKeyword => "remove it, it never matches",
+            ConfigError::ConflictingSymbol(..) => "remove one of the names",
+            ConfigError::SymbolIsSection(..) => "remove the symbol or give the section a longer start",
+            ConfigError::SymbolIsWhitespace(_) => "remove the character from the whitespace set",
+            ConfigError::EmptyStart(_) => "give the section a start delimiter, it never starts",
+            ConfigError::EmptyEnd(_) => "give the section an end delimiter or use Section::line",
+            ConfigError::PrefixSection(..) => "the longer start wins wherever both match",
+            ConfigError::UnknownSection(_) => "define the section or remove the reference",
+            ConfigError::UnknownMode(_) => "add the mode to modes",
+            ConfigError::UnknownLanguage(_) => "register the language with Lexer::register_language",
+            ConfigError::RedefinedSection(_) | ConfigError::RedefinedMode(_) | ConfigError::RedefinedRule(_) => "the overlay definition replaces the base one",
+        };
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}, {}", self.message(), self.suggestion());
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn validate_rules(lexer: &Lexer, keywords: &[String], sections: &[Section], symbols: &[(char, String)], errors: &mut Vec<ConfigError>) {
+    for (index, keyword) in keywords.iter().enumerate() {
+        if keyword.is_empty() {
+            errors.push(ConfigError::EmptyKeyword);
+        } else if keywords[..index].contains(keyword) {
+            errors.push(ConfigError::DuplicateKeyword(keyword.clone()));
+        }
+    }
+
+    for (index, (symbol, name)) in symbols.iter().enumerate() {
+        if let Some((_, first)) = symbols[..index].iter().find(|(earlier, first)| earlier == symbol && first != name) {
+            errors.push(ConfigError::ConflictingSymbol(*symbol, first.clone(), name.clone()));
+        }
+        if lexer.whitespace.contains(symbol) {
+            errors.push(ConfigError::SymbolIsWhitespace(*symbol));
+        }
+        if let Some(section) = sections.iter().find(|section| section.start == symbol.to_string()) {
+            errors.push(ConfigError::SymbolIsSection(*symbol, section.name.clone()));
+        }
+    }
+
+    for (index, section) in sections.iter().enumerate() {
+        if section.start.is_empty() {
+            errors.push(ConfigError::EmptyStart(section.name.clone()));
+            continue;
+        }
+        if section.end.is_empty() && section.heredoc.is_none() {
+            errors.push(ConfigError::EmptyEnd(section.name.clone()));
+        }
+        if sections[..index].iter().any(|earlier| earlier.start == section.start && earlier.end == section.end) {
+            errors.push(ConfigError::DuplicateSection(section.name.clone()));
+        }
+        for other in sections.iter().filter(|other| other.start.len() > section.start.len() && other.start.starts_with(&section.start)) {
+            errors.push(ConfigError::PrefixSection(section.name.clone(), other.name.clone()));
+        }
+        if let Some(language) = section.language.as_ref().filter(|language| !lexer.languages.iter().any(|(name, _)| name == *language)) {
+            errors.push(ConfigError::UnknownLanguage(language.clone()));
+        }
+    }
+}
+
+impl Default for LexerConfig {
+    fn default() -> LexerConfig {
+        return Lexer::new(&[], &[], &[], false).config();
+    }
+}
+
+impl Lexer {
+    // typed keyword tags have no text form and are left out, string tags are kept
+    pub fn config(&self) -> LexerConfig {
+        return LexerConfig {
+            keywords: self.keywords.clone(),
+            sections: self.sections.clone(),
+            symbols: self.symbols.clone(),
+            allow_whitespace: self.allow_whitespace,
+            whitespace: self.whitespace.clone(),
+            ident_symbols: self.ident_symbols.clone(),
+            allow_exponent: self.allow_exponent,
+            allow_numbers: self.allow_numbers,
+            allow_leading_dot: self.allow_leading_dot,
+            allow_trailing_dot: self.allow_trailing_dot,
+            decimal_separator: self.decimal_separator,
+            number_suffixes: self.number_suffixes.clone(),
+            integer_overflow: self.integer_overflow,
+            allow_digit_separators: self.allow_digit_separators,
+            pragma_prefix: self.pragma_prefix.clone(),
+            line_directive: self.line_directive.clone(),
+            keyword_tags: self.keyword_tags.iter().filter_map(|(keyword, tag)| Some((keyword.clone(), tag.as_str()?.to_string()))).collect(),
+            keyword_aliases: self.keyword_aliases.clone(),
+            contextual_keywords: self.contextual_keywords.clone(),
+            symbol_info: self.symbol_info.clone(),
+            symbol_pairs: self.symbol_pairs.clone(),
+            check_pairs: self.check_pairs,
+            emit_eof: self.emit_eof,
+            tab_width: self.tab_width,
+            expand_tabs: self.expand_tabs,
+            column_unit: self.column_unit,
+            case_insensitive_keywords: self.case_insensitive_keywords,
+            split_keywords: self.split_keywords,
+            comment_sections: self.comment_sections.clone(),
+            shebang: self.shebang,
+            emit_shebang: self.emit_shebang,
+            transcode_utf16: self.transcode_utf16,
+            offside: self.offside,
+            emit_newlines: self.emit_newlines,
+            strict: self.strict,
+            limits: self.limits,
+            emit_unknown: self.emit_unknown,
+            xid_idents: self.xid_idents,
+            normalize_idents: self.normalize_idents,
+            char_quote: self.char_quote,
+            modes: self.modes.clone(),
+            transitions: self.transitions.clone(),
+            languages: self.languages.iter().map(|(name, lexer)| (name.clone(), lexer.config())).collect(),
+        };
+    }
+}
+
+impl Lexer {
+    // finds rules that conflict or can never match, the base rules first and then every mode
+    pub fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        validate_rules(self, &self.keywords, &self.sections, &self.symbols, &mut errors);
+        for mode in &self.modes {
+            validate_rules(self, &mode.keywords, &mode.sections, &mode.symbols, &mut errors);
+        }
+
+        let sections = self.sections.iter().chain(self.modes.iter().flat_map(|mode| &mode.sections)).collect::<Vec<&Section>>();
+        let nested = sections.iter().flat_map(|section| &section.nested);
+        for name in self.comment_sections.iter().chain(nested) {
+            if !sections.iter().any(|section| section.name == *name) {
+                errors.push(ConfigError::UnknownSection(name.clone()));
+            }
+        }
+
+        for transition in &self.transitions {
+            let target = match &transition.action {
+                ModeAction::Push(mode) => Some(mode),
+                ModeAction::Pop => None,
+            };
+            for mode in transition.mode.iter().chain(target) {
+                if !self.modes.iter().any(|existing| existing.name == *mode) {
+                    errors.push(ConfigError::UnknownMode(mode.clone()));
+                }
+            }
+        }
+        return errors;
+    }
+}
+
+#[cfg(any(feature = "json", feature = "toml"))]
+impl Lexer {
+    // fields missing from the document keep the defaults of Lexer::new. a config that fails
+    // validate with anything but warnings is rejected
+    pub fn from_config_str(document: &str, format: ConfigFormat) -> Result<Lexer, Box<dyn std::error::Error>> {
+        let config: LexerConfig = match format {
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => serde_json::from_str(document)?,
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => toml::from_str(document)?,
+        };
+
+        let lexer = Lexer::from(config);
+        let errors = lexer.validate().into_iter().filter(|error| !error.is_warning()).map(|error| error.to_string()).collect::<Vec<String>>();
+        if !errors.is_empty() {
+            return Err(errors.join("\n").into());
+        }
+        return Ok(lexer);
+    }
+
+    pub fn from_config_file(path: &str) -> Result<Lexer, Box<dyn std::error::Error>> {
+        let format = ConfigFormat::from_path(path).ok_or_else(|| format!("unknown config format: {}", path))?;
+        return Lexer::from_config_str(&std::fs::read_to_string(path)?, format);
+    }
+}
+
+impl From<LexerConfig> for Lexer {
+    fn from(config: LexerConfig) -> Lexer {
+        let mut lexer = Lexer::new(&config.keywords, &config.sections, &config.symbols, config.allow_whitespace);
+        lexer.whitespace = config.whitespace;
+        lexer.ident_symbols = config.ident_symbols;
+        lexer.allow_exponent = config.allow_exponent;
+        lexer.allow_numbers = config.allow_numbers;
+        lexer.allow_leading_dot = config.allow_leading_dot;
+        lexer.allow_trailing_dot = config.allow_trailing_dot;
+        lexer.decimal_separator = config.decimal_separator;
+        lexer.number_suffixes = config.number_suffixes;
+        lexer.integer_overflow = config.integer_overflow;
+        lexer.allow_digit_separators = config.allow_digit_separators;
+        lexer.pragma_prefix = config.pragma_prefix;
+        lexer.line_directive = config.line_directive;
+        lexer.keyword_tags = config.keyword_tags.into_iter().map(|(keyword, tag)| (keyword, KeywordTag::new(tag))).collect();
+        lexer.keyword_aliases = config.keyword_aliases;
+        lexer.contextual_keywords = config.contextual_keywords;
+        lexer.symbol_info = config.symbol_info;
+        lexer.symbol_pairs = config.symbol_pairs;
+        lexer.check_pairs = config.check_pairs;
+        lexer.emit_eof = config.emit_eof;
+        lexer.tab_width = config.tab_width;
+        lexer.expand_tabs = config.expand_tabs;
+        lexer.column_unit = config.column_unit;
+        lexer.case_insensitive_keywords = config.case_insensitive_keywords;
+        lexer.split_keywords = config.split_keywords;
+        lexer.comment_sections = config.comment_sections;
+        lexer.shebang = config.shebang;
+        lexer.emit_shebang = config.emit_shebang;
+        lexer.transcode_utf16 = config.transcode_utf16;
+        lexer.offside = config.offside;
+        lexer.emit_newlines = config.emit_newlines;
+        lexer.strict = config.strict;
+        lexer.limits = config.limits;
+        lexer.emit_unknown = config.emit_unknown;
+        lexer.xid_idents = config.xid_idents;
+        lexer.normalize_idents = config.normalize_idents;
+        lexer.char_quote = config.char_quote;
+        lexer.modes = config.modes;
+        lexer.transitions = config.transitions;
+        lexer.languages = config.languages.into_iter().map(|(name, config)| (name, Lexer::from(config))).collect();
+        return lexer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, LexerConfig, Section};
+
+    #[test]
+    fn config_test() {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[(';', "semi".to_string())], true);
+        lexer.emit_eof = true;
+        lexer.register_language("inner", Lexer::new(&["x".to_string()], &[], &[], false));
+
+        let config = lexer.config();
+        let rebuilt = Lexer::from(config.clone());
+        assert_eq!(rebuilt.config(), config);
+        assert_eq!(rebuilt.config_hash(), lexer.config_hash());
+        assert_eq!(LexerConfig::default().tab_width, 4);
+    }
+
+    #[test]
+    fn validate_test() {
+        use crate::{ConfigError, ModeAction};
+
+        let sections = [Section::new("string", "\"", "\""), Section::new("doc", "\"\"\"", "\"\"\""), Section::new("comment", "#", ""), Section::new("copy", "\"", "\"")];
+        let mut lexer = Lexer::new(&["let".to_string(), "let".to_string()], &sections, &[('#', "hash".to_string()), ('=', "equal".to_string()), ('=', "assign".to_string())], false);
+        lexer.comment_sections.push("line".to_string());
+        lexer.add_transition(None, "hash", ModeAction::Push("inner".to_string()));
+
+        let errors = lexer.validate();
+        assert_eq!(errors, [
+            ConfigError::DuplicateKeyword("let".to_string()),
+            ConfigError::SymbolIsSection('#', "comment".to_string()),
+            ConfigError::ConflictingSymbol('=', "equal".to_string(), "assign".to_string()),
+            ConfigError::PrefixSection("string".to_string(), "doc".to_string()),
+            ConfigError::EmptyEnd("comment".to_string()),
+            ConfigError::DuplicateSection("copy".to_string()),
+            ConfigError::PrefixSection("copy".to_string(), "doc".to_string()),
+            ConfigError::UnknownSection("line".to_string()),
+            ConfigError::UnknownMode("inner".to_string()),
+        ]);
+        assert!(errors[0].is_warning());
+        assert_eq!(errors[4].to_string(), "section comment has an empty end, give the section an end delimiter or use Section::line");
+        assert!(Lexer::new(&["let".to_string()], &[Section::line("comment", "#")], &[('=', "equal".to_string())], false).validate().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[(';', "semi".to_string())], false);
+        lexer.load_str("let a = \"b\";");
+
+        let tokens = lexer.tokenize()?;
+        let json = serde_json::to_string(&tokens)?;
+        assert_eq!(serde_json::from_str::<Vec<crate::Token>>(&json)?, tokens);
+
+        let config = serde_json::from_str::<LexerConfig>(r#"{"keywords": ["let"], "symbols": [[";", "semi"]]}"#)?;
+        assert!(!config.allow_whitespace);
+        assert_eq!(Lexer::from(config).config().keywords, ["let"]);
+        assert_eq!(serde_json::from_str::<LexerConfig>(&serde_json::to_string(&lexer.config())?)?, lexer.config());
+        return Ok(());
+    }
+
+    #[cfg(all(feature = "json", feature = "toml"))]
+    #[test]
+    fn config_format_test() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::ConfigFormat;
+
+        let toml = concat!(
+            "keywords = [\"let\"]\n",
+            "symbols = [[\"=\", \"equal\"]]\n",
+            "emit_eof = true\n",
+            "[[sections]]\n",
+            "name = \"string\"\n",
+            "start = \"'\"\n",
+            "end = \"'\"\n",
+        );
+        let mut lexer = Lexer::from_config_str(toml, ConfigFormat::Toml)?;
+        assert!(lexer.allow_exponent);
+        lexer.load_str("let a = 'b'");
+
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("let")?;
+        tokens[2].is_symbol("equal")?;
+        assert_eq!(tokens[3].is_section("string")?, "b");
+        tokens[4].is_eof()?;
+
+        let json = Lexer::from_config_str(r#"{"keywords": ["let"], "symbols": [["=", "equal"]], "emit_eof": true, "sections": [{"name": "string", "start": "'", "end": "'"}]}"#, ConfigFormat::Json)?;
+        assert_eq!(json.config(), lexer.config());
+
+        let path = std::env::temp_dir().join("lexin_config_test.json");
+        std::fs::write(&path, serde_json::to_string(&lexer.config())?)?;
+        assert_eq!(Lexer::from_config_file(path.to_str().ok_or("path")?)?.config(), lexer.config());
+        assert_eq!(ConfigFormat::from_path("lexer.toml"), Some(ConfigFormat::Toml));
+        assert!(Lexer::from_config_file("lexer.yaml").is_err());
+        return Ok(());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{LexError, Loc, Token};
+
+// renders the message with the offending line of source and a caret under the column, width is
+// the amount of columns to underline and is at least one
+pub fn render(source: &str, file: &str, loc: Loc, width: usize, message: &str) -> String {
+    let line = loc.line as usize;
+    let column = loc.col as usize;
+    let text = source.lines().nth(line.saturating_sub(1)).unwrap_or_default();
+    let gutter = line.to_string().len();
+
+    let mut rendered = format!("error: {}\n", message);
+    rendered.push_str(&format!("{:>gutter$}--> {}:{}:{}\n", "", file, line, column, gutter = gutter));
+    rendered.push_str(&format!("{:>gutter$} |\n", "", gutter = gutter));
+    rendered.push_str(&format!("{} | {}\n", line, text));
+
+    // tabs are kept so the caret lines up with the source in the terminal
+    let padding = text.chars()
+        .take(column.saturating_sub(1))
+        .map(|character| if character == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    rendered.push_str(&format!("{:>gutter$} | {}{}\n", "", padding, "^".repeat(width.max(1)), gutter = gutter));
+    return rendered;
+}
+
+pub fn render_error(source: &str, file: &str, error: &LexError) -> String {
+    return render(source, file, error.loc(), 1, &error.message());
+}
+
+// for failed expectations such as Token::is_keyword, the message is usually the error it returned
+pub fn render_token(source: &str, file: &str, token: &Token, message: &str) -> String {
+    return render(source, file, token.loc(), 1, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Escapes, FileId, Lexer, Section};
+
+    #[test]
+    fn render_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut section = Section::new("string", "\"", "\"");
+        section.escapes = Escapes::Decoded('\\');
+
+        let mut lexer = Lexer::new(&["let".to_string()], &[section], &[], false);
+        let source = "let a\n\tlet \"b\\q\"";
+        lexer.load_str(source);
+
+        let error = lexer.tokenize_lossy().1.remove(0);
+        assert_eq!(
+            render_error(source, "main.src", &error),
+            "error: invalid escape sequence: \\q\n --> main.src:2:8\n  |\n2 | \tlet \"b\\q\"\n  | \t      ^\n",
+        );
+
+        let tokens = lexer.tokenize_lossy().0;
+        let expected = tokens[1].is_keyword("let").unwrap_err().to_string();
+        let rendered = render_token(source, "main.src", &tokens[1], &expected);
+        assert!(rendered.starts_with("error: expected keyword"));
+        assert!(render(source, "main.src", Loc::new(FileId::default()), 3, "bad").ends_with("1 | let a\n  | ^^^\n"));
+        return Ok(());
+    }
+}
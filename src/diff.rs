@@ -0,0 +1,91 @@
+use crate::{Loc, Token};
+
+use std::ops::Range;
+
+// a change between two lexes of the same file, old ranges index the old tokens and new ranges the
+// new tokens. tokens are compared without their locs, so text moving down a line is no change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenEdit {
+    // new tokens inserted before the old token at the index
+    Insert(usize, Range<usize>),
+    // old tokens removed, the new tokens continue at the index
+    Delete(Range<usize>, usize),
+    Replace(Range<usize>, Range<usize>),
+}
+
+fn without_locs(tokens: &[Token]) -> Vec<Token> {
+    return tokens.iter().map(|token| token.clone().map_loc(&|_| Loc::default())).collect();
+}
+
+fn push_edit(edits: &mut Vec<TokenEdit>, old: Range<usize>, new: Range<usize>) {
+    match (old.is_empty(), new.is_empty()) {
+        (true, true) => {},
+        (true, false) => edits.push(TokenEdit::Insert(old.start, new)),
+        (false, true) => edits.push(TokenEdit::Delete(old, new.start)),
+        (false, false) => edits.push(TokenEdit::Replace(old, new)),
+    }
+}
+
+// the fewest tokens inserted and deleted to turn old into new, with a deletion next to an insertion
+// reported as a replace. the common start and end are skipped first, so only the changed region
+// is compared token by token
+pub fn diff_tokens(old: &[Token], new: &[Token]) -> Vec<TokenEdit> {
+    let (old, new) = (without_locs(old), without_locs(new));
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    // common[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let width = b.len() + 1;
+    let mut common = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i * width + j] = if a[i] == b[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut old_start, mut new_start) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            push_edit(&mut edits, prefix + old_start..prefix + i, prefix + new_start..prefix + j);
+            i += 1;
+            j += 1;
+            (old_start, new_start) = (i, j);
+        } else if j == b.len() || (i < a.len() && common[(i + 1) * width + j] >= common[i * width + j + 1]) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    push_edit(&mut edits, prefix + old_start..prefix + i, prefix + new_start..prefix + j);
+    return edits;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{diff_tokens, Lexer, TokenEdit};
+
+    #[test]
+    fn diff_tokens_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string()), (';', "semicolon".to_string())], false);
+        lexer.load_str("let a = 1;\nlet b = 2;\nlet c = 3;");
+        let old = lexer.tokenize()?;
+
+        lexer.load_str("\nlet a = 1;\nlet b = 20;\nlet c = 3;");
+        let new = lexer.tokenize()?;
+        assert_eq!(diff_tokens(&old, &new), vec![TokenEdit::Replace(8..9, 8..9)]);
+
+        lexer.load_str("let a = 1;\nlet c = 3; x");
+        let new = lexer.tokenize()?;
+        assert_eq!(diff_tokens(&old, &new), vec![TokenEdit::Delete(6..11, 6), TokenEdit::Insert(15, 10..11)]);
+        assert_eq!(diff_tokens(&old, &old), vec![]);
+        assert_eq!(diff_tokens(&[], &old), vec![TokenEdit::Insert(0, 0..15)]);
+        return Ok(());
+    }
+}
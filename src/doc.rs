@@ -0,0 +1,62 @@
+use crate::Token;
+
+// the doc comments written directly before the token at target, in source order
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocComment {
+    pub target: usize,
+    pub lines: Vec<String>,
+}
+
+// associates comments of the doc kinds with the next token that is not a comment. other comments
+// in between are skipped, doc comments with nothing but Eof after them are dropped
+pub fn doc_comments(tokens: &[Token], doc_kinds: &[&str]) -> Vec<DocComment> {
+    let mut docs = Vec::new();
+    let mut lines = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Comment(kind, text, _) => {
+                if doc_kinds.contains(&kind.as_str()) {
+                    lines.push(text.clone());
+                }
+            },
+            Token::Eof(_) => break,
+            Token::Indent(_) | Token::Dedent(_) | Token::Newline(_) => {},
+            _ => {
+                if !lines.is_empty() {
+                    docs.push(DocComment {
+                        target: index,
+                        lines: std::mem::take(&mut lines),
+                    });
+                }
+            },
+        }
+    }
+    return docs;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{doc_comments, Lexer, Section};
+
+    #[test]
+    fn doc_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &["fn".to_string()],
+            &[Section::new("line", "//", "\n"), Section::new("doc", "///", "\n"), Section::new("doc", "/**", "*/")],
+            &[],
+            false,
+        );
+        lexer.comment_sections = vec!["line".to_string(), "doc".to_string()];
+        lexer.load_str("/// adds\n// note\n/** two */\nfn add\nfn sub\n/// dangling\n");
+
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[1].is_comment("line")?, " note");
+
+        let docs = doc_comments(&tokens, &["doc"]);
+        assert_eq!(docs.len(), 1);
+        tokens[docs[0].target].is_keyword("fn")?;
+        assert_eq!(docs[0].lines, [" adds", " two "]);
+        assert_eq!(docs[0].target, 3);
+        return Ok(());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{LexError, Lexer, Loc, TokenFile, TokenRef};
+
+use std::borrow::Cow;
+
+// a section body lexed by the lexer registered for its language, token is the index of the
+// section in the enclosing file and the embedded locations are relative to the enclosing source
+#[derive(Debug, Clone)]
+pub struct Embedded {
+    pub language: String,
+    pub token: usize,
+    pub file: TokenFile,
+}
+
+impl Lexer {
+    fn embedded_language(&self, name: &str) -> Option<&str> {
+        return self.sections.iter()
+            .chain(self.modes.iter().flat_map(|mode| &mode.sections))
+            .find(|section| section.name == name && section.language.is_some())
+            .and_then(|section| section.language.as_deref());
+    }
+
+    pub(crate) fn lex_file(&self, source: &str, origin: Loc) -> Result<TokenFile, LexError> {
+        let (scanned, _) = self.scan_until(source, source.len(), &mut self.new_run(), &mut origin.clone(), true)?;
+        let tokens = scanned.tokens;
+
+        let mut embedded = Vec::new();
+        let mut mapped = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let TokenRef::Section(name, Cow::Borrowed(body), loc) = token else {
+                mapped.extend(self.finish_token(token));
+                continue;
+            };
+            let Some(language) = self.embedded_language(name) else {
+                mapped.extend(self.finish_token(token));
+                continue;
+            };
+            let (_, lexer) = self.languages.iter()
+                .find(|(registered, _)| registered == language)
+                .ok_or_else(|| LexError::UnknownLanguage(language.to_string(), loc))?;
+
+            // embedded bodies are never decoded, so the body always borrows from source
+            let mut start = origin;
+            self.advance(&mut start, &source[..body.as_ptr() as usize - source.as_ptr() as usize]);
+
+            // a section dropped by map_token takes its embedded file with it
+            let file = lexer.lex_file(body, start)?;
+            if let Some(section) = self.finish_token(token) {
+                embedded.push(Embedded {
+                    language: language.to_string(),
+                    token: mapped.len(),
+                    file,
+                });
+                mapped.push(section);
+            }
+        }
+
+        return Ok(TokenFile::nested(mapped, source.to_string(), origin.line as usize, self.tab_width, embedded));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section, Token};
+
+    #[test]
+    fn embed_test() -> Result<(), Box<dyn std::error::Error>> {
+        let css = Lexer::new(&["color".to_string()], &[], &[(':', "colon".to_string()), (';', "semicolon".to_string())], false);
+
+        let mut html = Lexer::new(&["div".to_string()], &[Section::new("style", "style=\"", "\"").embed("css")], &[('<', "lt".to_string()), ('>', "gt".to_string())], false);
+        html.register_language("css", css);
+        html.load_str("<div style=\"color: red;\n  color: \\\"blue\\\";\">\n<div>");
+
+        let file = html.tokenize_file()?;
+        assert_eq!(file.embedded().len(), 1);
+
+        let embedded = &file.embedded()[0];
+        assert_eq!(embedded.language, "css");
+        file[embedded.token].is_section("style")?;
+
+        let inner = &embedded.file;
+        inner[0].is_keyword("color")?;
+        assert_eq!(inner[0].loc().line, 1);
+        assert!(inner[0].loc().col as usize > "<div style=\"".len());
+        inner[4].is_keyword("color")?;
+        assert_eq!(inner[4].loc().line, 2);
+        assert_eq!(inner.line(2), Some("  color: \\\"blue\\\";"));
+        assert_eq!(inner.tokens_on_line(2).count(), inner.len() - 4);
+
+        html.sections[0] = Section::new("style", "style=\"", "\"").embed("js");
+        html.load_str("<div style=\"a\">");
+        assert!(html.tokenize_file().is_err());
+
+        let mut plain = Lexer::new(&[], &[Section::new("style", "style=\"", "\"")], &[], false);
+        plain.load_str("style=\"a\"");
+        assert!(plain.tokenize_file()?.embedded().is_empty());
+        assert!(matches!(plain.tokenize_file()?[0], Token::Section(..)));
+        return Ok(());
+    }
+}
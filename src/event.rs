@@ -0,0 +1,57 @@
+use crate::{Loc, TokenRef};
+
+// what the lexer reports while it scans, see Lexer::debug_hook. tokens are reported as they are
+// scanned, before map_token, and lexers that scan ahead like PushLexer may report a token twice
+#[derive(Debug, Clone, Copy)]
+pub enum LexEvent<'a> {
+    Token(&'a TokenRef<'a>),
+    // the mode entered or left and the loc of the token that triggered the transition
+    PushMode(&'a str, Loc),
+    PopMode(&'a str, Loc),
+}
+
+pub type DebugFn = for<'a> fn(LexEvent<'a>);
+
+// hands event to the hook and, with the tracing feature, to tracing
+pub(crate) fn report(hook: Option<DebugFn>, event: LexEvent) {
+    #[cfg(feature = "tracing")]
+    match event {
+        LexEvent::Token(token) => tracing::trace!(?token, "token"),
+        LexEvent::PushMode(mode, loc) => tracing::debug!(mode, %loc, "push mode"),
+        LexEvent::PopMode(mode, loc) => tracing::debug!(mode, %loc, "pop mode"),
+    }
+
+    if let Some(hook) = hook {
+        hook(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LexEvent, LexMode, Lexer, ModeAction};
+
+    use std::sync::Mutex;
+
+    static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    #[test]
+    fn debug_hook_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('{', "open".to_string()), ('}', "close".to_string())], false);
+        lexer.modes.push(LexMode::new("inner", &["x".to_string()], &[], &[('}', "close".to_string())]));
+        lexer.add_transition(None, "open", ModeAction::Push("inner".to_string()));
+        lexer.add_transition(Some("inner"), "close", ModeAction::Pop);
+        lexer.debug_hook = Some(|event| {
+            let event = match event {
+                LexEvent::Token(token) => token.to_owned().as_string(),
+                LexEvent::PushMode(mode, loc) => format!("push {} {}", mode, loc.col),
+                LexEvent::PopMode(mode, loc) => format!("pop {} {}", mode, loc.col),
+            };
+            EVENTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(event);
+        });
+
+        lexer.load_str("{ x }");
+        lexer.tokenize()?;
+        assert_eq!(*EVENTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()), ["push inner 1", "{", "x", "pop inner 5", "}"]);
+        return Ok(());
+    }
+}
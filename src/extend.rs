@@ -0,0 +1,120 @@
+use crate::{ConfigError, Lexer};
+
+// pushes the items of overlay missing from base
+fn join<T: Clone + PartialEq>(base: &mut Vec<T>, overlay: &[T]) {
+    for item in overlay {
+        if !base.contains(item) {
+            base.push(item.clone());
+        }
+    }
+}
+
+impl Lexer {
+    // adds the rules of overlay on top of these, like a dialect adding to a shared core. keywords and
+    // other lists are joined without duplicates, while a symbol, section, mode or rule that overlay
+    // defines differently replaces the one here and is reported. options like whitespace are kept
+    pub fn extend(&mut self, overlay: &Lexer) -> Vec<ConfigError> {
+        let mut conflicts = Vec::new();
+        join(&mut self.keywords, &overlay.keywords);
+        join(&mut self.contextual_keywords, &overlay.contextual_keywords);
+        join(&mut self.keyword_tags, &overlay.keyword_tags);
+        join(&mut self.keyword_aliases, &overlay.keyword_aliases);
+        join(&mut self.comment_sections, &overlay.comment_sections);
+        join(&mut self.number_suffixes, &overlay.number_suffixes);
+        join(&mut self.symbol_pairs, &overlay.symbol_pairs);
+        join(&mut self.transitions, &overlay.transitions);
+        join(&mut self.ident_symbols, &overlay.ident_symbols);
+
+        for (symbol, name) in &overlay.symbols {
+            match self.symbols.iter_mut().find(|(existing, _)| existing == symbol) {
+                Some((_, existing)) if existing != name => {
+                    conflicts.push(ConfigError::ConflictingSymbol(*symbol, existing.clone(), name.clone()));
+                    *existing = name.clone();
+                },
+                Some(_) => {},
+                None => self.symbols.push((*symbol, name.clone())),
+            }
+        }
+
+        for (name, info) in &overlay.symbol_info {
+            self.symbol_info.retain(|(existing, _)| existing != name);
+            self.symbol_info.push((name.clone(), *info));
+        }
+
+        for section in &overlay.sections {
+            match self.sections.iter_mut().find(|existing| existing.name == section.name) {
+                Some(existing) if existing != section => {
+                    conflicts.push(ConfigError::RedefinedSection(section.name.clone()));
+                    *existing = section.clone();
+                },
+                Some(_) => {},
+                None => self.sections.push(section.clone()),
+            }
+        }
+
+        for mode in &overlay.modes {
+            match self.modes.iter_mut().find(|existing| existing.name == mode.name) {
+                Some(existing) if existing != mode => {
+                    conflicts.push(ConfigError::RedefinedMode(mode.name.clone()));
+                    *existing = mode.clone();
+                },
+                Some(_) => {},
+                None => self.modes.push(mode.clone()),
+            }
+        }
+
+        // matchers can't be compared, a rule of the same name is always a redefinition
+        for rule in &overlay.rules {
+            if self.rules.iter().any(|existing| existing.name == rule.name) {
+                conflicts.push(ConfigError::RedefinedRule(rule.name.clone()));
+                self.rules.retain(|existing| existing.name != rule.name);
+            }
+            self.rules.push(rule.clone());
+        }
+
+        for (name, lexer) in &overlay.languages {
+            self.register_language(name, lexer.clone());
+        }
+        return conflicts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConfigError, Escapes, Lexer, Section};
+
+    #[test]
+    fn extend_test() -> Result<(), Box<dyn std::error::Error>> {
+        let core = Lexer::new(
+            &["let".to_string(), "if".to_string()],
+            &[Section::new("string", "\"", "\"")],
+            &[('=', "equal".to_string()), ('+', "plus".to_string())],
+            false,
+        );
+        let overlay = Lexer::new(
+            &["if".to_string(), "match".to_string()],
+            &[Section::new("string", "\"", "\"").escapes(Escapes::Off), Section::new("char", "'", "'")],
+            &[('+', "concat".to_string()), ('|', "pipe".to_string())],
+            false,
+        );
+
+        let mut dialect = core.clone();
+        let conflicts = dialect.extend(&overlay);
+        assert_eq!(conflicts, [ConfigError::ConflictingSymbol('+', "plus".to_string(), "concat".to_string()), ConfigError::RedefinedSection("string".to_string())]);
+        assert_eq!(dialect.keywords, ["let", "if", "match"]);
+        assert_eq!(dialect.symbols.len(), 3);
+        assert_eq!(dialect.sections.len(), 2);
+        assert_eq!(dialect.sections[0].escapes, Escapes::Off);
+
+        dialect.load_str("match a + b | 'c' \"d\"");
+        let tokens = dialect.tokenize()?;
+        tokens[0].is_keyword("match")?;
+        assert_eq!(tokens[2].name(), Some("concat"));
+        assert_eq!(tokens[5].is_section("char")?, "c");
+
+        // extending again with the same rules changes nothing
+        assert!(dialect.clone().extend(&overlay).is_empty());
+        assert_eq!(core.keywords.len(), 2);
+        return Ok(());
+    }
+}
@@ -0,0 +1,187 @@
+// a c interface to the lexer. every pointer handed out is owned by the caller and released with
+// the matching free function, every pointer passed in must come from this module or be a valid
+// nul terminated string. build a library with `cargo rustc --release --features ffi --crate-type cdylib`
+// and generate the header with `cbindgen --config cbindgen.toml`
+#![allow(clippy::missing_safety_doc)]
+
+use crate::{Lexer, Section, Token, TokenKind};
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+pub struct LexinLexer {
+    lexer: Lexer,
+    error: Option<CString>,
+}
+
+pub struct LexinTokens {
+    tokens: Vec<Token>,
+    // nul terminated copies of as_string and name, so they can be handed out as c strings
+    texts: Vec<CString>,
+    names: Vec<Option<CString>>,
+}
+
+// interior nul bytes would end the string early, they are dropped instead
+fn c_string(text: &str) -> CString {
+    return CString::new(text.replace('\0', "")).expect("nul bytes are removed");
+}
+
+unsafe fn str_arg<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+    return CStr::from_ptr(string).to_str().ok();
+}
+
+#[no_mangle]
+pub extern "C" fn lexin_lexer_new(allow_whitespace: bool) -> *mut LexinLexer {
+    return Box::into_raw(Box::new(LexinLexer {
+        lexer: Lexer::new(&[], &[], &[], allow_whitespace),
+        error: None,
+    }));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lexin_free(lexer: *mut LexinLexer) {
+    if !lexer.is_null() {
+        drop(Box::from_raw(lexer));
+    }
+}
+
+// the add functions return false when an argument is null or not utf-8
+#[no_mangle]
+pub unsafe extern "C" fn lexin_add_keyword(lexer: *mut LexinLexer, keyword: *const c_char) -> bool {
+    let (Some(lexer), Some(keyword)) = (lexer.as_mut(), str_arg(keyword)) else {
+        return false;
+    };
+    lexer.lexer.keywords.push(keyword.to_string());
+    return true;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lexin_add_symbol(lexer: *mut LexinLexer, symbol: u32, name: *const c_char) -> bool {
+    let (Some(lexer), Some(symbol), Some(name)) = (lexer.as_mut(), char::from_u32(symbol), str_arg(name)) else {
+        return false;
+    };
+    lexer.lexer.symbols.push((symbol, name.to_string()));
+    return true;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lexin_add_section(lexer: *mut LexinLexer, name: *const c_char, start: *const c_char, end: *const c_char) -> bool {
+    let (Some(lexer), Some(name), Some(start), Some(end)) = (lexer.as_mut(), str_arg(name), str_arg(start), str_arg(end)) else {
+        return false;
+    };
+    lexer.lexer.sections.push(Section::new(name, start, end));
+    return true;
+}
+
+// lexes length bytes of input, null on failure with the reason left in lexin_error
+#[no_mangle]
+pub unsafe extern "C" fn lexin_tokenize(lexer: *mut LexinLexer, input: *const u8, length: usize) -> *mut LexinTokens {
+    let Some(lexer) = lexer.as_mut() else {
+        return ptr::null_mut();
+    };
+
+    let bytes = if length == 0 { &[][..] } else { std::slice::from_raw_parts(input, length) };
+    let tokens = std::str::from_utf8(bytes).map_err(|err| err.to_string()).and_then(|input| {
+        lexer.lexer.load_str(input);
+        return lexer.lexer.tokenize().map_err(|err| err.to_string());
+    });
+
+    return match tokens {
+        Ok(tokens) => {
+            lexer.error = None;
+            Box::into_raw(Box::new(LexinTokens {
+                texts: tokens.iter().map(|token| c_string(&token.as_string())).collect(),
+                names: tokens.iter().map(|token| token.name().map(c_string)).collect(),
+                tokens,
+            }))
+        },
+        Err(err) => {
+            lexer.error = Some(c_string(&err));
+            ptr::null_mut()
+        },
+    };
+}
+
+// the error of the last failed lexin_tokenize, null after a successful one. valid until the next call
+#[no_mangle]
+pub unsafe extern "C" fn lexin_error(lexer: *const LexinLexer) -> *const c_char {
+    return lexer.as_ref().and_then(|lexer| lexer.error.as_ref()).map_or(ptr::null(), |error| error.as_ptr());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lexin_tokens_free(tokens: *mut LexinTokens) {
+    if !tokens.is_null() {
+        drop(Box::from_raw(tokens));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lexin_tokens_len(tokens: *const LexinTokens) -> usize {
+    return tokens.as_ref().map_or(0, |tokens| tokens.tokens.len());
+}
+
+// the accessors return Eof, null or 0 for an index out of range
+#[no_mangle]
+pub unsafe extern "C" fn lexin_token_kind(tokens: *const LexinTokens, index: usize) -> TokenKind {
+    return tokens.as_ref().and_then(|tokens| tokens.tokens.get(index)).map_or(TokenKind::Eof, Token::kind);
+}
+
+// the text of the token as given by as_string, valid until the tokens are freed
+#[no_mangle]
+pub unsafe extern "C" fn lexin_token_text(tokens: *const LexinTokens, index: usize) -> *const c_char {
+    return tokens.as_ref().and_then(|tokens| tokens.texts.get(index)).map_or(ptr::null(), |text| text.as_ptr());
+}
+
+// the section, symbol, custom or comment name, null for other tokens
+#[no_mangle]
+pub unsafe extern "C" fn lexin_token_name(tokens: *const LexinTokens, index: usize) -> *const c_char {
+    return tokens.as_ref().and_then(|tokens| tokens.names.get(index)?.as_ref()).map_or(ptr::null(), |name| name.as_ptr());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lexin_token_line(tokens: *const LexinTokens, index: usize) -> u32 {
+    return tokens.as_ref().and_then(|tokens| tokens.tokens.get(index)).map_or(0, |token| token.loc().line);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lexin_token_col(tokens: *const LexinTokens, index: usize) -> u32 {
+    return tokens.as_ref().and_then(|tokens| tokens.tokens.get(index)).map_or(0, |token| token.loc().col);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ffi_test() -> Result<(), Box<dyn std::error::Error>> {
+        unsafe {
+            let lexer = lexin_lexer_new(false);
+            assert!(lexin_add_keyword(lexer, c"let".as_ptr()));
+            assert!(lexin_add_symbol(lexer, '=' as u32, c"equal".as_ptr()));
+            assert!(lexin_add_section(lexer, c"string".as_ptr(), c"\"".as_ptr(), c"\"".as_ptr()));
+            assert!(!lexin_add_keyword(lexer, ptr::null()));
+
+            let input = "let a = \"b\"";
+            let tokens = lexin_tokenize(lexer, input.as_ptr(), input.len());
+            assert!(!tokens.is_null());
+            assert!(lexin_error(lexer).is_null());
+            assert_eq!(lexin_tokens_len(tokens), 4);
+            assert_eq!(lexin_token_kind(tokens, 0), TokenKind::Keyword);
+            assert_eq!(CStr::from_ptr(lexin_token_name(tokens, 2)).to_str()?, "equal");
+            assert_eq!(CStr::from_ptr(lexin_token_text(tokens, 3)).to_str()?, "b");
+            assert_eq!(lexin_token_col(tokens, 3), 11);
+            assert!(lexin_token_name(tokens, 1).is_null());
+            assert!(lexin_token_text(tokens, 4).is_null());
+            lexin_tokens_free(tokens);
+
+            let tokens = lexin_tokenize(lexer, [0xff].as_ptr(), 1);
+            assert!(tokens.is_null());
+            assert!(!lexin_error(lexer).is_null());
+            lexin_free(lexer);
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Escapes, IdentSymbol, IntegerOverflow, LexerConfig, Section};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+// configs are put together from a fixed vocabulary, so the fuzzer spends its time on lexers that
+// look like real ones rather than on keywords nobody will ever type
+const KEYWORDS: [&str; 8] = ["if", "else", "let", "fn", "return", "true", "false", "null"];
+const SYMBOLS: &str = "()[]{};,.=+-*/<>!&|:#";
+const SECTIONS: [(&str, &str); 5] = [("\"", "\""), ("'", "'"), ("/*", "*/"), ("`", "`"), ("<<", ">>")];
+const LINE_SECTIONS: [&str; 2] = ["//", "#"];
+
+fn section<'a>(u: &mut Unstructured<'a>, mut section: Section) -> Result<Section> {
+    section.escapes = match u.int_in_range(0..=3)? {
+        0 => Escapes::Off,
+        1 => Escapes::Kept('\\'),
+        2 => Escapes::Stripped('\\'),
+        _ => Escapes::Decoded('\\'),
+    };
+    section.allow_newlines = u.arbitrary()?;
+    section.keep_delimiters = u.arbitrary()?;
+    return Ok(section);
+}
+
+impl<'a> Arbitrary<'a> for LexerConfig {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<LexerConfig> {
+        let mut config = LexerConfig::default();
+        for keyword in KEYWORDS {
+            if u.arbitrary()? {
+                config.keywords.push(keyword.to_string());
+            }
+        }
+        for (index, symbol) in SYMBOLS.chars().enumerate() {
+            if u.arbitrary()? {
+                config.symbols.push((symbol, format!("symbol{}", index)));
+            }
+        }
+        for (index, (start, end)) in SECTIONS.iter().enumerate() {
+            if u.arbitrary()? {
+                config.sections.push(section(u, Section::new(&format!("section{}", index), start, end))?);
+            }
+        }
+        for (index, start) in LINE_SECTIONS.iter().enumerate() {
+            if u.arbitrary()? {
+                config.sections.push(section(u, Section::line(&format!("line{}", index), start))?);
+            }
+        }
+
+        if u.arbitrary()? {
+            config.ident_symbols.push(('-', *u.choose(&[IdentSymbol::Inner, IdentSymbol::Trailing])?));
+        }
+        config.allow_whitespace = u.arbitrary()?;
+        config.allow_exponent = u.arbitrary()?;
+        config.allow_digit_separators = u.arbitrary()?;
+        config.allow_numbers = u.arbitrary()?;
+        config.allow_leading_dot = u.arbitrary()?;
+        config.allow_trailing_dot = u.arbitrary()?;
+        config.decimal_separator = *u.choose(&['.', ','])?;
+        config.case_insensitive_keywords = u.arbitrary()?;
+        config.split_keywords = u.arbitrary()?;
+        config.offside = u.arbitrary()?;
+        config.emit_newlines = u.arbitrary()?;
+        config.strict = u.arbitrary()?;
+        config.emit_unknown = u.arbitrary()?;
+        config.expand_tabs = u.arbitrary()?;
+        config.char_quote = u.arbitrary::<bool>()?.then_some('\'');
+        config.integer_overflow = *u.choose(&[IntegerOverflow::Float, IntegerOverflow::Error, IntegerOverflow::BigInteger])?;
+        return Ok(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::roundtrip_property;
+    use crate::LexerConfig;
+
+    use arbitrary::{Arbitrary, Unstructured};
+
+    const INPUTS: [&str; 6] = [
+        "let x = 1.5e3;\n",
+        "if (a) { return \"s\\\"t\" } // c\n",
+        "f(`a\nb`) /* x */ 'c' <<y>>",
+        "\tindented\n  mixed #comment\n",
+        "\"open\nline",
+        "1_000 .5 0x1f é ∑ \\",
+    ];
+
+    #[test]
+    fn arbitrary_config_test() -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = (0..4096u32).map(|index| (index.wrapping_mul(2654435761) >> 13) as u8).collect::<Vec<u8>>();
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..32 {
+            let config = LexerConfig::arbitrary(&mut u)?;
+            for input in INPUTS {
+                roundtrip_property(&config, input)?;
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{Lexer, Loc, Token};
+
+use std::fmt;
+
+// a token or a delimited group of them, see group_tokens
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenTree {
+    Token(Token),
+    Group(TokenGroup),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenGroup {
+    pub open: Token,
+    pub close: Token,
+    pub children: Vec<TokenTree>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupError {
+    // an open delimiter that is never closed, with the symbol name of the open delimiter
+    Unclosed(String, Loc),
+    // a close delimiter without a matching open one
+    Unopened(String, Loc),
+}
+
+impl GroupError {
+    pub fn loc(&self) -> Loc {
+        return match self {
+            GroupError::Unclosed(_, loc) | GroupError::Unopened(_, loc) => *loc,
+        };
+    }
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            GroupError::Unclosed(name, loc) => write!(f, "{}: unclosed delimiter: {}", loc, name),
+            GroupError::Unopened(name, loc) => write!(f, "{}: unexpected close delimiter: {}", loc, name),
+        };
+    }
+}
+
+impl std::error::Error for GroupError {}
+
+impl TokenTree {
+    // the loc of the token or of the close delimiter of the group
+    pub fn loc(&self) -> Loc {
+        return match self {
+            TokenTree::Token(token) => token.loc(),
+            TokenTree::Group(group) => group.close.loc(),
+        };
+    }
+}
+
+impl TokenGroup {
+    // the name of the open delimiter symbol
+    pub fn delimiter(&self) -> &str {
+        return self.open.name().unwrap_or_default();
+    }
+}
+
+// pairs the open and close symbols named in pairs into a tree of groups. every unbalanced
+// delimiter is reported, a close delimiter that matches an outer group closes the groups inside it
+pub fn group_tokens(tokens: &[Token], pairs: &[(&str, &str)]) -> Result<Vec<TokenTree>, Vec<GroupError>> {
+    let mut stack: Vec<(Token, Vec<TokenTree>)> = Vec::new();
+    let mut root = Vec::new();
+    let mut errors = Vec::new();
+
+    for token in tokens {
+        let Token::Symbol(_, name, loc) = token else {
+            stack.last_mut().map_or(&mut root, |(_, children)| children).push(TokenTree::Token(token.clone()));
+            continue;
+        };
+
+        if pairs.iter().any(|(open, _)| open == name) {
+            stack.push((token.clone(), Vec::new()));
+            continue;
+        }
+
+        let closes = |open: &Token| pairs.iter().any(|pair| Some(pair.0) == open.name() && pair.1 == name);
+        let Some(depth) = stack.iter().rposition(|(open, _)| closes(open)) else {
+            if pairs.iter().any(|(_, close)| close == name) {
+                errors.push(GroupError::Unopened(name.clone(), *loc));
+            } else {
+                stack.last_mut().map_or(&mut root, |(_, children)| children).push(TokenTree::Token(token.clone()));
+            }
+            continue;
+        };
+
+        for (open, _) in stack.drain(depth + 1..) {
+            errors.push(GroupError::Unclosed(open.name().unwrap_or_default().to_string(), open.loc()));
+        }
+
+        let (open, children) = stack.pop().expect("depth is inside the stack");
+        let group = TokenTree::Group(TokenGroup {
+            open,
+            close: token.clone(),
+            children,
+        });
+        stack.last_mut().map_or(&mut root, |(_, children)| children).push(group);
+    }
+
+    for (open, _) in stack {
+        errors.push(GroupError::Unclosed(open.name().unwrap_or_default().to_string(), open.loc()));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok(root);
+}
+
+impl Lexer {
+    // open and close are symbol names, like group_tokens takes them. editors use the pairs for
+    // bracket matching and auto closing, with check_pairs set tokenize also checks the balance
+    pub fn add_symbol_pair(&mut self, open: &str, close: &str) {
+        self.symbol_pairs.push((open.to_string(), close.to_string()));
+    }
+
+    // every paired symbol in tokens without its partner
+    pub(crate) fn unbalanced(&self, tokens: &[Token]) -> Vec<GroupError> {
+        let pairs = self.symbol_pairs.iter().map(|(open, close)| (open.as_str(), close.as_str())).collect::<Vec<(&str, &str)>>();
+        return group_tokens(tokens, &pairs).err().unwrap_or_default();
+    }
+}
+
+impl Token {
+    // the character of the symbol paired with this one in lexer, what an editor inserts to close an
+    // open symbol or jumps to from either of them
+    pub fn matching_symbol(&self, lexer: &Lexer) -> Option<char> {
+        let Token::Symbol(_, name, _) = self else {
+            return None;
+        };
+        let partner = lexer.symbol_pairs.iter().find_map(|(open, close)| match name {
+            name if name == open => Some(close),
+            name if name == close => Some(open),
+            _ => None,
+        })?;
+        return lexer.symbols.iter()
+            .chain(lexer.modes.iter().flat_map(|mode| &mode.symbols))
+            .find(|(_, symbol)| symbol == partner)
+            .map(|(character, _)| *character);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{group_tokens, GroupError, LexError, Lexer, Loc, TokenTree};
+
+    const PAIRS: [(&str, &str); 2] = [("openparen", "closeparen"), ("openbrace", "closebrace")];
+
+    #[test]
+    fn group_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &[],
+            &[],
+            &[('(', "openparen".to_string()), (')', "closeparen".to_string()), ('{', "openbrace".to_string()), ('}', "closebrace".to_string()), (',', "comma".to_string())],
+            false,
+        );
+
+        lexer.load_str("f(a, {b}) c");
+        let trees = group_tokens(&lexer.tokenize()?, &PAIRS).map_err(|errors| errors[0].to_string())?;
+        assert_eq!(trees.len(), 3);
+        let TokenTree::Group(call) = &trees[1] else {
+            return Err("expected a group".into());
+        };
+        assert_eq!(call.delimiter(), "openparen");
+        assert_eq!(call.children.len(), 3);
+        assert!(matches!(&call.children[2], TokenTree::Group(block) if block.delimiter() == "openbrace" && block.children.len() == 1));
+
+        lexer.load_str("(a {b) }\n)");
+        let errors = group_tokens(&lexer.tokenize()?, &PAIRS).unwrap_err();
+        assert_eq!(errors, vec![
+            GroupError::Unclosed("openbrace".to_string(), Loc { line: 1, col: 4, offset: 3, ..Loc::default() }),
+            GroupError::Unopened("closebrace".to_string(), Loc { line: 1, col: 8, offset: 7, ..Loc::default() }),
+            GroupError::Unopened("closeparen".to_string(), Loc { line: 2, col: 1, offset: 9, ..Loc::default() }),
+        ]);
+        return Ok(());
+    }
+
+    #[test]
+    fn symbol_pair_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('(', "openparen".to_string()), (')', "closeparen".to_string()), (',', "comma".to_string())], false);
+        lexer.add_symbol_pair("openparen", "closeparen");
+        lexer.load_str("f(a, (b)");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[1].matching_symbol(&lexer), Some(')'));
+        assert_eq!(tokens[6].matching_symbol(&lexer), Some('('));
+        assert_eq!(tokens[3].matching_symbol(&lexer), None);
+
+        lexer.check_pairs = true;
+        let err = lexer.tokenize().map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(err, Err("1:2: unclosed delimiter: openparen".to_string()));
+
+        lexer.load_str("a) (b)");
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(errors, vec![LexError::Unbalanced(GroupError::Unopened("closeparen".to_string(), Loc { line: 1, col: 2, offset: 1, ..Loc::default() }))]);
+        return Ok(());
+    }
+}
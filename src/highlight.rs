@@ -0,0 +1,208 @@
+use crate::{LosslessToken, TokenKind, TriviaKind};
+#[cfg(feature = "json")]
+use crate::{Escapes, Lexer, SectionSpec};
+
+// renders the source of lossless tokens, see Lexer::tokenize_lossless, so every byte of the input
+// comes out exactly once. comment trivia is highlighted like a comment token
+fn render(tokens: &[LosslessToken], mut write: impl FnMut(Option<TokenKind>, &str)) {
+    for token in tokens {
+        for trivia in &token.leading {
+            write((trivia.kind == TriviaKind::Comment).then_some(TokenKind::Comment), &trivia.text);
+        }
+        write(Some(token.token.kind()), &token.text);
+        for trivia in &token.trailing {
+            write((trivia.kind == TriviaKind::Comment).then_some(TokenKind::Comment), &trivia.text);
+        }
+    }
+}
+
+// the sgr color code of a token kind, None leaves the text in the default color
+pub fn ansi_color(kind: TokenKind) -> Option<&'static str> {
+    return match kind {
+        TokenKind::Keyword => Some("35"),
+        TokenKind::Section | TokenKind::Interpolated | TokenKind::Char => Some("32"),
+        TokenKind::Integer | TokenKind::BigInteger | TokenKind::Float => Some("33"),
+        TokenKind::Symbol => Some("36"),
+        TokenKind::Pragma => Some("34"),
+        TokenKind::Custom => Some("94"),
+        TokenKind::Comment | TokenKind::Shebang => Some("90"),
+        TokenKind::Error => Some("31"),
+        TokenKind::Ident | TokenKind::Indent | TokenKind::Dedent | TokenKind::Newline | TokenKind::Eof => None,
+    };
+}
+
+pub fn ansi(tokens: &[LosslessToken]) -> String {
+    let mut output = String::new();
+    render(tokens, |kind, text| {
+        match kind.and_then(ansi_color).filter(|_| !text.is_empty()) {
+            Some(color) => output.push_str(&format!("\x1b[{}m{}\x1b[0m", color, text)),
+            None => output.push_str(text),
+        }
+    });
+    return output;
+}
+
+// the css class of a token kind, the kind name in lowercase
+pub fn css_class(kind: TokenKind) -> String {
+    return kind.to_string().to_lowercase();
+}
+
+fn escape_html(text: &str, output: &mut String) {
+    for character in text.chars() {
+        match character {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(character),
+        }
+    }
+}
+
+// whitespace is left unwrapped, so the output belongs inside a <pre> element
+pub fn html(tokens: &[LosslessToken]) -> String {
+    let mut output = String::new();
+    render(tokens, |kind, text| {
+        match kind.filter(|_| !text.is_empty()) {
+            Some(kind) => {
+                output.push_str(&format!("<span class=\"{}\">", css_class(kind)));
+                escape_html(text, &mut output);
+                output.push_str("</span>");
+            },
+            None => escape_html(text, &mut output),
+        }
+    });
+    return output;
+}
+
+// escapes the characters that are special in an oniguruma pattern
+#[cfg(feature = "json")]
+fn escape_pattern(text: &str) -> String {
+    let mut pattern = String::new();
+    for character in text.chars() {
+        match character {
+            '\n' => pattern.push_str("\\n"),
+            '\t' => pattern.push_str("\\t"),
+            '\\' | '^' | '$' | '.' | '|' | '?' | '*' | '+' | '(' | ')' | '[' | ']' | '{' | '}' | '/' | '-' | '#' => {
+                pattern.push('\\');
+                pattern.push(character);
+            },
+            _ => pattern.push(character),
+        }
+    }
+    return pattern;
+}
+
+#[cfg(feature = "json")]
+fn textmate_section(section: &SectionSpec, language: &str) -> serde_json::Value {
+    let scope = if !section.comment {
+        format!("string.quoted.{}.{}", section.name, language)
+    } else if section.line {
+        format!("comment.line.{}.{}", section.name, language)
+    } else {
+        format!("comment.block.{}.{}", section.name, language)
+    };
+
+    let mut patterns = Vec::new();
+    if let Escapes::Decoded(escape) = section.escapes {
+        patterns.push(serde_json::json!({ "name": format!("constant.character.escape.{}", language), "match": format!("{}.", escape_pattern(&escape.to_string())) }));
+    }
+    if let Some((start, end)) = &section.interpolation {
+        patterns.push(serde_json::json!({
+            "name": format!("meta.embedded.{}", language),
+            "begin": escape_pattern(start),
+            "end": escape_pattern(end),
+            "patterns": [{ "include": "$self" }],
+        }));
+    }
+
+    return serde_json::json!({
+        "name": scope,
+        "begin": escape_pattern(&section.start),
+        // a line section also ends at the end of input, which $ covers
+        "end": if section.line { "$".to_string() } else { escape_pattern(&section.end) },
+        "patterns": patterns,
+    });
+}
+
+// a textmate grammar, the .tmLanguage.json format read by most editors, for the base rules of the
+// lexer. keywords become keyword.control, sections string.quoted or comment and symbols
+// punctuation, each suffixed with the last part of the scope. heredocs and modes are left out as
+// they can't be expressed by the grammar
+#[cfg(feature = "json")]
+pub fn textmate(lexer: &Lexer, name: &str, scope: &str) -> String {
+    let spec = lexer.describe();
+    let language = scope.rsplit('.').next().unwrap_or(scope);
+    let mut patterns = spec.sections.iter().filter(|section| !section.heredoc).map(|section| textmate_section(section, language)).collect::<Vec<serde_json::Value>>();
+
+    let mut keywords = spec.keywords.iter().flat_map(|keyword| std::iter::once(&keyword.keyword).chain(&keyword.aliases)).map(|keyword| escape_pattern(keyword)).collect::<Vec<String>>();
+    // the longest spelling has to be tried first when one keyword starts another
+    keywords.sort_by_key(|keyword| std::cmp::Reverse(keyword.len()));
+    if !keywords.is_empty() {
+        let flags = if lexer.case_insensitive_keywords { "(?i)" } else { "" };
+        patterns.push(serde_json::json!({
+            "name": format!("keyword.control.{}", language),
+            "match": format!("{}\\b(?:{})\\b", flags, keywords.join("|")),
+        }));
+    }
+
+    for symbol in &spec.symbols {
+        patterns.push(serde_json::json!({
+            "name": format!("punctuation.{}.{}", symbol.name.to_lowercase(), language),
+            "match": escape_pattern(&symbol.symbol.to_string()),
+        }));
+    }
+
+    let grammar = serde_json::json!({
+        "$schema": "https://raw.githubusercontent.com/martinring/tmlanguage/master/tmlanguage.json",
+        "name": name,
+        "scopeName": scope,
+        "patterns": patterns,
+    });
+    return serde_json::to_string_pretty(&grammar).expect("a json value always serializes");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Lexer, Section};
+
+    #[test]
+    fn highlight_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\""), Section::new("comment", "#", "\n")], &[('=', "equal".to_string())], false);
+        lexer.comment_sections.push("comment".to_string());
+        lexer.load_str("let a = \"<b>\" # c\n");
+
+        let tokens = lexer.tokenize_lossless()?;
+        assert_eq!(html(&tokens), concat!(
+            "<span class=\"keyword\">let</span> <span class=\"ident\">a</span> <span class=\"symbol\">=</span> ",
+            "<span class=\"section\">&quot;&lt;b&gt;&quot;</span> <span class=\"comment\"># c\n</span>",
+        ));
+        assert_eq!(ansi(&tokens), "\x1b[35mlet\x1b[0m a \x1b[36m=\x1b[0m \x1b[32m\"<b>\"\x1b[0m \x1b[90m# c\n\x1b[0m");
+        return Ok(());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn textmate_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"").interpolate("{", "}"), Section::line("comment", "//")], &[('+', "Plus".to_string())], false);
+        lexer.comment_sections.push("comment".to_string());
+        lexer.add_keyword_alias("var", "let");
+
+        let grammar = serde_json::from_str::<serde_json::Value>(&textmate(&lexer, "Demo", "source.demo"))?;
+        assert_eq!(grammar["scopeName"], "source.demo");
+        let patterns = &grammar["patterns"];
+        assert_eq!(patterns[0]["name"], "string.quoted.string.demo");
+        assert_eq!(patterns[0]["begin"], "\"");
+        assert_eq!(patterns[0]["patterns"][0]["begin"], "\\{");
+        assert_eq!(patterns[1]["name"], "comment.line.comment.demo");
+        assert_eq!(patterns[1]["begin"], "\\/\\/");
+        assert_eq!(patterns[1]["end"], "$");
+        assert_eq!(patterns[2]["match"], "\\b(?:let|var)\\b");
+        assert_eq!(patterns[3]["name"], "punctuation.plus.demo");
+        assert_eq!(patterns[3]["match"], "\\+");
+        return Ok(());
+    }
+}
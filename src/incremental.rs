@@ -0,0 +1,455 @@
+use crate::{LexError, Lexer, Loc, Run, Token};
+
+use std::ops::{ControlFlow, Range};
+
+// a line start where the lexer is in normal mode, lexing can restart from here
+#[derive(Clone)]
+struct Checkpoint {
+    offset: usize,
+    token: usize,
+    run: Run,
+    loc: Loc,
+}
+
+// tokens, every checkpoint passed and the checkpoint lexing stopped at, None at the end of the
+// source along with the line directives applied by then
+type Batches = (Vec<Token>, Vec<Checkpoint>, Result<Checkpoint, usize>);
+
+pub struct TokenCache {
+    source: String,
+    tokens: Vec<Token>,
+    checkpoints: Vec<Checkpoint>,
+    // the line directives in the whole source, see Run::directives
+    directives: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+// token indices replaced by the edit, old_end is exclusive in the old tokens and new_end in the new tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DamagedRange {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+// moves a loc after the edit by the lines and bytes the edit added or removed
+fn shift(loc: Loc, line_delta: isize, offset_delta: isize) -> Loc {
+    return Loc {
+        line: (loc.line as isize + line_delta) as u32,
+        offset: (loc.offset as isize + offset_delta) as usize,
+        ..loc
+    };
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: &str) -> TextEdit {
+        return TextEdit {
+            range,
+            text: text.to_string(),
+        };
+    }
+}
+
+impl TokenCache {
+    pub fn new(lexer: &Lexer, source: &str) -> Result<TokenCache, Box<dyn std::error::Error>> {
+        let start = Checkpoint {
+            offset: 0,
+            token: 0,
+            run: lexer.new_run(),
+            loc: Loc::new(lexer.file),
+        };
+
+        let (tokens, checkpoints, end) = lexer.lex_batches(source, start, |_| false)?;
+        return Ok(TokenCache {
+            source: source.to_string(),
+            tokens,
+            checkpoints,
+            directives: end.err().unwrap_or_default(),
+        });
+    }
+
+    pub fn source(&self) -> &str {
+        return &self.source;
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        return &self.tokens;
+    }
+}
+
+impl Lexer {
+    // lexes a complete line at a time, never stopping inside a section
+    fn next_batch(&self, source: &str, checkpoint: &Checkpoint) -> Result<(Vec<Token>, Checkpoint, bool), LexError> {
+        let rest = &source[checkpoint.offset..];
+        // the newline must not be the last character, it needs lookahead
+        let last = rest.char_indices().next_back().map_or(0, |(last, _)| last);
+        let mut search = 0;
+        loop {
+            // an open section can be carried past the last character, leaving only the finishing scan
+            let limit = rest.get(search..last).and_then(|line| line.find('\n')).map(|newline| search + newline + 1);
+            let finish = limit.is_none();
+            let limit = limit.unwrap_or(rest.len());
+
+            // a section open at the line break is carried on to the line it ends on in the same scan
+            let mut run = Run {
+                extend: true,
+                ..checkpoint.run.clone()
+            };
+            let mut loc = checkpoint.loc;
+            let (scanned, open) = self.scan_until(rest, limit, &mut run, &mut loc, finish)?;
+            if open && !finish {
+                // the input ran out first, or a heredoc is still open and is taken a line at a time
+                search = scanned.end.max(limit);
+                continue;
+            }
+
+            let limit = scanned.end;
+            let tokens = scanned.tokens.into_iter().filter_map(|token| self.finish_token(token)).collect::<Vec<Token>>();
+            let end = Checkpoint {
+                offset: checkpoint.offset + limit,
+                token: checkpoint.token + tokens.len(),
+                run,
+                loc,
+            };
+            return Ok((tokens, end, finish));
+        }
+    }
+
+    // lexes from start until stop accepts a checkpoint or the source ends
+    fn lex_batches(&self, source: &str, start: Checkpoint, mut stop: impl FnMut(&Checkpoint) -> bool) -> Result<Batches, LexError> {
+        let mut tokens = Vec::new();
+        let mut checkpoints = vec![start];
+        loop {
+            let (batch, end, finished) = self.next_batch(source, &checkpoints[checkpoints.len() - 1])?;
+            tokens.extend(batch);
+            if finished {
+                return Ok((tokens, checkpoints, Err(end.run.directives)));
+            } else if stop(&end) {
+                return Ok((tokens, checkpoints, Ok(end)));
+            }
+            checkpoints.push(end);
+        }
+    }
+
+    // the line start at or before offset where lexing can resume, as the offset, run and loc there.
+    // lexes source up to it, a TokenCache keeps these checkpoints for every line
+    pub(crate) fn line_state(&self, source: &str, offset: usize) -> Result<(usize, Run, Loc), LexError> {
+        let start = Checkpoint {
+            offset: 0,
+            token: 0,
+            run: self.new_run(),
+            loc: Loc::new(self.file),
+        };
+        let (_, mut checkpoints, _) = self.lex_batches(source, start, |checkpoint| checkpoint.offset > offset)?;
+        let checkpoint = checkpoints.pop().expect("lex_batches keeps the start");
+        return Ok((checkpoint.offset, checkpoint.run, checkpoint.loc));
+    }
+
+    // like token_at for the source of the cache, resuming from the checkpoint of the offset's line
+    // instead of lexing the lines before it
+    pub fn cached_token_at(&self, cache: &TokenCache, offset: usize) -> Option<(Token, Range<usize>)> {
+        let index = cache.checkpoints.partition_point(|checkpoint| checkpoint.offset <= offset).checked_sub(1)?;
+        let checkpoint = &cache.checkpoints[index];
+        return self.token_from(&cache.source, checkpoint.offset, checkpoint.run.clone(), checkpoint.loc, offset);
+    }
+
+    // re-lexes only the lines affected by the edit and splices the result into the cache
+    pub fn relex(&self, cache: &mut TokenCache, edit: TextEdit) -> Result<DamagedRange, Box<dyn std::error::Error>> {
+        let TextEdit { range, text } = edit;
+        if range.start > range.end || range.end > cache.source.len() || !cache.source.is_char_boundary(range.start) || !cache.source.is_char_boundary(range.end) {
+            return Err(format!("invalid edit range: {:?}", range).into());
+        }
+
+        let mut source = cache.source.clone();
+        source.replace_range(range.clone(), &text);
+        let edit_end = range.start + text.len();
+        let old_offset = |offset: usize| (offset + range.end).checked_sub(edit_end);
+
+        // the checkpoint's lookahead character must be untouched by the edit
+        let first = cache.checkpoints.iter().rposition(|checkpoint| checkpoint.offset < range.start).unwrap_or(0);
+        let start = cache.checkpoints[first].clone();
+
+        let old_checkpoints = &cache.checkpoints;
+        let old_directives = cache.directives;
+        let mut synced = None;
+        let (tokens, checkpoints, end) = self.lex_batches(&source, start.clone(), |checkpoint| {
+            if checkpoint.offset < edit_end {
+                return false;
+            }
+
+            // the old tokens after the checkpoint are moved to the new lines, which a line directive
+            // among them would have kept where they were. one changed by the edit can also move them
+            // to another file
+            let old = old_checkpoints.binary_search_by_key(&old_offset(checkpoint.offset), |old| Some(old.offset));
+            synced = old.ok().filter(|old| {
+                let old = &old_checkpoints[*old];
+                let moves = old.loc.line != checkpoint.loc.line && old.run.directives != old_directives;
+                return old.run.same_state(&checkpoint.run) && old.loc.file == checkpoint.loc.file && !moves;
+            });
+            return synced.is_some();
+        })?;
+
+        let old_end = synced.map_or(cache.tokens.len(), |old| cache.checkpoints[old].token);
+        let damaged = DamagedRange {
+            start: start.token,
+            old_end,
+            new_end: start.token + tokens.len(),
+        };
+
+        let mut spliced_checkpoints = cache.checkpoints[..first].to_vec();
+        spliced_checkpoints.extend(checkpoints);
+        let tail_tokens = cache.tokens.split_off(old_end);
+        cache.tokens.truncate(start.token);
+        cache.tokens.extend(tokens);
+
+        if let (Some(old), Ok(end)) = (synced, &end) {
+            let line_delta = end.loc.line as isize - cache.checkpoints[old].loc.line as isize;
+            let offset_delta = end.offset as isize - cache.checkpoints[old].offset as isize;
+            let token_delta = damaged.new_end as isize - damaged.old_end as isize;
+            let run_delta = end.run.tokens as isize - cache.checkpoints[old].run.tokens as isize;
+            let directive_delta = end.run.directives as isize - cache.checkpoints[old].run.directives as isize;
+
+            cache.tokens.extend(tail_tokens.into_iter().map(|token| token.map_loc(&|loc| shift(loc, line_delta, offset_delta))));
+            spliced_checkpoints.extend(cache.checkpoints[old..].iter().map(|checkpoint| Checkpoint {
+                offset: (checkpoint.offset as isize + offset_delta) as usize,
+                token: (checkpoint.token as isize + token_delta) as usize,
+                run: Run {
+                    tokens: (checkpoint.run.tokens as isize + run_delta) as usize,
+                    directives: (checkpoint.run.directives as isize + directive_delta) as usize,
+                    ..checkpoint.run.clone()
+                },
+                loc: shift(checkpoint.loc, line_delta, offset_delta),
+            }));
+            cache.directives = (cache.directives as isize + directive_delta) as usize;
+        } else if let Err(directives) = end {
+            cache.directives = directives;
+        }
+
+        cache.source = source;
+        cache.checkpoints = spliced_checkpoints;
+        return Ok(damaged);
+    }
+
+    // hands the tokens to sink as they are lexed, a line at a time, without collecting them.
+    // lexing stops as soon as sink breaks, so the rest of the input is never looked at
+    pub fn tokenize_with(&self, mut sink: impl FnMut(Token) -> ControlFlow<()>) -> Result<(), Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let mut checkpoint = Checkpoint {
+            offset: 0,
+            token: 0,
+            run: self.new_run(),
+            loc: Loc::new(self.file),
+        };
+
+        loop {
+            let (tokens, end, finished) = self.next_batch(source, &checkpoint)?;
+            for token in tokens {
+                if sink(token).is_break() {
+                    return Ok(());
+                }
+            }
+
+            if finished {
+                return Ok(());
+            }
+            checkpoint = end;
+        }
+    }
+
+    // like tokenize, calling progress with the bytes lexed so far and the size of the input each
+    // time another interval bytes are done and once at the end. input is lexed a line at a time, so
+    // a line longer than interval is only reported when it is done
+    pub fn tokenize_with_progress(&self, interval: usize, mut progress: impl FnMut(usize, usize)) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let mut checkpoint = Checkpoint {
+            offset: 0,
+            token: 0,
+            run: self.new_run(),
+            loc: Loc::new(self.file),
+        };
+
+        let interval = interval.max(1);
+        let mut tokens = Vec::new();
+        let mut reported = 0;
+        loop {
+            let (batch, end, finished) = self.next_batch(source, &checkpoint)?;
+            tokens.extend(batch);
+            if finished {
+                break;
+            } else if end.offset / interval > reported {
+                reported = end.offset / interval;
+                progress(end.offset, source.len());
+            }
+            checkpoint = end;
+        }
+
+        if let Some(error) = self.check_pairs.then(|| self.unbalanced(&tokens)).and_then(|errors| errors.into_iter().next()) {
+            return Err(LexError::Unbalanced(error).into());
+        }
+        progress(source.len(), source.len());
+        return Ok(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Section;
+
+    #[test]
+    fn relex_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        let source = "let a = 1\nlet b = \"x\ny\"\nlet c = 3\nlet d = 4\n ";
+        let mut cache = TokenCache::new(&lexer, source)?;
+
+        let damaged = lexer.relex(&mut cache, TextEdit::new(4..5, "aa"))?;
+        assert_eq!(damaged, DamagedRange { start: 0, old_end: 4, new_end: 4 });
+
+        let edits = [
+            TextEdit::new(14..15, "bb"),
+            TextEdit::new(0..0, "let z = 0\n"),
+            TextEdit::new(20..21, "\"open\n"),
+            TextEdit::new(20..26, ""),
+        ];
+
+        for edit in edits {
+            let damaged = lexer.relex(&mut cache, edit)?;
+
+            let mut full = lexer.clone();
+            full.load_str(cache.source());
+            assert_eq!(cache.tokens(), full.tokenize()?.as_slice());
+            assert!(damaged.start <= damaged.new_end);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn relex_line_directive_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('=', "equal".to_string())], false);
+        lexer.line_directive = Some("#line".to_string());
+        let source = "a\n#line 10 \"a.src\"\nb = c\nd\ne\n ";
+        let mut cache = TokenCache::new(&lexer, source)?;
+
+        // the lines after the directive move to another file and line, however far they are
+        let edits = [
+            TextEdit::new(8..18, "20 \"b.src\""),
+            TextEdit::new(8..10, "30"),
+            TextEdit::new(0..0, "z\n"),
+        ];
+        for edit in edits {
+            lexer.relex(&mut cache, edit)?;
+
+            let mut full = lexer.clone();
+            full.load_str(cache.source());
+            assert_eq!(cache.tokens(), full.tokenize()?.as_slice(), "{:?}", cache.source());
+        }
+        assert_eq!(cache.tokens().last().map(|token| (token.loc().file.name(), token.loc().line)), Some((Some("b.src"), 32)));
+
+        // directives added and removed
+        let d = cache.source().find("d\n").unwrap_or_default();
+        lexer.relex(&mut cache, TextEdit::new(d..d, "#line 5\n"))?;
+        let directive = cache.source().find("#line 30").unwrap_or_default();
+        let end = directive + cache.source()[directive..].find('\n').unwrap_or_default() + 1;
+        lexer.relex(&mut cache, TextEdit::new(directive..end, ""))?;
+
+        let mut full = lexer.clone();
+        full.load_str(cache.source());
+        assert_eq!(cache.tokens(), full.tokenize()?.as_slice());
+        assert_eq!(cache.tokens().last().map(|token| (token.loc().file.name(), token.loc().line)), Some((None, 6)));
+        return Ok(());
+    }
+
+    #[test]
+    fn open_section_batch_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("comment", "/*", "*/")], &[], false);
+        let source = format!("a /*{}*/ b\nc\n", " x\n".repeat(1000));
+        lexer.load_str(&source);
+
+        // the comment is lexed once, in the batch of the line it ends on
+        let cache = TokenCache::new(&lexer, &source)?;
+        let offsets = cache.checkpoints.iter().map(|checkpoint| checkpoint.offset).collect::<Vec<usize>>();
+        assert_eq!(offsets, [0, source.len() - "c\n".len()]);
+        assert_eq!(cache.tokens(), lexer.tokenize()?.as_slice());
+        assert_eq!(cache.checkpoints[1].token, 3);
+
+        // the same when the input ends inside the line the comment ends on
+        lexer.load_str(&source[..source.len() - "\nc\n".len()]);
+        let mut tokens = Vec::new();
+        lexer.tokenize_with(|token| {
+            tokens.push(token);
+            return ControlFlow::Continue(());
+        })?;
+        assert_eq!(tokens, lexer.tokenize()?);
+        assert_eq!(tokens.len(), 3);
+
+        // an escape just before the end carries the open section past the last character
+        let lexer = Lexer::new(&[], &[Section::new("comment", "/*", "*/"), Section::new("string", "\"", "\"")], &[], false);
+        for source in ["/*\n\\\"", "a\n/*\n\\\"", "/*\n\\"] {
+            let mut full = lexer.clone();
+            full.load_str(source);
+            let cache = TokenCache::new(&lexer, source)?;
+            assert_eq!(cache.tokens(), full.tokenize()?.as_slice(), "{:?}", source);
+        }
+
+        // every short input lexes the same a line at a time as in one go
+        let alphabet = ['/', '*', '\n', '\\', '"', 'a', ' '];
+        let mut sources = vec![String::new()];
+        for _ in 0..5 {
+            sources = sources.iter().flat_map(|source| alphabet.iter().map(move |character| format!("{}{}", source, character))).collect();
+            for source in &sources {
+                let mut full = lexer.clone();
+                full.load_str(source);
+                let tokens = TokenCache::new(&lexer, source).map(|cache| cache.tokens().to_vec()).map_err(|error| error.to_string());
+                assert_eq!(tokens, full.tokenize().map_err(|error| error.to_string()), "{:?}", source);
+            }
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn tokenize_with_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["module".to_string()], &[Section::new("string", "\"", "\"")], &[], false);
+        lexer.strict = true;
+        lexer.load_str("a \"b\nc\"\nmodule d\n$");
+
+        let mut seen = Vec::new();
+        lexer.tokenize_with(|token| {
+            let module = token.is_keyword("module").is_ok();
+            seen.push(token);
+            return if module { ControlFlow::Break(()) } else { ControlFlow::Continue(()) };
+        })?;
+        // the invalid character on the last line is never lexed
+        assert_eq!(seen.len(), 3);
+        assert!(lexer.tokenize().is_err());
+
+        lexer.load_str("a \"b\nc\"\nmodule d\n");
+        let mut all = Vec::new();
+        lexer.tokenize_with(|token| {
+            all.push(token);
+            return ControlFlow::Continue(());
+        })?;
+        assert_eq!(all, lexer.tokenize()?);
+        return Ok(());
+    }
+
+    #[test]
+    fn tokenize_with_progress_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        let source = "let a = \"multi\nline\"\n".repeat(100);
+        lexer.load_str(&source);
+
+        let mut reports = Vec::new();
+        let tokens = lexer.tokenize_with_progress(256, |done, total| reports.push((done, total)))?;
+        assert_eq!(tokens, lexer.tokenize()?);
+        assert_eq!(reports.len(), source.len() / 256 + 1);
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(reports.iter().all(|(done, total)| *total == source.len() && done % 21 == 0));
+        assert_eq!(reports.last(), Some(&(source.len(), source.len())));
+        return Ok(());
+    }
+}
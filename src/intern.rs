@@ -0,0 +1,150 @@
+use crate::{Lexer, Loc, Token, TokenRef};
+
+use std::collections::HashMap;
+
+// the id of an interned string, only meaningful for the interner that produced it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn index(&self) -> usize {
+        return self.0 as usize;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    strings: Vec<String>,
+    ids: HashMap<String, Symbol>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        return Interner::default();
+    }
+
+    // the same text always gives the same symbol, only new text is allocated
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.ids.get(text) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.strings.len() as u32);
+        self.strings.push(text.to_string());
+        self.ids.insert(text.to_string(), symbol);
+        return symbol;
+    }
+
+    // looks up text without interning it
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        return self.ids.get(text).copied();
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        return self.strings.get(symbol.index()).map(String::as_str);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.strings.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.strings.is_empty();
+    }
+}
+
+// a token whose identifier or keyword text is interned, every other token is kept as is
+#[derive(Debug, Clone, PartialEq)]
+pub enum InternedToken {
+    Ident(Symbol, Loc),
+    Keyword(Symbol, Loc),
+    Token(Token),
+}
+
+impl InternedToken {
+    pub fn loc(&self) -> Loc {
+        return match self {
+            InternedToken::Ident(_, loc) | InternedToken::Keyword(_, loc) => *loc,
+            InternedToken::Token(token) => token.loc(),
+        };
+    }
+
+    pub fn is_ident(&self) -> Option<Symbol> {
+        return match self {
+            InternedToken::Ident(symbol, _) => Some(*symbol),
+            _ => None,
+        };
+    }
+
+    pub fn is_keyword(&self) -> Option<Symbol> {
+        return match self {
+            InternedToken::Keyword(symbol, _) => Some(*symbol),
+            _ => None,
+        };
+    }
+
+    // turns the token back into a Token, None if the symbol is not from this interner
+    pub fn resolve(&self, interner: &Interner) -> Option<Token> {
+        return match self {
+            InternedToken::Ident(symbol, loc) => Some(Token::Ident(interner.resolve(*symbol)?.to_string(), *loc)),
+            InternedToken::Keyword(symbol, loc) => Some(Token::Keyword(interner.resolve(*symbol)?.to_string(), *loc)),
+            InternedToken::Token(token) => Some(token.clone()),
+        };
+    }
+}
+
+impl Lexer {
+    // like tokenize, but identifiers and keywords are interned instead of allocated per token.
+    // with a map_token hook every token goes through it first and is interned afterwards
+    pub fn tokenize_interned(&self, interner: &mut Interner) -> Result<Vec<InternedToken>, Box<dyn std::error::Error>> {
+        let mut tokens = Vec::new();
+        for token in self.tokenize_ref()? {
+            let interned = match token {
+                TokenRef::Ident(ident, loc) if self.map_token.is_none() => InternedToken::Ident(interner.intern(ident), loc),
+                TokenRef::Keyword(keyword, loc) if self.map_token.is_none() => InternedToken::Keyword(interner.intern(&keyword), loc),
+                token => match self.finish_token(token) {
+                    Some(Token::Ident(ident, loc)) => InternedToken::Ident(interner.intern(&ident), loc),
+                    Some(Token::Keyword(keyword, loc)) => InternedToken::Keyword(interner.intern(&keyword), loc),
+                    Some(token) => InternedToken::Token(token),
+                    None => continue,
+                },
+            };
+            tokens.push(interned);
+        }
+        return Ok(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Interner, InternedToken, Lexer, Token};
+
+    #[test]
+    fn intern_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = b\nlet b = a\n");
+
+        let mut interner = Interner::new();
+        let tokens = lexer.tokenize_interned(&mut interner)?;
+        assert_eq!(interner.len(), 3);
+        assert_eq!(tokens[0].is_keyword(), interner.get("let"));
+        assert_eq!(tokens[1].is_ident(), tokens[7].is_ident());
+        assert_eq!(tokens[3].is_ident(), tokens[5].is_ident());
+        assert_ne!(tokens[1].is_ident(), tokens[3].is_ident());
+        assert_eq!(tokens[1].is_ident().and_then(|symbol| interner.resolve(symbol)), Some("a"));
+        assert!(matches!(tokens[2], InternedToken::Token(Token::Symbol('=', _, _))));
+
+        let resolved = tokens.iter().map(|token| token.resolve(&interner)).collect::<Option<Vec<Token>>>();
+        assert_eq!(resolved, Some(lexer.tokenize()?));
+
+        lexer.map_token = Some(|token| match token {
+            Token::Ident(ident, loc) => Some(Token::Ident(ident.to_uppercase(), loc)),
+            token => Some(token),
+        });
+        let tokens = lexer.tokenize_interned(&mut interner)?;
+        assert_eq!(tokens[1].is_ident().and_then(|symbol| interner.resolve(symbol)), Some("A"));
+        assert_eq!(interner.len(), 5);
+        return Ok(());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{Token, TokenKind, TokenStream};
+
+use std::ops::BitOr;
+
+// a set of token kinds as a bitmask, so whether a token is one of several kinds is a single test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TokenKindSet(u32);
+
+impl TokenKindSet {
+    pub const EMPTY: TokenKindSet = TokenKindSet(0);
+    // the kinds TokenKind::is_trivia holds for
+    pub const TRIVIA: TokenKindSet = TokenKindSet::of(&[TokenKind::Comment, TokenKind::Pragma, TokenKind::Shebang]);
+    // the line structure emitted with emit_newlines and offside
+    pub const LAYOUT: TokenKindSet = TokenKindSet::of(&[TokenKind::Newline, TokenKind::Indent, TokenKind::Dedent]);
+
+    pub const fn of(kinds: &[TokenKind]) -> TokenKindSet {
+        let mut set = TokenKindSet::EMPTY;
+        let mut index = 0;
+        while index < kinds.len() {
+            set = set.with(kinds[index]);
+            index += 1;
+        }
+        return set;
+    }
+
+    pub const fn with(self, kind: TokenKind) -> TokenKindSet {
+        return TokenKindSet(self.0 | 1 << kind as u32);
+    }
+
+    pub const fn union(self, other: TokenKindSet) -> TokenKindSet {
+        return TokenKindSet(self.0 | other.0);
+    }
+
+    pub const fn contains(&self, kind: TokenKind) -> bool {
+        return self.0 & 1 << kind as u32 != 0;
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        return self.0 == 0;
+    }
+
+    pub fn matches(&self, token: &Token) -> bool {
+        return self.contains(token.kind());
+    }
+}
+
+impl From<TokenKind> for TokenKindSet {
+    fn from(kind: TokenKind) -> TokenKindSet {
+        return TokenKindSet::EMPTY.with(kind);
+    }
+}
+
+impl FromIterator<TokenKind> for TokenKindSet {
+    fn from_iter<I: IntoIterator<Item = TokenKind>>(kinds: I) -> TokenKindSet {
+        return kinds.into_iter().fold(TokenKindSet::EMPTY, TokenKindSet::with);
+    }
+}
+
+impl BitOr for TokenKindSet {
+    type Output = TokenKindSet;
+
+    fn bitor(self, other: TokenKindSet) -> TokenKindSet {
+        return self.union(other);
+    }
+}
+
+impl BitOr<TokenKind> for TokenKindSet {
+    type Output = TokenKindSet;
+
+    fn bitor(self, kind: TokenKind) -> TokenKindSet {
+        return self.with(kind);
+    }
+}
+
+impl<'a> TokenStream<'a> {
+    // skips every token outside of set and consumes the first one in it
+    pub fn next_matching(&mut self, set: TokenKindSet) -> Option<&'a Token> {
+        self.skip_while_in(TokenKindSet(!set.0));
+        return self.next();
+    }
+
+    // skips the tokens in set, returning how many were skipped. a parser skips trivia and layout
+    // with this before looking at the next token
+    pub fn skip_while_in(&mut self, set: TokenKindSet) -> usize {
+        let mut skipped = 0;
+        while self.peek().is_some_and(|token| set.matches(token)) {
+            self.next();
+            skipped += 1;
+        }
+        return skipped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section, TokenKind, TokenKindSet, TokenStream};
+
+    #[test]
+    fn token_kind_set_test() -> Result<(), Box<dyn std::error::Error>> {
+        let skip = TokenKindSet::TRIVIA | TokenKindSet::LAYOUT;
+        assert!(skip.contains(TokenKind::Comment) && skip.contains(TokenKind::Newline));
+        assert!(!skip.contains(TokenKind::Ident));
+        assert_eq!([TokenKind::Integer, TokenKind::BigInteger].into_iter().collect::<TokenKindSet>(), TokenKindSet::from(TokenKind::Integer) | TokenKind::BigInteger);
+        assert!(TokenKindSet::EMPTY.is_empty());
+
+        let mut lexer = Lexer::new(&[], &[Section::new("comment", "/*", "*/")], &[('=', "equal".to_string())], false);
+        lexer.comment_sections = vec!["comment".to_string()];
+        lexer.emit_newlines = true;
+        lexer.load_str("/* a */\n\nb = /* c */ 1\n");
+        let tokens = lexer.tokenize()?;
+
+        let mut stream = TokenStream::new(&tokens);
+        assert_eq!(stream.skip_while_in(skip), 3);
+        assert_eq!(stream.next().map(|token| token.as_string()), Some("b".to_string()));
+        assert_eq!(stream.next_matching(TokenKind::Integer.into()).map(|token| token.as_string()), Some("1".to_string()));
+        assert!(stream.next_matching(TokenKind::Ident.into()).is_none());
+        assert!(stream.is_empty());
+        return Ok(());
+    }
+}
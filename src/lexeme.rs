@@ -0,0 +1,72 @@
+use crate::{Lexer, Span, Token, TokenKind};
+
+// a token as a flat record of kind, text and span, for filtering, counting and serializing tokens
+// without matching on every variant. this is the struct form of a token: Token itself stays the
+// enum it always was, since every parser built on this crate matches on its variants, and a lexeme
+// carries it along for the decoded value. text is the token exactly as written, delimiters included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+    pub token: Token,
+}
+
+impl Lexeme {
+    // source is the input the span was lexed from
+    pub fn new(token: Token, span: Span, source: &str) -> Lexeme {
+        return Lexeme {
+            kind: token.kind(),
+            text: span.text(source).to_string(),
+            span,
+            token,
+        };
+    }
+
+    // the name of a section, symbol, custom rule or comment, see Token::name
+    pub fn name(&self) -> Option<&str> {
+        return self.token.name();
+    }
+}
+
+impl From<Lexeme> for Token {
+    fn from(lexeme: Lexeme) -> Token {
+        return lexeme.token;
+    }
+}
+
+impl From<Lexeme> for (Token, Span) {
+    fn from(lexeme: Lexeme) -> (Token, Span) {
+        return (lexeme.token, lexeme.span);
+    }
+}
+
+impl Lexer {
+    // like tokenize_spanned, with every token as a Lexeme
+    pub fn tokenize_lexemes(&self) -> Result<Vec<Lexeme>, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        return Ok(self.tokenize_spanned()?.into_iter().map(|(token, span)| Lexeme::new(token, span, source)).collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section, Token, TokenKind};
+
+    #[test]
+    fn tokenize_lexemes_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = \"b\\\"\" 1.50");
+        let lexemes = lexer.tokenize_lexemes()?;
+
+        let kinds = lexemes.iter().map(|lexeme| lexeme.kind).collect::<Vec<TokenKind>>();
+        assert_eq!(kinds, [TokenKind::Keyword, TokenKind::Ident, TokenKind::Symbol, TokenKind::Section, TokenKind::Float]);
+        assert_eq!(lexemes.iter().map(|lexeme| lexeme.text.as_str()).collect::<Vec<&str>>(), ["let", "a", "=", "\"b\\\"\"", "1.50"]);
+        assert_eq!(lexemes.iter().filter(|lexeme| lexeme.kind.is_literal()).count(), 2);
+        assert_eq!(lexemes[3].name(), Some("string"));
+        assert_eq!(lexemes[3].span.range, 8..13);
+
+        assert_eq!(lexemes.into_iter().map(Token::from).collect::<Vec<Token>>(), lexer.tokenize()?);
+        return Ok(());
+    }
+}
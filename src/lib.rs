@@ -1,34 +1,323 @@
+#![allow(clippy::needless_return)]
+
+pub mod codegen;
+pub mod diagnostics;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod highlight;
+pub mod presets;
+pub mod testing;
+
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod bytes;
+mod cache;
+mod channel;
+mod checksum;
+mod compact;
+mod complete;
+mod config;
+mod diff;
+mod doc;
+mod embed;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+mod event;
+mod extend;
+mod group;
+mod incremental;
+mod intern;
+mod kind_set;
+mod lexeme;
+mod limits;
+mod line_index;
+mod lint;
+mod lookup;
+mod loc;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod macros;
+mod merge;
+mod number;
+mod operator;
+mod push;
+#[cfg(feature = "python")]
+mod python;
+mod reader;
+mod reconstruct;
+mod repl;
+mod rewrite;
+mod source_map;
+mod sub_lex;
+mod span;
+mod spec;
+mod stats;
+mod stream;
+mod token_file;
+#[cfg(feature = "json")]
+mod token_json;
+mod token_ref;
+mod tokenizer;
+mod trivia;
+mod typed;
+#[cfg(feature = "unicode")]
+mod unicode;
+mod validate;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "tokio")]
+pub use asynchronous::AsyncTokenReader;
+pub use bytes::ByteToken;
+pub use channel::TokenSender;
+pub use checksum::token_hash;
+pub use compact::CompactTokens;
+pub use complete::{CompletionItem, CompletionKind};
+pub use config::{ConfigError, ConfigFormat, LexerConfig};
+pub use diff::{diff_tokens, TokenEdit};
+pub use doc::{doc_comments, DocComment};
+pub use embed::Embedded;
+pub use event::{DebugFn, LexEvent};
+pub use group::{group_tokens, GroupError, TokenGroup, TokenTree};
+pub use incremental::{DamagedRange, TextEdit, TokenCache};
+pub use intern::{InternedToken, Interner, Symbol};
+pub use kind_set::TokenKindSet;
+pub use lexeme::Lexeme;
+pub use limits::{Limit, Limits};
+pub use line_index::LineIndex;
+pub use lint::{line_loc, Lint, Linter, LongLines, MixedIndent, NonAsciiQuotes, Severity, TokenLint, TrailingWhitespace};
+pub use loc::{ColumnUnit, FileId, Loc, Origin};
+pub use merge::{merge_streams, SourceId, SourceLoc, SourceToken};
+pub use number::NumberStyle;
+pub use operator::{Associativity, SymbolInfo};
+pub use push::PushLexer;
+pub use reader::TokenReader;
+pub use repl::{LineLexer, LineStatus};
+pub use rewrite::Rewriter;
+pub use source_map::SourceMap;
+pub use span::Span;
+pub use spec::{KeywordSpec, LexerSpec, ModeSpec, SectionSpec, SymbolSpec};
+pub use stats::TokenStats;
+pub use stream::{Checkpoint, TokenStream};
+pub use token_file::{FileMetadata, Indent, TokenFile};
+#[cfg(feature = "json")]
+pub use token_json::{spanned_tokens_to_json, tokens_from_json, tokens_to_json, TOKEN_JSON_VERSION};
+pub use token_ref::{FragmentRef, TokenRef};
+pub use tokenizer::Tokenizer;
+pub use trivia::{lossless_source, LosslessToken, RoundtripDiff, Trivia, TriviaKind};
+pub use typed::FromTokenRef;
+#[cfg(feature = "unicode")]
+pub use unicode::is_xid_ident_char;
+pub use validate::{Constraint, Validator, Violation};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmLexer;
+
+use lookup::{IndexCache, RuleIndex, SetIndex};
+#[cfg(feature = "unicode")]
+use unicode::nfc;
+
+use std::borrow::Cow;
+use std::fmt;
 use std::fs;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::sync::Arc;
 
-type Loc = (usize, usize);
+const MAX_CHAR_LEN: usize = 4;
+const MAX_ESCAPE_LEN: usize = 10;
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
     Keyword(String, Loc),
     Section(String, String, Loc),
     Integer(usize, Loc),
+    // the digits of an integer too large for usize, see Lexer::integer_overflow
+    BigInteger(String, Loc),
     Float(f64, Loc),
+    Char(char, Loc),
     Symbol(char, String, Loc),
     Ident(String, Loc),
+    Pragma(String, Loc),
+    // the `#!` first line of a script, see Lexer::emit_shebang
+    Shebang(String, Loc),
+    Custom(String, String, Loc),
+    Comment(String, String, Loc),
+    Error(String, Loc),
+    Indent(Loc),
+    Dedent(Loc),
+    Newline(Loc),
+    Interpolated(String, Vec<Fragment>, Loc),
+    Eof(Loc),
+}
+
+// a piece of an interpolated section body, see Section::interpolate
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fragment {
+    Text(String),
+    Tokens(Vec<Token>),
 }
 
-#[derive(Debug)]
-enum Value {
-    Start(String),
-    End(String, String),
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum TokenKind {
+    Keyword,
+    Section,
+    Integer,
+    Float,
+    Char,
+    Symbol,
+    Ident,
+    Pragma,
+    Shebang,
+    Custom,
+    Comment,
+    Error,
+    Indent,
+    Dedent,
+    Newline,
+    Interpolated,
+    Eof,
+    // after the others so the values of the kinds before it stay the same
+    BigInteger,
 }
 
-enum StartOrSection<'a> {
-    Start(Vec<String>),
-    Section(&'a Section),
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexError {
+    InvalidEscape(String, Loc),
+    InvalidPragma(String, Loc),
+    UnknownMode(String, Loc),
+    UnknownLanguage(String, Loc),
+    InvalidUtf8(Loc),
+    UnterminatedSection(String, Loc),
+    InconsistentIndent(Loc),
+    UnexpectedChar(char, Loc),
+    LimitExceeded(Limit, Loc),
+    InvalidLineDirective(String, Loc),
+    IntegerOverflow(String, Loc),
+    Unbalanced(GroupError),
+    SubLexer(String, String, Loc),
 }
 
 #[derive(PartialEq, Eq)]
-enum Mode {
+enum State {
     Section,
     Normal,
 }
 
+// options that pragmas may change while lexing, scoped to a single run
+#[derive(Clone)]
+struct Run {
+    allow_whitespace: bool,
+    allow_exponent: bool,
+    allow_digit_separators: bool,
+    keywords: Vec<String>,
+    modes: Vec<usize>,
+    recover: bool,
+    indents: Vec<usize>,
+    // spans are only needed by callers that map tokens back onto the source
+    skip_spans: bool,
+    // a section open at the limit of a scan is lexed on to the end of the line it ends on, for
+    // callers lexing a line at a time that would otherwise lex the section again with every line
+    extend: bool,
+    index: Arc<RuleIndex>,
+    // lexed by the earlier scans of the run, so Limits::max_tokens covers input lexed in batches
+    tokens: usize,
+    // the line directives applied so far, the lines after one no longer move with the lines before it
+    directives: usize,
+}
+
+impl Run {
+    // whether lexing carries on the same from either, however many tokens and directives came
+    // before. the index is the lexer's, not part of the state
+    fn same_state(&self, other: &Run) -> bool {
+        return self.allow_whitespace == other.allow_whitespace
+            && self.allow_exponent == other.allow_exponent
+            && self.allow_digit_separators == other.allow_digit_separators
+            && self.keywords == other.keywords
+            && self.modes == other.modes
+            && self.recover == other.recover
+            && self.indents == other.indents
+            && self.skip_spans == other.skip_spans
+            && self.extend == other.extend;
+    }
+}
+
+// a named rule set that replaces the lexer's keywords, sections and symbols while it is active
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LexMode {
+    pub name: String,
+    pub keywords: Vec<String>,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<(char, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModeAction {
+    Push(String),
+    Pop,
+}
+
+// applies action when a keyword or symbol named trigger is lexed in mode, None being the base rules
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModeTransition {
+    pub mode: Option<String>,
+    pub trigger: String,
+    pub action: ModeAction,
+}
+
+struct RuleSet<'a> {
+    keywords: &'a [String],
+    sections: &'a [Section],
+    symbols: &'a [(char, String)],
+}
+
+// the tokens of a scan with the byte range each one covers in the scanned source. with a sink the
+// tokens are handed to it as they are produced instead of collected, see Lexer::tokenize_as
+#[derive(Default)]
+struct Scanned<'a> {
+    tokens: Vec<TokenRef<'a>>,
+    sink: Option<&'a mut dyn FnMut(TokenRef<'a>)>,
+    // the tokens produced so far, whether collected or handed to the sink
+    count: usize,
+    spans: Vec<Range<usize>>,
+    errors: Vec<LexError>,
+    skip_spans: bool,
+    debug_hook: Option<DebugFn>,
+    // the index the scan stopped at, past the limit when Run::extend carried it on
+    end: usize,
+    // when the scan was left open, the strings one of which more input has to bring before a scan
+    // can get further. None when any input might
+    closes: Option<Vec<&'a str>>,
+}
+
+// the line and file name a line directive moves to, see Lexer::line_directive
+type LineDirective<'a> = (u32, Option<&'a str>);
+
+pub type RuleFn = dyn Fn(&str) -> Option<usize> + Send + Sync;
+
+// see Section::sub_lex
+pub type SubLexer = fn(&str) -> Result<Vec<Token>, Box<dyn std::error::Error>>;
+
+// see Lexer::map_number
+pub type NumberFn = for<'a> fn(&'a str, Loc) -> Option<TokenRef<'a>>;
+
+// a tag attached to a keyword, a String for Lexer::add_keyword_tagged or a value of the caller's own
+// type for Lexer::add_keyword_typed. string tags compare by text, others only equal their clones
+#[derive(Clone)]
+pub struct KeywordTag(Arc<dyn std::any::Any + Send + Sync>);
+
+#[derive(Clone)]
+pub struct Rule {
+    pub name: String,
+    pub matcher: Arc<RuleFn>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Lexer {
     pub keywords: Vec<String>,
@@ -36,15 +325,371 @@ pub struct Lexer {
     pub symbols: Vec<(char, String)>,
     pub buffer: Vec<u8>,
     pub allow_whitespace: bool,
+    // characters that separate tokens and are dropped, like the space. a tab outside of the set is
+    // part of the token it touches, a comma in the set is insignificant like a space
+    pub whitespace: Vec<char>,
+    pub allow_exponent: bool,
+    pub allow_digit_separators: bool,
+    // without it numbers are lexed as identifiers
+    pub allow_numbers: bool,
+    // floats like .5, only when '.' is not a symbol
+    pub allow_leading_dot: bool,
+    // floats like 1., on unless a float must have fractional digits
+    pub allow_trailing_dot: bool,
+    // the ascii character between the whole and fractional digits of a float, like the , in 3,14.
+    // the leading and trailing dot options are about this character. see Lexer::set_number_style
+    pub decimal_separator: char,
+    // type suffixes like u32 in 10u32, a suffixed number becomes a Token::Custom named after the
+    // suffix with the number as its text
+    pub number_suffixes: Vec<String>,
+    pub integer_overflow: IntegerOverflow,
+    pub pragma_prefix: Option<String>,
+    // the prefix of directives like `#line 10 "foo.src"` that preprocessors leave behind. the line
+    // after one is reported as the given line of the given file, or of the current file without a
+    // name. the directive itself is not a token and offsets keep counting the input as it is
+    pub line_directive: Option<String>,
+    pub rules: Vec<Rule>,
+    // the tags of keywords as (keyword, tag), see Lexer::add_keyword_tagged and Lexer::add_keyword_typed
+    pub keyword_tags: Vec<(String, KeywordTag)>,
+    // other spellings of a keyword as (alias, keyword), see Lexer::add_keyword_alias
+    pub keyword_aliases: Vec<(String, String)>,
+    // soft keywords, lexed as identifiers that the parser may take as keywords, see
+    // Lexer::add_contextual_keyword
+    pub contextual_keywords: Vec<String>,
+    // operator metadata by symbol name, see Lexer::add_symbol_info
+    pub symbol_info: Vec<(String, SymbolInfo)>,
+    // open and close symbol names that belong together, see Lexer::add_symbol_pair
+    pub symbol_pairs: Vec<(String, String)>,
+    // tokenize fails on a paired symbol without its partner, see Lexer::add_symbol_pair
+    pub check_pairs: bool,
+    pub emit_eof: bool,
+    pub tab_width: usize,
+    // tabs move columns to the next multiple of tab_width instead of counting as one
+    pub expand_tabs: bool,
+    pub column_unit: ColumnUnit,
+    pub case_insensitive_keywords: bool,
+    pub ident_char: fn(char) -> bool,
+    // symbol characters that are part of an identifier they are written in, see Lexer::add_ident_symbol
+    pub ident_symbols: Vec<(char, IdentSymbol)>,
+    // identifiers are made of the XID_Continue characters instead of ident_char, and idents and
+    // keywords are compared in NFC with normalize_idents. both need the unicode feature and have
+    // no effect without it
+    pub xid_idents: bool,
+    pub normalize_idents: bool,
+    pub split_keywords: bool,
+    pub comment_sections: Vec<String>,
+    // a `#!` first line is left out of the tokens, or becomes a Token::Shebang with emit_shebang.
+    // `#![` is not a shebang so inner attributes are still lexed
+    pub shebang: bool,
+    pub emit_shebang: bool,
+    // input loaded with a utf-16 byte order mark is converted to utf-8, without it such input is an error
+    pub transcode_utf16: bool,
+    // the offside rule, Indent and Dedent are emitted where the indentation of a line changes and
+    // Newline at the end of every line with tokens. tabs advance to the next multiple of tab_width
+    pub offside: bool,
+    // emits a Newline for every line break outside of sections, including blank lines
+    pub emit_newlines: bool,
+    // characters that are not part of an identifier, number, symbol or section are errors instead
+    // of ending up in an Ident, see Lexer::ident_char
+    pub strict: bool,
+    // guards for untrusted input, none are set by default
+    pub limits: Limits,
+    // the characters strict mode rejects become Token::Error without failing, so every byte of the
+    // input shows up in the tokens. has no effect when strict is set
+    pub emit_unknown: bool,
+    // a single character or escape between two of these quotes becomes a Token::Char. anything
+    // else, like a lifetime 'a, is lexed as if the quote was an ordinary character
+    pub char_quote: Option<char>,
+    // runs on every token as it is produced, returning None drops the token. tokenize_ref,
+    // tokenize_bytes, tokenize_lossless and semantic_tokens see the tokens as lexed
+    pub map_token: Option<fn(Token) -> Option<Token>>,
+    // sees the text of every token starting like a number before it is parsed, the token it
+    // returns is used instead. None leaves the text to the built in number parsing
+    pub map_number: Option<NumberFn>,
+    // stands for ident_char, map_token, map_number, the rule matchers and the sub lexers in
+    // config_hash, which otherwise tells them apart by address. set it, and change it with any of
+    // them, for caches that outlive a run of the program, see Lexer::config_hash
+    pub hooks_version: Option<u64>,
+    // sees every token and mode transition while lexing, for logging. the tracing feature reports
+    // the same events to tracing
+    pub debug_hook: Option<DebugFn>,
+    // stamped on every loc, load_file sets it to the file name and the other loaders clear it
+    pub file: FileId,
+    pub modes: Vec<LexMode>,
+    pub transitions: Vec<ModeTransition>,
+    pub languages: Vec<(String, Lexer)>,
+    #[cfg(feature = "mmap")]
+    mapped: Option<std::sync::Arc<memmap2::Mmap>>,
+    index: IndexCache,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+// sub_lexer is compared by address, which is enough to tell sections apart
+#[allow(unpredictable_function_pointer_comparisons)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Section {
     pub name: String,
     pub start: String,
     pub end: String,
+    // the options default to those of Section::new when missing from a config document
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub escapes: Escapes,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub language: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unterminated: Unterminated,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interpolation: Option<(String, String)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub heredoc: Option<Heredoc>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nested: Vec<String>,
+    // with it off a line break before the end delimiter fails with LexError::UnterminatedSection,
+    // or is an error token when unterminated is Token. the line break is lexed after it
+    #[cfg_attr(feature = "serde", serde(default = "default_true"))]
+    pub allow_newlines: bool,
+    // the body starts and ends with the delimiters as they were written
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub keep_delimiters: bool,
+    // functions can't be written to a config document, a loaded section has none
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub sub_lexer: Option<SubLexer>,
+}
+
+// how the body of a heredoc section is kept, see Section::heredoc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Heredoc {
+    // the body lines as written
+    Keep,
+    // the indentation the body lines share is removed and the end word may be indented
+    StripIndent,
+}
+
+// what a section does with the character that starts an escape, a backslash by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Escapes {
+    // there are no escapes, so the first end delimiter ends the section, like in r"C:\dir\"
+    Off,
+    // an escape keeps the end delimiter from ending the section, but the body keeps it as written
+    Kept(char),
+    // the escape character is left out of the body and the character after it is kept as it is
+    Stripped(char),
+    // escapes like \n, \t and \u{1f600} become the character they stand for
+    Decoded(char),
+}
+
+// what happens to a section still open at the end of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Unterminated {
+    // the rest of the input becomes the section body
+    #[default]
+    Section,
+    // lexing fails with LexError::UnterminatedSection
+    Error,
+    // the rest of the input including the start delimiter becomes a Token::Error
+    Token,
+}
+
+// what becomes of an integer literal too large for a usize, which is only 32 bits wide on some
+// targets. a literal written as a float is a Token::Float however large
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IntegerOverflow {
+    // a Token::Float, which may not hold the exact value
+    #[default]
+    Float,
+    // lexing fails with LexError::IntegerOverflow
+    Error,
+    // a Token::BigInteger holding the digits without separators
+    BigInteger,
+}
+
+// where a symbol character may continue an identifier, see Lexer::add_ident_symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IdentSymbol {
+    // only followed by an identifier character or another identifier symbol, like the - in foo-bar
+    Inner,
+    // also at the end of the identifier, like the ? in empty? or the ! in set!
+    Trailing,
+}
+
+impl LexError {
+    pub fn loc(&self) -> Loc {
+        return match self {
+            LexError::InvalidEscape(_, loc) => *loc,
+            LexError::InvalidPragma(_, loc) => *loc,
+            LexError::UnknownMode(_, loc) => *loc,
+            LexError::UnknownLanguage(_, loc) => *loc,
+            LexError::InvalidUtf8(loc) => *loc,
+            LexError::UnterminatedSection(_, loc) => *loc,
+            LexError::InconsistentIndent(loc) => *loc,
+            LexError::UnexpectedChar(_, loc) => *loc,
+            LexError::LimitExceeded(_, loc) => *loc,
+            LexError::InvalidLineDirective(_, loc) => *loc,
+            LexError::IntegerOverflow(_, loc) => *loc,
+            LexError::Unbalanced(error) => error.loc(),
+            LexError::SubLexer(_, _, loc) => *loc,
+        };
+    }
+
+    pub(crate) fn map_loc(self, f: &impl Fn(Loc) -> Loc) -> LexError {
+        return match self {
+            LexError::InvalidEscape(escape, loc) => LexError::InvalidEscape(escape, f(loc)),
+            LexError::InvalidPragma(pragma, loc) => LexError::InvalidPragma(pragma, f(loc)),
+            LexError::UnknownMode(mode, loc) => LexError::UnknownMode(mode, f(loc)),
+            LexError::UnknownLanguage(language, loc) => LexError::UnknownLanguage(language, f(loc)),
+            LexError::InvalidUtf8(loc) => LexError::InvalidUtf8(f(loc)),
+            LexError::UnterminatedSection(section, loc) => LexError::UnterminatedSection(section, f(loc)),
+            LexError::InconsistentIndent(loc) => LexError::InconsistentIndent(f(loc)),
+            LexError::UnexpectedChar(character, loc) => LexError::UnexpectedChar(character, f(loc)),
+            LexError::LimitExceeded(limit, loc) => LexError::LimitExceeded(limit, f(loc)),
+            LexError::InvalidLineDirective(directive, loc) => LexError::InvalidLineDirective(directive, f(loc)),
+            LexError::IntegerOverflow(integer, loc) => LexError::IntegerOverflow(integer, f(loc)),
+            LexError::Unbalanced(GroupError::Unclosed(name, loc)) => LexError::Unbalanced(GroupError::Unclosed(name, f(loc))),
+            LexError::Unbalanced(GroupError::Unopened(name, loc)) => LexError::Unbalanced(GroupError::Unopened(name, f(loc))),
+            LexError::SubLexer(section, message, loc) => LexError::SubLexer(section, message, f(loc)),
+        };
+    }
+
+    // the description without the location
+    pub fn message(&self) -> String {
+        return match self {
+            LexError::InvalidEscape(escape, _) => format!("invalid escape sequence: {}", escape),
+            LexError::InvalidPragma(pragma, _) => format!("invalid pragma: {}", pragma),
+            LexError::UnknownMode(mode, _) => format!("unknown mode: {}", mode),
+            LexError::UnknownLanguage(language, _) => format!("unknown language: {}", language),
+            LexError::InvalidUtf8(_) => "invalid utf-8".to_string(),
+            LexError::UnterminatedSection(section, _) => format!("unterminated section: {}", section),
+            LexError::InconsistentIndent(_) => "dedent does not match any outer indentation level".to_string(),
+            LexError::UnexpectedChar(character, _) => format!("unexpected character: {:?}", character),
+            LexError::LimitExceeded(limit, _) => format!("{} limit exceeded", limit),
+            LexError::InvalidLineDirective(directive, _) => format!("invalid line directive: {}", directive),
+            LexError::IntegerOverflow(integer, _) => format!("integer literal too large: {}", integer),
+            LexError::Unbalanced(GroupError::Unclosed(name, _)) => format!("unclosed delimiter: {}", name),
+            LexError::Unbalanced(GroupError::Unopened(name, _)) => format!("unexpected close delimiter: {}", name),
+            LexError::SubLexer(section, message, _) => format!("sub-lexer of section {} failed: {}", section, message),
+        };
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}: {}", self.loc(), self.message());
+    }
+}
+
+impl std::error::Error for LexError {}
+
+impl<'a> Scanned<'a> {
+    fn push(&mut self, token: TokenRef<'a>, span: Range<usize>) {
+        event::report(self.debug_hook, LexEvent::Token(&token));
+        self.count += 1;
+        match self.sink.as_mut() {
+            Some(sink) => sink(token),
+            None => self.tokens.push(token),
+        }
+        if !self.skip_spans {
+            self.spans.push(span);
+        }
+    }
+
+    // when recovering the error is collected and None returned so lexing can carry on
+    fn recover<T>(&mut self, recover: bool, result: Result<T, LexError>) -> Result<Option<T>, LexError> {
+        return match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if recover => {
+                self.errors.push(error);
+                Ok(None)
+            },
+            Err(error) => Err(error),
+        };
+    }
+}
+
+impl KeywordTag {
+    pub fn new<T: std::any::Any + Send + Sync>(tag: T) -> KeywordTag {
+        return KeywordTag(Arc::new(tag));
+    }
+
+    pub fn get<T: std::any::Any>(&self) -> Option<&T> {
+        return self.0.downcast_ref::<T>();
+    }
+
+    // the text of a tag added by Lexer::add_keyword_tagged, None for typed tags
+    pub fn as_str(&self) -> Option<&str> {
+        return self.get::<String>().map(String::as_str);
+    }
+}
+
+impl From<&str> for KeywordTag {
+    fn from(tag: &str) -> KeywordTag {
+        return KeywordTag::new(tag.to_string());
+    }
+}
+
+impl Default for Escapes {
+    fn default() -> Escapes {
+        return Escapes::Stripped('\\');
+    }
+}
+
+impl PartialEq for KeywordTag {
+    fn eq(&self, other: &KeywordTag) -> bool {
+        return Arc::ptr_eq(&self.0, &other.0) || matches!((self.as_str(), other.as_str()), (Some(tag), Some(other)) if tag == other);
+    }
+}
+
+impl fmt::Debug for KeywordTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self.as_str() {
+            Some(tag) => f.debug_tuple("KeywordTag").field(&tag).finish(),
+            None => f.debug_tuple("KeywordTag").finish_non_exhaustive(),
+        };
+    }
+}
+
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.debug_struct("Rule").field("name", &self.name).finish_non_exhaustive();
+    }
+}
+
+// the source text of the token with the usual delimiters, see Token::lexeme. as_string gives the
+// value without them
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.lexeme());
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return fmt::Debug::fmt(self, f);
+    }
 }
 
+// categories for handling tokens generically without matching on every variant
+impl TokenKind {
+    // tokens whose payload is a value written in the source
+    pub fn is_literal(&self) -> bool {
+        return matches!(self, TokenKind::Integer | TokenKind::BigInteger | TokenKind::Float | TokenKind::Char | TokenKind::Section);
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        return matches!(self, TokenKind::Integer | TokenKind::BigInteger | TokenKind::Float);
+    }
+
+    // tokens a parser usually skips, they never affect the meaning of the input
+    pub fn is_trivia(&self) -> bool {
+        return matches!(self, TokenKind::Comment | TokenKind::Pragma | TokenKind::Shebang);
+    }
+}
 
 impl Token {
     pub fn as_string(&self) -> String {
@@ -52,9 +697,56 @@ impl Token {
             Token::Keyword(keyword, _) => keyword.clone(),
             Token::Section(_, value, _) => value.clone(),
             Token::Integer(integer, _) => integer.to_string(),
+            Token::BigInteger(digits, _) => digits.clone(),
             Token::Float(float, _) => float.to_string(),
+            Token::Char(character, _) => character.to_string(),
             Token::Symbol(value, _, _) => value.to_string(),
             Token::Ident(ident, _) => ident.clone(),
+            Token::Pragma(pragma, _) => pragma.clone(),
+            Token::Shebang(line, _) => line.clone(),
+            Token::Custom(_, text, _) => text.clone(),
+            Token::Comment(_, text, _) => text.clone(),
+            Token::Error(text, _) => text.clone(),
+            Token::Newline(_) => "\n".to_string(),
+            Token::Interpolated(_, fragments, _) => fragments.iter().map(|fragment| match fragment {
+                Fragment::Text(text) => text.clone(),
+                Fragment::Tokens(tokens) => tokens.iter().map(Token::as_string).collect::<Vec<String>>().join(" "),
+            }).collect(),
+            Token::Indent(_) | Token::Dedent(_) | Token::Eof(_) => String::new(),
+        };
+    }
+
+    pub fn kind(&self) -> TokenKind {
+        return match self {
+            Token::Keyword(..) => TokenKind::Keyword,
+            Token::Section(..) => TokenKind::Section,
+            Token::Integer(..) => TokenKind::Integer,
+            Token::BigInteger(..) => TokenKind::BigInteger,
+            Token::Float(..) => TokenKind::Float,
+            Token::Char(..) => TokenKind::Char,
+            Token::Symbol(..) => TokenKind::Symbol,
+            Token::Ident(..) => TokenKind::Ident,
+            Token::Pragma(..) => TokenKind::Pragma,
+            Token::Shebang(..) => TokenKind::Shebang,
+            Token::Custom(..) => TokenKind::Custom,
+            Token::Comment(..) => TokenKind::Comment,
+            Token::Error(..) => TokenKind::Error,
+            Token::Indent(..) => TokenKind::Indent,
+            Token::Dedent(..) => TokenKind::Dedent,
+            Token::Newline(..) => TokenKind::Newline,
+            Token::Interpolated(..) => TokenKind::Interpolated,
+            Token::Eof(..) => TokenKind::Eof,
+        };
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        return match self {
+            Token::Section(name, _, _) => Some(name),
+            Token::Symbol(_, name, _) => Some(name),
+            Token::Custom(name, _, _) => Some(name),
+            Token::Comment(kind, _, _) => Some(kind),
+            Token::Interpolated(name, _, _) => Some(name),
+            _ => None,
         };
     }
 
@@ -63,12 +755,59 @@ impl Token {
             Token::Keyword(_, loc) => *loc,
             Token::Section(_, _, loc) => *loc,
             Token::Integer(_, loc) => *loc,
+            Token::BigInteger(_, loc) => *loc,
             Token::Float(_, loc) => *loc,
             Token::Symbol(_, _, loc) => *loc,
             Token::Ident(_, loc) => *loc,
+            Token::Pragma(_, loc) => *loc,
+            Token::Shebang(_, loc) => *loc,
+            Token::Custom(_, _, loc) => *loc,
+            Token::Comment(_, _, loc) => *loc,
+            Token::Error(_, loc) => *loc,
+            Token::Char(_, loc) => *loc,
+            Token::Indent(loc) => *loc,
+            Token::Dedent(loc) => *loc,
+            Token::Newline(loc) => *loc,
+            Token::Interpolated(_, _, loc) => *loc,
+            Token::Eof(loc) => *loc,
+        };
+    }
+
+    // also maps the locs of tokens nested in interpolated sections
+    pub(crate) fn map_loc(self, f: &impl Fn(Loc) -> Loc) -> Token {
+        return match self {
+            Token::Keyword(keyword, loc) => Token::Keyword(keyword, f(loc)),
+            Token::Section(name, value, loc) => Token::Section(name, value, f(loc)),
+            Token::Integer(integer, loc) => Token::Integer(integer, f(loc)),
+            Token::BigInteger(digits, loc) => Token::BigInteger(digits, f(loc)),
+            Token::Float(float, loc) => Token::Float(float, f(loc)),
+            Token::Symbol(value, name, loc) => Token::Symbol(value, name, f(loc)),
+            Token::Ident(ident, loc) => Token::Ident(ident, f(loc)),
+            Token::Pragma(pragma, loc) => Token::Pragma(pragma, f(loc)),
+            Token::Shebang(line, loc) => Token::Shebang(line, f(loc)),
+            Token::Custom(name, text, loc) => Token::Custom(name, text, f(loc)),
+            Token::Comment(kind, text, loc) => Token::Comment(kind, text, f(loc)),
+            Token::Error(text, loc) => Token::Error(text, f(loc)),
+            Token::Char(character, loc) => Token::Char(character, f(loc)),
+            Token::Indent(loc) => Token::Indent(f(loc)),
+            Token::Dedent(loc) => Token::Dedent(f(loc)),
+            Token::Newline(loc) => Token::Newline(f(loc)),
+            Token::Interpolated(name, fragments, loc) => {
+                let fragments = fragments.into_iter().map(|fragment| match fragment {
+                    Fragment::Tokens(tokens) => Fragment::Tokens(tokens.into_iter().map(|token| token.map_loc(f)).collect()),
+                    text => text,
+                }).collect();
+                Token::Interpolated(name, fragments, f(loc))
+            },
+            Token::Eof(loc) => Token::Eof(f(loc)),
         };
     }
 
+    // whether the token is an identifier spelling one of the contextual keywords of lexer
+    pub fn ident_is_keyword_candidate(&self, lexer: &Lexer) -> bool {
+        return matches!(self, Token::Ident(ident, _) if lexer.find_contextual_keyword(ident).is_some());
+    }
+
     pub fn is_keyword(&self, keyword: &str) -> Result<(), Box<dyn std::error::Error>> {
         if let Token::Keyword(value, _) = self {
             if value == keyword {
@@ -101,6 +840,13 @@ impl Token {
         return Err(format!("expected integer: {:?}", self).into());
     }
 
+    pub fn is_big_integer(&self) -> Result<String, Box<dyn std::error::Error>> {
+        if let Token::BigInteger(digits, _) = self {
+            return Ok(digits.clone());
+        }
+        return Err(format!("expected big integer: {:?}", self).into());
+    }
+
     pub fn is_float(&self) -> Result<f64, Box<dyn std::error::Error>> {
         if let Token::Float(float, _) = self {
             return Ok(*float);
@@ -108,6 +854,38 @@ impl Token {
         return Err(format!("expected float: {:?}", self).into());
     }
 
+    pub fn is_char(&self) -> Result<char, Box<dyn std::error::Error>> {
+        if let Token::Char(character, _) = self {
+            return Ok(*character);
+        }
+        return Err(format!("expected char: {:?}", self).into());
+    }
+
+    pub fn is_custom(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if let Token::Custom(c_name, text, _) = self {
+            if c_name == name {
+                return Ok(text.clone());
+            }
+        }
+        return Err(format!("expected custom: {:?}", self).into());
+    }
+
+    pub fn is_comment(&self, kind: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if let Token::Comment(c_kind, text, _) = self {
+            if c_kind == kind {
+                return Ok(text.clone());
+            }
+        }
+        return Err(format!("expected comment: {:?}", self).into());
+    }
+
+    pub fn is_eof(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Token::Eof(_) = self {
+            return Ok(());
+        }
+        return Err(format!("expected end of input: {:?}", self).into());
+    }
+
     pub fn is_symbol(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
         if let Token::Symbol(_, s_name, _) = self {
             if s_name == name {
@@ -118,12 +896,91 @@ impl Token {
     }
 }
 
+pub fn format_token_table(tokens: &[Token]) -> String {
+    return token_table(tokens.iter().map(|token| (token, None)));
+}
+
+// like format_token_table with the byte range of every token as returned by tokenize_spanned
+pub fn format_spanned_token_table(tokens: &[(Token, Span)]) -> String {
+    return token_table(tokens.iter().map(|(token, span)| (token, Some(span))));
+}
+
+fn token_table<'a>(tokens: impl Iterator<Item = (&'a Token, Option<&'a Span>)>) -> String {
+    let mut rows = vec![["index".to_string(), "kind".to_string(), "name".to_string(), "text".to_string(), "line:col".to_string(), "span".to_string()]];
+    for (index, (token, span)) in tokens.enumerate() {
+        let loc = token.loc();
+        rows.push([
+            index.to_string(),
+            token.kind().to_string(),
+            token.name().unwrap_or("-").to_string(),
+            format!("{:?}", token.as_string()),
+            format!("{}:{}", loc.line, loc.col),
+            span.map_or(String::new(), |span| format!("{}..{}", span.range.start, span.range.end)),
+        ]);
+    }
+    // without spans the column is left out
+    if rows[1..].iter().all(|row| row[5].is_empty()) {
+        rows[0][5].clear();
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in &rows {
+        let cells = row.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect::<Vec<String>>();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    return table;
+}
+
+#[cfg(feature = "mmap")]
+fn is_utf16(bytes: &[u8]) -> bool {
+    return bytes.starts_with(&[0xff, 0xfe]) || bytes.starts_with(&[0xfe, 0xff]);
+}
+
+// the default identifier character class, see Lexer::ident_char
+pub fn is_ident_char(character: char) -> bool {
+    return character.is_alphanumeric() || character == '_';
+}
+
+#[cfg(feature = "serde")]
+fn default_true() -> bool {
+    return true;
+}
+
+impl LexMode {
+    pub fn new(name: &str, keywords: &[String], sections: &[Section], symbols: &[(char, String)]) -> LexMode {
+        return LexMode {
+            name: name.to_string(),
+            keywords: keywords.to_vec(),
+            sections: sections.to_vec(),
+            symbols: symbols.to_vec(),
+        };
+    }
+}
+
 impl Section {
     pub fn new(name: &str, start: &str, end: &str) -> Section {
         return Section {
             name: name.to_string(),
             start: start.to_string(),
             end: end.to_string(),
+            escapes: Escapes::default(),
+            language: None,
+            unterminated: Unterminated::Section,
+            interpolation: None,
+            heredoc: None,
+            line: false,
+            nested: Vec::new(),
+            allow_newlines: true,
+            keep_delimiters: false,
+            sub_lexer: None,
         };
     }
 
@@ -132,8 +989,112 @@ impl Section {
             name: String::new(),
             start: String::new(),
             end,
+            escapes: Escapes::default(),
+            language: None,
+            unterminated: Unterminated::Section,
+            interpolation: None,
+            heredoc: None,
+            line: false,
+            nested: Vec::new(),
+            allow_newlines: true,
+            keep_delimiters: false,
+            sub_lexer: None,
+        };
+    }
+
+    // the body is lexed by the lexer registered under this language, see Lexer::register_language
+    pub fn embed(mut self, language: &str) -> Section {
+        self.language = Some(language.to_string());
+        return self;
+    }
+
+    // the body is split at interpolations starting with open and ending with close, which are lexed
+    // with the rules active at the section start. the section becomes a Token::Interpolated
+    pub fn interpolate(mut self, open: &str, close: &str) -> Section {
+        self.interpolation = Some((open.to_string(), close.to_string()));
+        return self;
+    }
+
+    // a section from start to the end of the line, like #include or @annotation. the body leaves out
+    // the line break, \r\n included, and the end of the input ends the section like a line would
+    pub fn line(name: &str, start: &str) -> Section {
+        let mut section = Section::new(name, start, "\n");
+        section.line = true;
+        return section;
+    }
+
+    // the named sections may appear in the body and are skipped as a whole, so their end delimiters
+    // don't end this section, like a string holding } inside ${ }. the body keeps them as written
+    pub fn nest(mut self, sections: &[&str]) -> Section {
+        self.nested = sections.iter().map(|section| section.to_string()).collect();
+        return self;
+    }
+
+    // a line break before the end delimiter is an unterminated section, see Section::allow_newlines
+    pub fn single_line(mut self) -> Section {
+        self.allow_newlines = false;
+        return self;
+    }
+
+    pub fn keep_delimiters(mut self) -> Section {
+        self.keep_delimiters = true;
+        return self;
+    }
+
+    // Escapes::Off for raw strings and regex literals that pass their body through untouched
+    pub fn escapes(mut self, escapes: Escapes) -> Section {
+        self.escapes = escapes;
+        return self;
+    }
+
+    // the raw body is lexed by sub_lexer, like an sql string or a regex with rules of its own. the
+    // tokens it returns are located as if the body was the whole input and are moved to where the
+    // body is, the section becomes a Token::Interpolated holding them as its only fragment
+    pub fn sub_lex(mut self, sub_lexer: SubLexer) -> Section {
+        self.sub_lexer = Some(sub_lexer);
+        return self;
+    }
+
+    // the character escapes start with, if the section has escapes at all
+    pub(crate) fn escape(&self) -> Option<char> {
+        return match self.escapes {
+            Escapes::Off => None,
+            Escapes::Kept(escape) | Escapes::Stripped(escape) | Escapes::Decoded(escape) => Some(escape),
         };
     }
+
+    // the start delimiter is followed by a word, like EOF in <<EOF, and the body is every line after
+    // the current one up to a line holding only that word. end is not used. the rest of the start
+    // line is lexed as usual and the section token comes after its tokens
+    pub fn heredoc(mut self, indent: Heredoc) -> Section {
+        self.heredoc = Some(indent);
+        return self;
+    }
+}
+
+// removes the leading spaces and tabs every line that is not blank starts with
+fn strip_indent(body: &str) -> Cow<'_, str> {
+    let indent = body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    if indent == 0 {
+        return Cow::Borrowed(body);
+    }
+
+    return Cow::Owned(body.split_inclusive('\n').map(|line| {
+        let whitespace = line.len() - line.trim_start_matches([' ', '\t']).len();
+        return &line[whitespace.min(indent)..];
+    }).collect());
+}
+
+// an interpolated section token from its fragments and the text after the last interpolation
+fn interpolated<'a>(name: &'a str, mut fragments: Vec<FragmentRef<'a>>, text: Cow<'a, str>, loc: Loc) -> TokenRef<'a> {
+    if !text.is_empty() {
+        fragments.push(FragmentRef::Text(text));
+    }
+    return TokenRef::Interpolated(name, fragments, loc);
 }
 
 impl Lexer {
@@ -144,180 +1105,2505 @@ impl Lexer {
             symbols: symbols.to_vec(),
             buffer: Vec::new(),
             allow_whitespace,
+            whitespace: vec![' '],
+            allow_exponent: true,
+            allow_digit_separators: true,
+            allow_numbers: true,
+            allow_leading_dot: false,
+            allow_trailing_dot: true,
+            decimal_separator: '.',
+            integer_overflow: IntegerOverflow::Float,
+            number_suffixes: Vec::new(),
+            pragma_prefix: None,
+            line_directive: None,
+            rules: Vec::new(),
+            keyword_tags: Vec::new(),
+            contextual_keywords: Vec::new(),
+            keyword_aliases: Vec::new(),
+            symbol_info: Vec::new(),
+            symbol_pairs: Vec::new(),
+            check_pairs: false,
+            emit_eof: false,
+            tab_width: 4,
+            expand_tabs: false,
+            column_unit: ColumnUnit::Char,
+            case_insensitive_keywords: false,
+            ident_char: is_ident_char,
+            ident_symbols: Vec::new(),
+            split_keywords: false,
+            comment_sections: Vec::new(),
+            shebang: false,
+            emit_shebang: false,
+            transcode_utf16: false,
+            offside: false,
+            emit_newlines: false,
+            strict: false,
+            limits: Limits::default(),
+            emit_unknown: false,
+            xid_idents: false,
+            normalize_idents: false,
+            char_quote: None,
+            map_token: None,
+            map_number: None,
+            hooks_version: None,
+            debug_hook: None,
+            file: FileId::default(),
+            modes: Vec::new(),
+            transitions: Vec::new(),
+            languages: Vec::new(),
+            #[cfg(feature = "mmap")]
+            mapped: None,
+            index: IndexCache::default(),
         };
     }
 
     pub fn load_str(&mut self, string: &str) {
+        self.unmap();
         self.buffer = string.as_bytes().to_vec();
+        self.file = FileId::default();
     }
 
     pub fn load_file(&mut self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.buffer = fs::read(filename)?;
+        self.unmap();
+        let file = FileId::new(filename);
+        self.buffer = self.decode_utf16(self.read_limited(fs::File::open(filename)?, Loc::new(file))?)?;
+        self.file = file;
         return Ok(());
     }
 
-    fn symbols_contain(&self, value: &char) -> Option<&str> {
-        for symbol in &self.symbols {
-            if symbol.0 == *value {
-                return Some(&symbol.1);
-            }
-        }
-        return None;
+    pub fn load_reader(&mut self, reader: impl Read) -> Result<(), Box<dyn std::error::Error>> {
+        self.unmap();
+        self.buffer = self.decode_utf16(self.read_limited(reader, Loc::new(FileId::default()))?)?;
+        self.file = FileId::default();
+        return Ok(());
     }
 
-    fn section_exists(&self, start: &str, end: &str) -> Result<String, ()> {
-        for section in &self.sections {
-            if section.start == start && section.end == end {
-                return Ok(section.name.to_string());
-            }
+    // the file must not be modified while it is mapped, see memmap2::Mmap::map
+    #[cfg(feature = "mmap")]
+    pub fn load_file_mmap(&mut self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let file = fs::File::open(filename)?;
+        self.check_input_limit(file.metadata()?.len() as usize, Loc::new(FileId::new(filename)))?;
+        let mapped = unsafe { memmap2::Mmap::map(&file)? };
+        if is_utf16(&mapped) {
+            // the mapped bytes can't be lexed in place, they are copied out when transcoded
+            self.unmap();
+            self.buffer = self.decode_utf16(mapped.to_vec())?;
+            self.file = FileId::new(filename);
+            return Ok(());
         }
-        return Err(());
+        self.buffer = Vec::new();
+        self.mapped = Some(std::sync::Arc::new(mapped));
+        self.file = FileId::new(filename);
+        return Ok(());
     }
 
-    fn is_section(&self, value: Value) -> Result<StartOrSection, ()> {
-        let mut matches: Vec<String> = Vec::new();
-        for section in &self.sections {
-            if let Value::Start(start) = &value {
-                if &section.start == start {
-                    matches.push(section.end.clone());
-                }
-            } else if let Value::End(start, end) = &value {
-                if &section.end == end && &section.start == start {
-                    return Ok(StartOrSection::Section(section)); // matches is not really needed here
-                }
-            }
+    // drops the loaded input, keeping the configuration. tokenizing never changes the lexer, so
+    // loading new input or resetting is all there is to reusing one
+    pub fn reset(&mut self) {
+        self.unmap();
+        self.buffer = Vec::new();
+        self.file = FileId::default();
+    }
+
+    // bytes with a utf-16 byte order mark are transcoded to utf-8 or refused, see transcode_utf16
+    pub(crate) fn decode_utf16(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        let unit: fn([u8; 2]) -> u16 = match bytes.as_slice() {
+            [0xff, 0xfe, ..] => u16::from_le_bytes,
+            [0xfe, 0xff, ..] => u16::from_be_bytes,
+            _ => return Ok(bytes),
+        };
+
+        if !self.transcode_utf16 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "utf-16 input, set transcode_utf16 to lex it"));
+        }
+        if !bytes.len().is_multiple_of(2) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "utf-16 input with an odd number of bytes"));
         }
 
-        if matches.len() != 0 {
-            return Ok(StartOrSection::Start(matches));
+        let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]])).collect::<Vec<u16>>();
+        let string = String::from_utf16(&units).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        return Ok(string.into_bytes());
+    }
+
+    fn unmap(&mut self) {
+        #[cfg(feature = "mmap")]
+        {
+            self.mapped = None;
         }
-        return Err(());
     }
 
-    fn is_numeric(&self, token: &String, loc: Loc) -> Token {
-        if let Ok(integer) = token.parse::<usize>() {
-            return Token::Integer(integer, loc);
-        } else if let Ok(integer) = token.parse::<f64>() {
-            return Token::Float(integer, loc);
-        } else {
-            return Token::Ident(token.clone(), loc);
+    // the loaded input, either the buffer or the memory mapped file
+    fn source_bytes(&self) -> &[u8] {
+        #[cfg(feature = "mmap")]
+        if let Some(mapped) = &self.mapped {
+            return mapped;
         }
+        return &self.buffer;
     }
 
-    fn lex_token(&self, token: &String, loc: Loc) -> Option<Token> {
-        if token == "\n" {
-            return None;
-        } else if token == "" {
-            if self.allow_whitespace {
-                return Some(Token::Ident(" ".to_string(), loc));
-            } else {
-                return None;
-            }
-        } else if self.keywords.contains(&token) {
-            return Some(Token::Keyword(token.clone(), loc));
-        } else if token.len() == 1 {
-            let character = token.chars().collect::<Vec<char>>()[0];
-            if let Some(symbol_name) = self.symbols_contain(&character) {
-                return Some(Token::Symbol(character, symbol_name.to_string(), loc));
-            } else {
-                return Some(self.is_numeric(token, loc));
+    pub fn excerpt(&self, token: &Token, context_lines: usize) -> String {
+        let source = String::from_utf8_lossy(self.source_bytes());
+        let lines = source.lines().collect::<Vec<&str>>();
+        let line = token.loc().line as usize;
+        let column = token.loc().col as usize;
+
+        let first = line.saturating_sub(context_lines).max(1);
+        let last = (line + context_lines).min(lines.len());
+        let width = last.max(line).to_string().len();
+
+        let mut excerpt = String::new();
+        for number in first..=last {
+            excerpt.push_str(&format!("{:>width$} | {}\n", number, lines[number - 1], width = width));
+            if number == line {
+                excerpt.push_str(&format!("{:>width$} | {}^\n", "", " ".repeat(column.saturating_sub(1)), width = width));
             }
-        } else if let Ok(name) = self.section_exists(&token[0..1], &token[token.len()-1..token.len()]) {
-            return Some(Token::Section(name, token[1..token.len() - 1].to_string(), loc));
-        } else {
-            return Some(self.is_numeric(token, loc));
         }
+        return excerpt;
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
-        if self.symbols_contain(&' ').is_none() {
-            self.symbols.push((' ', "Space".to_string()));
+    pub fn add_keyword_tagged(&mut self, keyword: &str, tags: &[&str]) {
+        if !self.keywords.iter().any(|existing| existing == keyword) {
+            self.keywords.push(keyword.to_string());
         }
 
-        let mut mode = Mode::Normal;
-        let mut token = String::new();
-        let mut tokens: Vec<Token> = Vec::new();
-        let mut section: Vec<Section> = Vec::new();
-        let mut loc = (1, 1);
-
-        let mut index = 0;
-        while index < self.buffer.len() {
-            let byte = &self.buffer[index];
-            let character = String::from_utf8(vec![byte.clone()])?;
-            if (index + 1) < self.buffer.len() {
-                if mode == Mode::Normal {
-                    if let Ok(StartOrSection::Start(ends)) = self.is_section(Value::Start(character.clone())) {
-                        token = token + &character;
-                        for end in ends {
-                            section.push(Section::from_end(end.clone()));
-                            let idx = section.len() - 1;
-                            section[idx].start = character.clone();
-                        }
-                        mode = Mode::Section;
-                    } else if character.as_str() == "\n" {
-                        self.lex_token(&token, loc).map(|t| tokens.push(t));
-                        token = String::new();
-                    } else if character.as_str() != " " {
-                        token = token + &character;
-                    }
-                    if (self.symbols_contain(&char::from(byte.clone())).is_some() || self.symbols_contain(&char::from(self.buffer[index + 1])).is_some()) &&
-                       section.len() == 0 { // making sure we arent lexing symbols when we're in a section
-                        self.lex_token(&token, loc).map(|t| tokens.push(t));
-                        token = String::new();
-                    }
-                } else if mode == Mode::Section {
-                    if &character == "\\" {
-                        if index + 1 >= self.buffer.len() {
-                            return Ok(tokens);
-                        } else {
-                            index += 1;
-                            token = token + &(self.buffer[index] as char).to_string();
-                        }
-                    } else if self.is_section(Value::End(section[0].start.to_string(), character.clone())).is_ok() || index + 2 >= self.buffer.len() { // index doesnt matter here because all indexes has the same start
-                        token = token + &character;
-                        self.lex_token(&token, loc).map(|t| tokens.push(t));
-                        section = Vec::new();
-                        token = String::new();
-                        mode = Mode::Normal;
-                    } else {
-                        token = token + &character;
-                    }
-                }
-            }
+        for tag in tags {
+            self.keyword_tags.push((keyword.to_string(), KeywordTag::from(*tag)));
+        }
+    }
 
-            if &character == "\n" {
-                loc.0 += 1;
-                loc.1 = 1;
-            } else {
-                loc.1 += 1;
-            }
-            index += 1;
+    // like add_keyword_tagged with a tag of the caller's own type, which Lexer::keyword_tag returns
+    // for the keyword's tokens as it was given. both kinds of tag live in keyword_tags, so a keyword
+    // may have string tags and tags of several types at once
+    pub fn add_keyword_typed<T: std::any::Any + Send + Sync>(&mut self, keyword: &str, tag: T) {
+        if !self.keywords.iter().any(|existing| existing == keyword) {
+            self.keywords.push(keyword.to_string());
         }
+        self.keyword_tags.push((keyword.to_string(), KeywordTag::new(tag)));
+    }
 
-        return Ok(tokens);
+    // alias is lexed as Token::Keyword(keyword) wherever keyword is active, so parsers only match the
+    // keyword. the spelling that was used is returned by Lexer::keyword_spelling
+    pub fn add_keyword_alias(&mut self, alias: &str, keyword: &str) {
+        if !self.keywords.iter().any(|existing| existing == keyword) {
+            self.keywords.push(keyword.to_string());
+        }
+        self.keyword_aliases.push((alias.to_string(), keyword.to_string()));
     }
-}
 
+    // how a keyword token of the loaded input was written, which for an alias or a case insensitive
+    // keyword differs from the keyword it was lexed as. None for other tokens
+    pub fn keyword_spelling(&self, token: &Token) -> Option<&str> {
+        let Token::Keyword(keyword, loc) = token else {
+            return None;
+        };
+        // the token is located at its last character
+        let source = std::str::from_utf8(self.source_bytes()).ok()?;
+        let end = loc.offset + source.get(loc.offset..)?.chars().next()?.len_utf8();
+        return self.keyword_aliases.iter()
+            .filter(|(_, canonical)| canonical == keyword)
+            .map(|(alias, _)| alias.as_str())
+            .chain([keyword.as_str()])
+            .filter_map(|spelling| Some((spelling, source.get(end.checked_sub(spelling.len())?..end)?)))
+            .filter(|(spelling, text)| if self.case_insensitive_keywords { spelling.eq_ignore_ascii_case(text) } else { spelling == text })
+            .map(|(_, text)| text)
+            .max_by_key(|text| text.len());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // keyword stays an Ident so it can still name things, where the grammar expects the keyword the
+    // parser checks Token::ident_is_keyword_candidate and takes it with Lexer::contextual_keyword
+    pub fn add_contextual_keyword(&mut self, keyword: &str) {
+        if !self.contextual_keywords.iter().any(|existing| existing == keyword) {
+            self.contextual_keywords.push(keyword.to_string());
+        }
+    }
 
-    #[test]
-    fn load_test() -> Result<(), Box<dyn std::error::Error>> {
-        let mut lexer = Lexer::new(
-            &["def".to_string(), "if".to_string(), "return".to_string()],
-            &[Section::new("string", "\"", "\"")],
-            &[(':', "column".to_string()), ('(', "openbrace".to_string()), (')', "closebrace".to_string()), (' ', "space".to_string())],
-            true,
-        );
+    fn find_contextual_keyword(&self, ident: &str) -> Option<&str> {
+        return self.contextual_keywords.iter()
+            .find(|keyword| if self.case_insensitive_keywords { keyword.eq_ignore_ascii_case(ident) } else { *keyword == ident })
+            .map(String::as_str);
+    }
+
+    // the identifier as a Token::Keyword when it spells a contextual keyword, None otherwise
+    pub fn contextual_keyword(&self, token: &Token) -> Option<Token> {
+        if let Token::Ident(ident, loc) = token {
+            return self.find_contextual_keyword(ident).map(|keyword| Token::Keyword(keyword.to_string(), *loc));
+        }
+        return None;
+    }
+
+    // the tags of a keyword token, empty for any other token
+    pub fn tags(&self, token: &Token) -> Vec<&str> {
+        if let Token::Keyword(keyword, _) = token {
+            return self.keyword_tags.iter().filter(|(tagged, _)| tagged == keyword).filter_map(|(_, tag)| tag.as_str()).collect();
+        }
+        return Vec::new();
+    }
+
+    // the tag of keyword's type that was added first, see Lexer::add_keyword_typed
+    pub fn keyword_tag<T: std::any::Any>(&self, token: &Token) -> Option<&T> {
+        let Token::Keyword(keyword, _) = token else {
+            return None;
+        };
+        return self.keyword_tags.iter()
+            .filter(|(tagged, _)| tagged == keyword)
+            .find_map(|(_, tag)| tag.get::<T>());
+    }
+
+    pub fn has_tag(&self, token: &Token, tag: &str) -> bool {
+        return self.tags(token).contains(&tag);
+    }
+
+    pub fn keywords_tagged(&self, tag: &str) -> Vec<&str> {
+        return self.keyword_tags.iter().filter(|(_, existing)| existing.as_str() == Some(tag)).map(|(keyword, _)| keyword.as_str()).collect();
+    }
+
+    // the matcher receives the remaining input and returns the length in bytes of the prefix it claims
+    pub fn add_rule(&mut self, name: &str, matcher: impl Fn(&str) -> Option<usize> + Send + Sync + 'static) {
+        self.rules.push(Rule {
+            name: name.to_string(),
+            matcher: Arc::new(matcher),
+        });
+    }
+
+    // the pattern is anchored to the current position, matches become Token::Custom like any other rule
+    #[cfg(feature = "regex")]
+    pub fn add_regex_rule(&mut self, name: &str, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let regex = regex::Regex::new(&format!("^(?:{})", pattern))?;
+        self.add_rule(name, move |input: &str| regex.find(input).map(|found| found.end()));
+        return Ok(());
+    }
+
+    // the longest prefix claimed by a rule, earlier rules win ties
+    fn match_rule<'a>(&'a self, rest: &str) -> Option<(&'a str, usize)> {
+        let mut best: Option<(&str, usize)> = None;
+        for rule in &self.rules {
+            if let Some(length) = (rule.matcher)(rest) {
+                if length > 0 && length <= rest.len() && rest.is_char_boundary(length) && best.is_none_or(|(_, best)| length > best) {
+                    best = Some((&rule.name, length));
+                }
+            }
+        }
+        return best;
+    }
+
+    pub fn add_mode(&mut self, mode: LexMode) {
+        self.modes.push(mode);
+    }
+
+    pub fn add_transition(&mut self, mode: Option<&str>, trigger: &str, action: ModeAction) {
+        self.transitions.push(ModeTransition {
+            mode: mode.map(str::to_string),
+            trigger: trigger.to_string(),
+            action,
+        });
+    }
+
+    pub fn register_language(&mut self, name: &str, lexer: Lexer) {
+        self.languages.retain(|(existing, _)| existing != name);
+        self.languages.push((name.to_string(), lexer));
+    }
+
+    fn rule_set(&self, run: &Run) -> RuleSet<'_> {
+        if let Some(mode) = run.modes.last().map(|mode| &self.modes[*mode]) {
+            return RuleSet {
+                keywords: &mode.keywords,
+                sections: &mode.sections,
+                symbols: &mode.symbols,
+            };
+        }
+
+        return RuleSet {
+            keywords: &self.keywords,
+            sections: &self.sections,
+            symbols: &self.symbols,
+        };
+    }
+
+    // the hashed lookups for the rules of rule_set
+    fn rule_index<'r>(&self, run: &'r Run) -> &'r SetIndex {
+        return run.index.set(run.modes.last().copied());
+    }
+
+    fn transition(&self, run: &mut Run, token: &TokenRef, loc: Loc) -> Result<(), LexError> {
+        let trigger = match token {
+            TokenRef::Keyword(keyword, _) => keyword.as_ref(),
+            TokenRef::Symbol(_, name, _) => *name,
+            _ => return Ok(()),
+        };
+
+        let current = run.modes.last().map(|mode| self.modes[*mode].name.as_str());
+        let transition = self.transitions.iter().find(|transition| transition.mode.as_deref() == current && transition.trigger == trigger);
+        match transition.map(|transition| &transition.action) {
+            Some(ModeAction::Push(name)) => {
+                let mode = self.modes.iter().position(|mode| mode.name == *name).ok_or_else(|| LexError::UnknownMode(name.clone(), loc))?;
+                event::report(self.debug_hook, LexEvent::PushMode(name, loc));
+                run.modes.push(mode);
+            },
+            Some(ModeAction::Pop) => {
+                if let Some(mode) = run.modes.pop() {
+                    event::report(self.debug_hook, LexEvent::PopMode(&self.modes[mode].name, loc));
+                }
+            },
+            None => {},
+        }
+        return Ok(());
+    }
+
+    // lexes the accumulated token and applies any mode transition it triggers. with split_keywords
+    // keywords are split out of the token at identifier boundaries, otherwise the whole token must match
+    fn flush<'a>(&'a self, run: &mut Run, scanned: &mut Scanned<'a>, source: &'a str, span: Range<usize>, loc: Loc) -> Result<(), LexError> {
+        let token = &source[span.clone()];
+        if self.split_keywords {
+            let mut start = 0;
+            for (begin, end) in self.ident_runs(token) {
+                if self.match_keyword(run, &token[begin..end]).is_some() {
+                    if start < begin {
+                        self.push_token(run, scanned, source, span.start + start..span.start + begin, loc)?;
+                    }
+                    self.push_token(run, scanned, source, span.start + begin..span.start + end, loc)?;
+                    start = end;
+                }
+            }
+
+            if start > 0 {
+                if start < token.len() {
+                    self.push_token(run, scanned, source, span.start + start..span.end, loc)?;
+                }
+                return Ok(());
+            }
+        }
+        return self.push_token(run, scanned, source, span, loc);
+    }
+
+    fn push_token<'a>(&'a self, run: &mut Run, scanned: &mut Scanned<'a>, source: &'a str, span: Range<usize>, loc: Loc) -> Result<(), LexError> {
+        if let Some(t) = self.lex_token(run, &source[span.clone()], loc) {
+            // an integer literal only becomes an error when it overflows
+            if let TokenRef::Error(text, _) = &t {
+                if self.integer_overflow == IntegerOverflow::Error && self.numeric_kind(run, text) == Some(false) {
+                    scanned.recover(run.recover, Err::<(), LexError>(LexError::IntegerOverflow(text.to_string(), loc)))?;
+                }
+            }
+            scanned.recover(run.recover, self.transition(run, &t, loc))?;
+            scanned.push(t, span);
+        }
+        return Ok(());
+    }
+
+    // the byte ranges of the maximal runs of identifier characters in token
+    fn ident_runs(&self, token: &str) -> Vec<(usize, usize)> {
+        let mut runs = Vec::new();
+        let mut begin = None;
+        for (index, character) in token.char_indices() {
+            match (begin, self.is_ident_char(character)) {
+                (None, true) => begin = Some(index),
+                (Some(start), false) => {
+                    runs.push((start, index));
+                    begin = None;
+                },
+                _ => {},
+            }
+        }
+
+        if let Some(start) = begin {
+            runs.push((start, token.len()));
+        }
+        return runs;
+    }
+
+    // ident_char, or the unicode identifier class with xid_idents
+    fn is_ident_char(&self, character: char) -> bool {
+        #[cfg(feature = "unicode")]
+        if self.xid_idents {
+            return is_xid_ident_char(character);
+        }
+        return (self.ident_char)(character);
+    }
+
+    fn symbols_contain<'a>(&'a self, run: &Run, value: &char) -> Option<&'a str> {
+        return self.rule_index(run).symbols.get(value).map(|position| self.rule_set(run).symbols[*position].1.as_str());
+    }
+
+    // whether character ends the token before it, whitespace and symbols do
+    fn separates(&self, run: &Run, character: char) -> bool {
+        return self.whitespace.contains(&character) || self.symbols_contain(run, &character).is_some();
+    }
+
+    // whether the symbol starting rest stays in the identifier before it. the identifier must not
+    // start with a digit, so 1-2 is still a subtraction
+    fn continues_ident(&self, before: &str, rest: &str) -> bool {
+        let mut chars = rest.chars();
+        let Some((_, position)) = chars.next().and_then(|symbol| self.ident_symbols.iter().find(|(ident_symbol, _)| *ident_symbol == symbol)) else {
+            return false;
+        };
+        if !before.chars().next().is_some_and(|first| self.is_ident_char(first) && !first.is_ascii_digit()) {
+            return false;
+        }
+        return match position {
+            IdentSymbol::Inner => chars.next().is_some_and(|next| self.is_ident_char(next) || self.ident_symbols.iter().any(|(symbol, _)| *symbol == next)),
+            IdentSymbol::Trailing => true,
+        };
+    }
+
+    // symbol is lexed as part of an identifier it is written in rather than on its own, so with '-'
+    // foo-bar is one identifier while a - b is still a subtraction
+    pub fn add_ident_symbol(&mut self, symbol: char, position: IdentSymbol) {
+        self.ident_symbols.retain(|(existing, _)| *existing != symbol);
+        self.ident_symbols.push((symbol, position));
+    }
+
+    // all sections sharing the longest start delimiter found at the start of rest
+    fn section_starts(&self, run: &Run, rest: &str) -> Vec<&Section> {
+        let sections = self.rule_set(run).sections;
+        let longest = sections.iter()
+            .filter(|section| !section.start.is_empty() && rest.starts_with(&section.start))
+            .map(|section| section.start.len())
+            .max();
+
+        return sections.iter()
+            .filter(|section| Some(section.start.len()) == longest && rest.starts_with(&section.start))
+            .collect();
+    }
+
+    // sections listed in comment_sections become comments, named by the section as their kind
+    fn section_token<'a>(&self, name: &'a str, body: Cow<'a, str>, loc: Loc) -> TokenRef<'a> {
+        if self.comment_sections.iter().any(|comment| comment == name) {
+            return TokenRef::Comment(name, body, loc);
+        }
+        return TokenRef::Section(name, body, loc);
+    }
+
+    // the length of the body of section at the start of rest, up to its end delimiter. escapes,
+    // interpolations and nested sections are skipped whole, None when the input ends first
+    fn section_body_length(&self, run: &Run, section: &Section, rest: &str) -> Option<usize> {
+        let mut index = 0;
+        loop {
+            let remaining = &rest[index..];
+            if !section.end.is_empty() && remaining.starts_with(section.end.as_str()) {
+                return Some(index);
+            }
+
+            if let Some((open, close)) = section.interpolation.as_ref().filter(|(open, _)| remaining.starts_with(open.as_str())) {
+                index += open.len() + self.interpolation_end(run, &remaining[open.len()..], open, close)? + close.len();
+            } else if let Some(nested) = self.nested_start(run, section, remaining) {
+                index += nested.start.len();
+                index += self.section_body_length(run, nested, &rest[index..])? + nested.end.len();
+            } else {
+                let character = remaining.chars().next()?;
+                index += character.len_utf8();
+                if Some(character) == section.escape() {
+                    index += rest[index..].chars().next().map_or(0, char::len_utf8);
+                }
+            }
+        }
+    }
+
+    // a section section allows in its body starting at rest, see Section::nest
+    fn nested_start<'a>(&'a self, run: &Run, section: &Section, rest: &str) -> Option<&'a Section> {
+        if section.nested.is_empty() {
+            return None;
+        }
+        return self.section_starts(run, rest).into_iter().find(|start| section.nested.contains(&start.name));
+    }
+
+    // the length of an interpolation body up to its close marker. open markers and the bracket the
+    // close marker mirrors nest, and nested sections are skipped whole. None if it is never closed
+    fn interpolation_end(&self, run: &Run, rest: &str, open: &str, close: &str) -> Option<usize> {
+        let bracket = match close.chars().next() {
+            Some('}') => Some('{'),
+            Some(')') => Some('('),
+            Some(']') => Some('['),
+            _ => None,
+        };
+
+        let mut depth = 0;
+        let mut index = 0;
+        while let Some(character) = rest[index..].chars().next() {
+            let remaining = &rest[index..];
+            if remaining.starts_with(close) {
+                if depth == 0 {
+                    return Some(index);
+                }
+                depth -= 1;
+                index += close.len();
+            } else if remaining.starts_with(open) {
+                depth += 1;
+                index += open.len();
+            } else if let Some(section) = self.section_starts(run, remaining).first() {
+                index += section.start.len();
+                index += self.section_body_length(run, section, &rest[index..])? + section.end.len();
+            } else {
+                if Some(character) == bracket {
+                    depth += 1;
+                }
+                index += character.len_utf8();
+            }
+        }
+        return None;
+    }
+
+    // whether strict mode accepts character after the token text lexed so far. signs that are not
+    // symbols are only part of a token as the exponent sign of a number
+    fn expected_char(&self, run: &Run, token: &str, character: char) -> bool {
+        return match character {
+            '\t' | '\r' | '\n' | '.' => true,
+            _ if character == self.decimal_separator => true,
+            _ if self.whitespace.contains(&character) => true,
+            '+' | '-' if self.symbols_contain(run, &character).is_some() => true,
+            '+' | '-' => run.allow_exponent && token.starts_with(|first: char| first.is_ascii_digit()) && token.ends_with(['e', 'E']),
+            _ => self.is_ident_char(character) || self.symbols_contain(run, &character).is_some(),
+        };
+    }
+
+    // lexes the bodies of the heredocs started on the line that just ended, in the order they were
+    // started. returns where the last one ends, None when the input before limit cuts one short
+    #[allow(clippy::too_many_arguments)]
+    fn heredoc_bodies<'a>(&'a self, run: &Run, scanned: &mut Scanned<'a>, source: &'a str, mut index: usize, limit: usize, heredocs: Vec<(&'a Section, &'a str)>, loc: &mut Loc, finish: bool) -> Result<Option<usize>, LexError> {
+        for (section, word) in heredocs {
+            let start = (index, *loc);
+            let end = loop {
+                let line_end = source[index..].find('\n').map(|end| index + end);
+                if !finish && line_end.is_none_or(|end| end >= limit) {
+                    return Ok(None);
+                }
+
+                let line = &source[index..line_end.unwrap_or(source.len())];
+                let found = match section.heredoc {
+                    Some(Heredoc::StripIndent) => line.trim() == word,
+                    _ => line.trim_end_matches('\r') == word,
+                };
+                if found {
+                    break Some((index, line.len()));
+                }
+                match line_end {
+                    Some(end) => index = end + 1,
+                    None => break None,
+                }
+            };
+
+            let body = &source[start.0..end.map_or(source.len(), |(end, _)| end)];
+            self.advance(loc, body);
+            match end {
+                Some((end, length)) => {
+                    let body = if section.heredoc == Some(Heredoc::StripIndent) { strip_indent(body) } else { Cow::Borrowed(body) };
+                    scanned.push(self.section_token(&section.name, body, *loc), start.0..end + length);
+                    self.advance(loc, &source[end..end + length]);
+                    index = end + length;
+                },
+                None if section.unterminated == Unterminated::Section && !run.recover => {
+                    scanned.push(self.section_token(&section.name, Cow::Borrowed(body), *loc), start.0..source.len());
+                    index = source.len();
+                },
+                None => {
+                    if section.unterminated != Unterminated::Token || run.recover {
+                        scanned.recover(run.recover, Err::<(), LexError>(LexError::UnterminatedSection(section.name.clone(), start.1)))?;
+                    }
+                    scanned.push(TokenRef::Error(body, *loc), start.0..source.len());
+                    index = source.len();
+                },
+            }
+        }
+        return Ok(Some(index));
+    }
+
+    fn section_end<'a>(&self, candidates: &[&'a Section], rest: &str) -> Option<&'a Section> {
+        return candidates.iter()
+            .filter(|section| !section.end.is_empty() && rest.starts_with(&section.end))
+            .max_by_key(|section| section.end.len())
+            .copied();
+    }
+
+    fn scan_digits(&self, run: &Run, bytes: &[u8], mut index: usize) -> usize {
+        let start = index;
+        while index < bytes.len() && (bytes[index].is_ascii_digit() || (run.allow_digit_separators && bytes[index] == b'_' && index > start)) {
+            index += 1;
+        }
+        return index;
+    }
+
+    // returns Some(is_float) if the whole token is a numeric literal. every character of a literal
+    // is ascii, so any other character stops the scan short of the end
+    fn numeric_kind(&self, run: &Run, token: &str) -> Option<bool> {
+        let bytes = token.as_bytes();
+        let mut is_float = false;
+
+        if !self.starts_number(token) {
+            return None;
+        }
+
+        let mut index = self.scan_digits(run, bytes, 0);
+
+        if index < bytes.len() && self.is_decimal_separator(bytes[index]) {
+            let fraction = self.scan_digits(run, bytes, index + 1);
+            if fraction == index + 1 && !self.allow_trailing_dot {
+                return None;
+            }
+            is_float = true;
+            index = fraction;
+        }
+
+        if run.allow_exponent && index < bytes.len() && (bytes[index] == b'e' || bytes[index] == b'E') {
+            let mut exponent = index + 1;
+            if exponent < bytes.len() && (bytes[exponent] == b'+' || bytes[exponent] == b'-') {
+                exponent += 1;
+            }
+
+            let end = self.scan_digits(run, bytes, exponent);
+            if end == exponent {
+                return None;
+            }
+
+            is_float = true;
+            index = end;
+        }
+
+        if index != bytes.len() {
+            return None;
+        }
+        return Some(is_float);
+    }
+
+    fn starts_number(&self, token: &str) -> bool {
+        let bytes = token.as_bytes();
+        return bytes.first().is_some_and(u8::is_ascii_digit) || (self.allow_leading_dot && bytes.first().is_some_and(|first| self.is_decimal_separator(*first)) && bytes.get(1).is_some_and(u8::is_ascii_digit));
+    }
+
+    fn is_decimal_separator(&self, byte: u8) -> bool {
+        return self.decimal_separator.is_ascii() && byte == self.decimal_separator as u8;
+    }
+
+    fn is_numeric<'a>(&'a self, run: &Run, token: &'a str, loc: Loc) -> TokenRef<'a> {
+        if !self.allow_numbers || !self.starts_number(token) {
+            return TokenRef::Ident(token, loc);
+        }
+
+        if let Some(token) = self.map_number.and_then(|map_number| map_number(token, loc)) {
+            return token;
+        }
+
+        if let Some(number) = self.parse_number(run, token, loc) {
+            return number;
+        }
+
+        // the longest suffix that leaves a number in front of it
+        let suffix = self.number_suffixes.iter()
+            .filter(|suffix| !suffix.is_empty() && token.len() > suffix.len() && token.ends_with(suffix.as_str()))
+            .filter(|suffix| self.numeric_kind(run, &token[..token.len() - suffix.len()]).is_some())
+            .max_by_key(|suffix| suffix.len());
+        if let Some(suffix) = suffix {
+            return TokenRef::Custom(suffix, &token[..token.len() - suffix.len()], loc);
+        }
+        return TokenRef::Ident(token, loc);
+    }
+
+    fn parse_number<'a>(&self, run: &Run, token: &'a str, loc: Loc) -> Option<TokenRef<'a>> {
+        let is_float = self.numeric_kind(run, token)?;
+        let cleaned = if token.contains('_') { Cow::Owned(token.replace('_', "")) } else { Cow::Borrowed(token) };
+        // the syntax was checked above, the float is parsed in the form rust writes it
+        let cleaned = if is_float && self.decimal_separator != '.' { Cow::Owned(cleaned.replace(self.decimal_separator, ".")) } else { cleaned };
+        if !is_float {
+            if let Ok(integer) = cleaned.parse::<usize>() {
+                return Some(TokenRef::Integer(integer, loc));
+            }
+            match self.integer_overflow {
+                IntegerOverflow::Float => {},
+                // push_token reports the error
+                IntegerOverflow::Error => return Some(TokenRef::Error(token, loc)),
+                IntegerOverflow::BigInteger => return Some(TokenRef::BigInteger(cleaned, loc)),
+            }
+        }
+        return cleaned.parse::<f64>().ok().map(|float| TokenRef::Float(float, loc));
+    }
+
+    // case insensitive matches produce the keyword as it was registered, ignoring ascii case only
+    fn match_keyword<'a>(&'a self, run: &Run, token: &'a str) -> Option<Cow<'a, str>> {
+        #[cfg(feature = "unicode")]
+        if let Some(Cow::Owned(normalized)) = (self.normalize_idents && !token.is_ascii()).then(|| nfc(token)) {
+            return self.match_keyword(run, &normalized).map(|keyword| Cow::Owned(keyword.into_owned()));
+        }
+
+        let (keywords, index) = (self.rule_set(run).keywords, self.rule_index(run));
+        let key = if self.case_insensitive_keywords { Cow::Owned(token.to_ascii_lowercase()) } else { Cow::Borrowed(token) };
+        if !self.case_insensitive_keywords {
+            if index.keywords.contains(token) || run.keywords.iter().any(|keyword| keyword == token) {
+                return Some(Cow::Borrowed(token));
+            }
+        } else if let Some(position) = index.folded.get(key.as_ref()) {
+            return Some(Cow::Borrowed(&keywords[*position]));
+        } else if let Some(keyword) = run.keywords.iter().find(|keyword| keyword.eq_ignore_ascii_case(token)) {
+            return Some(Cow::Owned(keyword.clone()));
+        }
+
+        return run.index.aliases(&key).iter()
+            .map(|position| &self.keyword_aliases[*position].1)
+            .find(|keyword| index.keywords.contains(*keyword) || run.keywords.contains(keyword))
+            .map(|keyword| Cow::Borrowed(keyword.as_str()));
+    }
+
+    fn lex_token<'a>(&'a self, run: &Run, token: &'a str, loc: Loc) -> Option<TokenRef<'a>> {
+        if token == "\n" {
+            return None;
+        } else if token.is_empty() {
+            if run.allow_whitespace {
+                return Some(TokenRef::Ident(" ", loc));
+            } else {
+                return None;
+            }
+        } else if let Some(keyword) = self.match_keyword(run, token) {
+            return Some(TokenRef::Keyword(keyword, loc));
+        } else if let Some(character) = token.chars().next().filter(|character| character.len_utf8() == token.len()) {
+            if let Some(symbol_name) = self.symbols_contain(run, &character) {
+                return Some(TokenRef::Symbol(character, symbol_name, loc));
+            } else {
+                return Some(self.is_numeric(run, token, loc));
+            }
+        } else {
+            return Some(self.is_numeric(run, token, loc));
+        }
+    }
+
+    // the amount of bytes past the start of a character the lexer may inspect before deciding on it,
+    // None when custom rules are registered since a matcher can look arbitrarily far ahead
+    pub fn required_lookahead(&self) -> Option<usize> {
+        if !self.rules.is_empty() {
+            return None;
+        }
+
+        // a character and the next one decide on symbols, and the one after with ident_symbols.
+        // \u{10ffff} is the longest escape
+        let mut lookahead = if self.ident_symbols.is_empty() { 2 * MAX_CHAR_LEN } else { 3 * MAX_CHAR_LEN };
+        for section in self.sections.iter().chain(self.modes.iter().flat_map(|mode| &mode.sections)) {
+            lookahead = lookahead.max(section.start.len()).max(section.end.len());
+            if matches!(section.escapes, Escapes::Decoded(_)) {
+                lookahead = lookahead.max(MAX_ESCAPE_LEN);
+            }
+        }
+        lookahead = lookahead.max(self.line_directive.as_ref().map_or(0, String::len));
+        return Some(lookahead.max(self.pragma_prefix.as_ref().map_or(0, String::len)));
+    }
+
+    // rest starts at the backslash, returns the decoded character and the amount of bytes consumed
+    fn decode_escape(&self, rest: &str, loc: Loc) -> Result<(char, usize), LexError> {
+        // the escape character of the section, a backslash unless it was changed
+        let prefix = rest.chars().next().unwrap_or('\\');
+        let mut chars = rest.chars().skip(1);
+        let escape = chars.next().unwrap_or(prefix);
+        let width = prefix.len_utf8();
+
+        let decoded = match escape {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            other if other == prefix => prefix,
+            'u' => {
+                if chars.next() != Some('{') {
+                    return Err(LexError::InvalidEscape(format!("{}u", prefix), loc));
+                }
+
+                let hex = chars.take_while(|c| *c != '}').collect::<String>();
+                let escape = format!("{}u{{{}}}", prefix, hex);
+                if !rest[width + 2 + hex.len()..].starts_with('}') || hex.is_empty() || hex.len() > 6 {
+                    return Err(LexError::InvalidEscape(escape, loc));
+                }
+
+                let decoded = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                return Ok((decoded.ok_or(LexError::InvalidEscape(escape, loc))?, width + 3 + hex.len()));
+            },
+            other => return Err(LexError::InvalidEscape(format!("{}{}", prefix, other), loc)),
+        };
+        return Ok((decoded, width + escape.len_utf8()));
+    }
+
+    // the heredoc end word following the start delimiter, made of identifier characters
+    fn heredoc_word<'a>(&self, rest: &'a str) -> &'a str {
+        let length = rest.find(|character| !self.is_ident_char(character)).unwrap_or(rest.len());
+        return &rest[..length];
+    }
+
+    // the value of the char literal rest starts with and the amount of bytes it spans
+    fn char_literal(&self, rest: &str, loc: Loc) -> Option<(Result<char, LexError>, usize)> {
+        let quote = self.char_quote?;
+        let body = rest.strip_prefix(quote)?;
+
+        let (value, length) = if let Some(escape) = body.strip_prefix('\\') {
+            // a backslash at the end of the input escapes nothing, like in a section
+            if escape.is_empty() {
+                return None;
+            }
+            let mut escape_loc = loc;
+            self.advance(&mut escape_loc, &rest[..quote.len_utf8()]);
+            match self.decode_escape(body, escape_loc) {
+                Ok((character, length)) => (Ok(character), length),
+                // an invalid escape still ends at the closing quote, if it is on the same line
+                Err(error) => (Err(error), escape.find([quote, '\n']).map(|end| end + 1)?),
+            }
+        } else {
+            let character = body.chars().next().filter(|character| *character != quote && *character != '\n')?;
+            (Ok(character), character.len_utf8())
+        };
+        return body.get(length..)?.starts_with(quote).then_some((value, length + 2 * quote.len_utf8()));
+    }
+
+    // returns the directive following the pragma prefix and the amount of bytes it spans
+    fn read_pragma<'a>(&self, rest: &'a str) -> Option<(&'a str, usize)> {
+        let prefix = self.pragma_prefix.as_ref()?;
+        if !rest.starts_with(prefix.as_str()) {
+            return None;
+        }
+
+        let length = rest.find('\n').unwrap_or(rest.len());
+        return Some((rest[prefix.len()..length].trim(), length));
+    }
+
+    // the line number and file name of the line directive at the start of rest, with the amount of
+    // bytes it spans up to its line break
+    fn read_line_directive<'a>(&self, rest: &'a str, loc: Loc) -> Option<(Result<LineDirective<'a>, LexError>, usize)> {
+        let prefix = self.line_directive.as_ref()?;
+        if !rest.starts_with(prefix.as_str()) {
+            return None;
+        }
+
+        let length = rest.find('\n').unwrap_or(rest.len());
+        let directive = rest[prefix.len()..length].trim();
+        let (line, file) = directive.split_once(char::is_whitespace).map_or((directive, None), |(line, file)| (line, Some(file.trim())));
+        let file = match file {
+            Some(file) => file.strip_prefix('"').and_then(|file| file.strip_suffix('"')).map(Some),
+            None => Some(None),
+        };
+        return match (line.parse::<u32>().ok().filter(|line| *line > 0), file) {
+            (Some(line), Some(file)) => Some((Ok((line, file)), length)),
+            _ => Some((Err(LexError::InvalidLineDirective(directive.to_string(), loc)), length)),
+        };
+    }
+
+    fn apply_pragma(&self, run: &mut Run, directive: &str, loc: Loc) -> Result<(), LexError> {
+        let invalid = || LexError::InvalidPragma(directive.to_string(), loc);
+        let (option, value) = directive.split_once(' ').ok_or_else(invalid)?;
+        let value = value.trim();
+
+        if option == "keyword" {
+            run.keywords.push(value.to_string());
+            return Ok(());
+        }
+
+        let enabled = match value {
+            "on" | "true" => true,
+            "off" | "false" => false,
+            _ => return Err(invalid()),
+        };
+
+        match option {
+            "allow_whitespace" => run.allow_whitespace = enabled,
+            "allow_exponent" => run.allow_exponent = enabled,
+            "allow_digit_separators" => run.allow_digit_separators = enabled,
+            _ => return Err(invalid()),
+        }
+        return Ok(());
+    }
+
+    fn new_run(&self) -> Run {
+        return Run {
+            allow_whitespace: self.allow_whitespace,
+            allow_exponent: self.allow_exponent,
+            allow_digit_separators: self.allow_digit_separators,
+            keywords: Vec::new(),
+            modes: Vec::new(),
+            recover: false,
+            indents: Vec::new(),
+            skip_spans: false,
+            extend: false,
+            index: self.index.get(self),
+            tokens: 0,
+            directives: 0,
+        };
+    }
+
+    // at the start of a line in offside mode, skips the indentation and emits the Indent or Dedent
+    // tokens it implies. blank and comment only lines leave the indentation alone. returns the
+    // length skipped and whether the line has tokens
+    fn offside<'a>(&self, run: &mut Run, scanned: &mut Scanned<'a>, rest: &str, index: usize, loc: &mut Loc) -> Result<(usize, bool), LexError> {
+        let length = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        let width = rest[..length].chars().fold(0, |width, character| match character {
+            '\t' => width + self.tab_width.max(1) - width % self.tab_width.max(1),
+            _ => width + 1,
+        });
+        self.advance(loc, &rest[..length]);
+
+        let line = &rest[length..];
+        if line.is_empty() || line.starts_with('\n') || line.starts_with("\r\n") || self.section_starts(run, line).iter().any(|section| self.comment_sections.contains(&section.name)) {
+            return Ok((length, false));
+        }
+
+        let at = index + length;
+        if width > run.indents.last().copied().unwrap_or(0) {
+            run.indents.push(width);
+            scanned.push(TokenRef::Indent(*loc), at..at);
+        }
+        while run.indents.last().is_some_and(|level| *level > width) {
+            run.indents.pop();
+            scanned.push(TokenRef::Dedent(*loc), at..at);
+        }
+
+        if run.indents.last().copied().unwrap_or(0) != width {
+            scanned.recover(run.recover, Err::<(), LexError>(LexError::InconsistentIndent(*loc)))?;
+        }
+        return Ok((length, true));
+    }
+
+    // finds a byte order mark and, with shebang set, a `#!` first line at the start of the input.
+    // loc is moved past both, which still count towards offsets but not columns. returns the index
+    // lexing starts at and the shebang line without its line break, located at its last character
+    fn preamble<'a>(&self, source: &'a str, loc: &mut Loc) -> (usize, Option<(&'a str, Loc)>) {
+        let mut index = 0;
+        if source.starts_with('\u{feff}') {
+            index = '\u{feff}'.len_utf8();
+            loc.offset = index;
+        }
+
+        let rest = &source[index..];
+        if !self.shebang || !rest.starts_with("#!") || rest.starts_with("#![") {
+            return (index, None);
+        }
+        let raw = rest.split('\n').next().unwrap_or_default();
+        let line = raw.trim_end_matches('\r');
+        let mut end = *loc;
+        self.advance(&mut end, &line[..line.len() - 1]);
+        self.advance(loc, raw);
+        return (index + raw.len(), Some((line, end)));
+    }
+
+    // skips the preamble, emitting the shebang with emit_shebang, see Lexer::preamble
+    fn skip_preamble<'a>(&'a self, scanned: &mut Scanned<'a>, source: &'a str, loc: &mut Loc) -> usize {
+        let (index, shebang) = self.preamble(source, loc);
+        if let Some((line, end)) = shebang.filter(|_| self.emit_shebang) {
+            let start = line.as_ptr() as usize - source.as_ptr() as usize;
+            scanned.push(TokenRef::Shebang(line, end), start..start + line.len());
+        }
+        return index;
+    }
+
+    fn scan<'a>(&'a self, source: &'a str) -> Result<Vec<TokenRef<'a>>, LexError> {
+        let mut run = self.new_run();
+        run.skip_spans = true;
+        let (scanned, _) = self.scan_until(source, source.len(), &mut run, &mut Loc::new(self.file), true)?;
+        return Ok(scanned.tokens);
+    }
+
+    // ends a section the input or, when eol is set, the line ran out on, which started at start and
+    // runs to index. a single line section cut off by a line break is an error rather than a body
+    #[allow(clippy::too_many_arguments)]
+    fn unterminated<'a>(&'a self, run: &Run, scanned: &mut Scanned<'a>, section: &'a Section, source: &'a str, start: (usize, Loc), mut body: Cow<'a, str>, fragments: Vec<FragmentRef<'a>>, index: usize, loc: Loc, eol: bool) -> Result<(), LexError> {
+        let (start, start_loc) = start;
+        let error = LexError::UnterminatedSection(section.name.clone(), start_loc);
+        // the input ending is the end of the last line
+        let unterminated = match section.unterminated {
+            _ if section.line => Unterminated::Section,
+            Unterminated::Section if eol => Unterminated::Error,
+            unterminated => unterminated,
+        };
+        match unterminated {
+            Unterminated::Section if !run.recover || section.line => {
+                if section.keep_delimiters {
+                    body = Cow::Owned(format!("{}{}", &source[start..start + section.start.len()], body));
+                }
+                if section.interpolation.is_some() {
+                    scanned.push(interpolated(&section.name, fragments, body, loc), start..index);
+                } else {
+                    scanned.push(self.section_token(&section.name, body, loc), start..index);
+                }
+            },
+            Unterminated::Token if !run.recover => {
+                scanned.push(TokenRef::Error(&source[start..index], loc), start..index);
+            },
+            _ => {
+                scanned.recover(run.recover, Err::<(), LexError>(error))?;
+                scanned.push(TokenRef::Error(&source[start..index], loc), start..index);
+            },
+        }
+        return Ok(());
+    }
+
+    // lexes source[..limit] using the rest of source as lookahead, returning the tokens and whether
+    // a section was still open at the limit. open sections are only emitted when finish is set, and
+    // with Run::extend they are lexed past the limit instead, see Scanned::end
+    fn scan_until<'a>(&'a self, source: &'a str, limit: usize, run: &mut Run, loc: &mut Loc, finish: bool) -> Result<(Scanned<'a>, bool), LexError> {
+        return self.scan_into(source, limit, run, loc, finish, None);
+    }
+
+    // like scan_until, handing the tokens to sink when there is one
+    fn scan_into<'a>(&'a self, source: &'a str, limit: usize, run: &mut Run, loc: &mut Loc, finish: bool, sink: Option<&'a mut dyn FnMut(TokenRef<'a>)>) -> Result<(Scanned<'a>, bool), LexError> {
+        let mut state = State::Normal;
+        let mut token = 0..0;
+        // tokens are located at their last character, wherever the character ending them is
+        let mut token_loc = *loc;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("scan", file = self.file.name().unwrap_or_default(), limit).entered();
+        let mut scanned = Scanned {
+            skip_spans: run.skip_spans,
+            debug_hook: self.debug_hook,
+            sink,
+            ..Scanned::default()
+        };
+        let mut section: Vec<&Section> = Vec::new();
+        let mut section_start = (0, Loc::default());
+        let mut decoded: Option<String> = None;
+        let mut fragments: Vec<FragmentRef<'a>> = Vec::new();
+        let mut heredocs: Vec<(&Section, &str)> = Vec::new();
+        let mut at_line_start = loc.col == 1;
+        let mut line_has_tokens = loc.col != 1;
+
+        self.check_input_limit(source.len(), *loc)?;
+        let mut index = 0;
+        if loc.offset == 0 {
+            index = self.skip_preamble(&mut scanned, source, loc);
+        }
+        let mut extended = false;
+        let mut stopped = false;
+        while let Some(character) = source[index..].chars().next() {
+            if index >= limit {
+                if !run.extend || finish || !(extended || state == State::Section) {
+                    break;
+                }
+                extended = true;
+                if state == State::Normal && source[..index].ends_with('\n') {
+                    stopped = true;
+                    break;
+                } else if index + character.len_utf8() == source.len() {
+                    // the last character is lookahead, the caller has to lex the rest to the end
+                    break;
+                }
+            }
+            self.check_scan_limits((state == State::Section).then_some(section_start), index, run.tokens + scanned.count, *loc)?;
+            if self.offside && at_line_start && state == State::Normal {
+                at_line_start = false;
+                let (length, has_tokens) = self.offside(run, &mut scanned, &source[index..], index, loc)?;
+                line_has_tokens = has_tokens;
+                if length > 0 {
+                    index += length;
+                    continue;
+                }
+            }
+
+            let rest = &source[index..];
+            let next = rest[character.len_utf8()..].chars().next();
+            if state == State::Normal && (next.is_some() || finish) {
+                let starts = self.section_starts(run, rest);
+                if let Some((directive, length)) = self.read_line_directive(rest, *loc) {
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+
+                    let directive = scanned.recover(run.recover, directive)?;
+                    if directive.is_none() {
+                        scanned.push(TokenRef::Error(rest[..length].trim_end_matches('\r'), *loc), index..index + length);
+                    }
+                    self.advance(loc, &rest[..length]);
+                    if let Some((line, file)) = directive {
+                        // the line break ending the directive moves to the line it names
+                        loc.line = line - 1;
+                        loc.file = file.map_or(loc.file, FileId::new);
+                        run.directives += 1;
+                    }
+                    index += length;
+                    continue;
+                } else if let Some((directive, length)) = self.read_pragma(rest) {
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+
+                    if scanned.recover(run.recover, self.apply_pragma(run, directive, *loc))?.is_some() {
+                        scanned.push(TokenRef::Pragma(directive, *loc), index..index + length);
+                    } else {
+                        scanned.push(TokenRef::Error(directive, *loc), index..index + length);
+                    }
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if let Some((name, length)) = self.match_rule(rest).filter(|_| token.is_empty()) {
+                    scanned.push(TokenRef::Custom(name, &rest[..length], *loc), index..index + length);
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if let Some((value, length)) = self.char_literal(rest, *loc).filter(|_| token.is_empty()) {
+                    // like sections the token is located at its closing quote
+                    let mut end = *loc;
+                    self.advance(&mut end, &rest[..length - self.char_quote.map_or(0, char::len_utf8)]);
+                    match scanned.recover(run.recover, value)? {
+                        Some(character) => scanned.push(TokenRef::Char(character, end), index..index + length),
+                        None => scanned.push(TokenRef::Error(&rest[..length], end), index..index + length),
+                    }
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if let Some(word) = starts.first().filter(|section| section.heredoc.is_some()).map(|section| self.heredoc_word(&rest[section.start.len()..])).filter(|word| !word.is_empty()) {
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+
+                    let length = starts[0].start.len() + word.len();
+                    heredocs.push((starts[0], word));
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if !starts.is_empty() {
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+
+                    let length = starts[0].start.len();
+                    section = starts;
+                    section_start = (index, *loc);
+                    state = State::Section;
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    token = index..index;
+                    continue;
+                } else if character == char::REPLACEMENT_CHARACTER && run.recover {
+                    // invalid utf-8 was replaced before lexing, see tokenize_lossy
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+
+                    let length = character.len_utf8();
+                    scanned.errors.push(LexError::InvalidUtf8(*loc));
+                    scanned.push(TokenRef::Error(&rest[..length], *loc), index..index + length);
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if (self.strict || self.emit_unknown) && !self.expected_char(run, if token.is_empty() { "" } else { &source[token.clone()] }, character) {
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+
+                    let length = character.len_utf8();
+                    if self.strict {
+                        scanned.recover(run.recover, Err::<(), LexError>(LexError::UnexpectedChar(character, *loc)))?;
+                    }
+                    scanned.push(TokenRef::Error(&rest[..length], *loc), index..index + length);
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if character == '\r' && next == Some('\n') {
+                    // the line break of a crlf line, never part of the token before it
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+                } else if character == '\n' {
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+
+                    if self.emit_newlines || (self.offside && line_has_tokens) {
+                        scanned.push(TokenRef::Newline(*loc), index..index + 1);
+                    }
+                    at_line_start = true;
+
+                    if !heredocs.is_empty() {
+                        self.advance(loc, "\n");
+                        let Some(end) = self.heredoc_bodies(run, &mut scanned, source, index + 1, limit, std::mem::take(&mut heredocs), loc, finish)? else {
+                            scanned.closes = Some(vec!["\n"]);
+                            return Ok((scanned, true));
+                        };
+                        index = end;
+                        continue;
+                    }
+                } else if !self.whitespace.contains(&character) {
+                    if token.is_empty() {
+                        token = index..index;
+                    }
+                    token.end = index + character.len_utf8();
+                    token_loc = *loc;
+                    self.check_token_limit(&token, *loc)?;
+                }
+                // a symbol continuing an identifier neither ends it nor is ended by it
+                let in_token = !token.is_empty() && token.end == index + character.len_utf8();
+                let continues = |length: usize| in_token && self.continues_ident(&source[token.start..index + length], &rest[length..]);
+                if (self.separates(run, character) && !continues(0)) || next.is_some_and(|next| self.separates(run, next) && !continues(character.len_utf8())) {
+                    self.flush(run, &mut scanned, source, if token.is_empty() { index..index } else { token }, token_loc)?;
+                    token = 0..0;
+                }
+            } else if state == State::Section {
+                if let Some(end) = self.section_end(&section, rest) {
+                    let mut body = decoded.take().map_or(Cow::Borrowed(&source[token.start..index]), Cow::Owned);
+                    if end.line && body.ends_with('\r') {
+                        body = match body {
+                            Cow::Borrowed(body) => Cow::Borrowed(&body[..body.len() - 1]),
+                            Cow::Owned(body) => Cow::Owned(body[..body.len() - 1].to_string()),
+                        };
+                    }
+                    let length = end.end.len();
+                    if end.keep_delimiters {
+                        body = Cow::Owned(format!("{}{}{}", &source[section_start.0..section_start.0 + section[0].start.len()], body, &rest[..length]));
+                    }
+                    // a section ending in a newline, like a line comment, also ends the line. the
+                    // Newline token gets the line break so their spans don't overlap
+                    let newline = end.end.ends_with('\n') && (self.emit_newlines || (self.offside && line_has_tokens));
+                    let span = section_start.0..index + length - newline as usize;
+                    let sub_lexed = match end.sub_lexer {
+                        Some(sub_lexer) => scanned.recover(run.recover, self.sub_lexed(end, sub_lexer, &source[token.start..index], section_start.1, *loc))?,
+                        None => None,
+                    };
+                    if let Some(sub_lexed) = sub_lexed {
+                        scanned.push(sub_lexed, span);
+                    } else if end.interpolation.is_some() {
+                        scanned.push(interpolated(&end.name, std::mem::take(&mut fragments), body, *loc), span);
+                    } else {
+                        scanned.push(self.section_token(&end.name, body, *loc), span);
+                    }
+
+                    if newline {
+                        scanned.push(TokenRef::Newline(*loc), index + length - 1..index + length);
+                    }
+                    at_line_start |= end.end.ends_with('\n');
+                    section = Vec::new();
+                    token = 0..0;
+                    state = State::Normal;
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if character == '\n' && section.iter().all(|s| !s.allow_newlines) {
+                    // the line break is left to be lexed after the section
+                    let body = decoded.take().map_or(Cow::Borrowed(&source[token.start..index]), Cow::Owned);
+                    self.unterminated(run, &mut scanned, section[0], source, section_start, body, std::mem::take(&mut fragments), index, *loc, true)?;
+                    section = Vec::new();
+                    token = 0..0;
+                    state = State::Normal;
+                    continue;
+                } else if let Some(length) = section.iter()
+                    .find_map(|s| self.nested_start(run, s, rest))
+                    .and_then(|nested| Some(nested.start.len() + self.section_body_length(run, nested, &rest[nested.start.len()..])? + nested.end.len()))
+                {
+                    if let Some(body) = decoded.as_mut() {
+                        body.push_str(&rest[..length]);
+                    }
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if let Some(((open, close), length)) = section.iter()
+                    .find_map(|s| s.interpolation.as_ref())
+                    .filter(|(open, _)| rest.starts_with(open.as_str()))
+                    .and_then(|(open, close)| Some(((open, close), self.interpolation_end(run, &rest[open.len()..], open, close)?)))
+                {
+                    let text = decoded.take().map_or(Cow::Borrowed(&source[token.start..index]), Cow::Owned);
+                    if !text.is_empty() {
+                        fragments.push(FragmentRef::Text(text));
+                    }
+
+                    // lexed with the rules active where the section started, layout tokens are left out
+                    let inner = &source[index + open.len()..index + open.len() + length];
+                    let mut inner_loc = *loc;
+                    self.advance(&mut inner_loc, open);
+                    let (inner, _) = self.scan_until(inner, inner.len(), &mut run.clone(), &mut inner_loc, true)?;
+                    scanned.errors.extend(inner.errors);
+                    fragments.push(FragmentRef::Tokens(inner.tokens.into_iter().filter(|token| !matches!(token, TokenRef::Indent(_) | TokenRef::Dedent(_) | TokenRef::Newline(_) | TokenRef::Eof(_))).collect()));
+
+                    let length = open.len() + length + close.len();
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    token = index..index;
+                    continue;
+                } else if Some(character) == section[0].escape() && next.is_some() && section.iter().any(|s| s.language.is_some() || matches!(s.escapes, Escapes::Kept(_)) || s.sub_lexer.is_some()) {
+                    // embedded bodies stay raw so their tokens can be mapped back onto the source
+                    let length = character.len_utf8() + next.map_or(0, char::len_utf8);
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if Some(character) == section[0].escape() && next.is_some() {
+                    let body = decoded.get_or_insert_with(|| source[token.start..index].to_string());
+                    let length = if section.iter().any(|s| matches!(s.escapes, Escapes::Decoded(_))) {
+                        // invalid escapes are kept as written when recovering
+                        let raw = (next.unwrap_or_default(), character.len_utf8() + next.map_or(0, char::len_utf8));
+                        let (character, length) = scanned.recover(run.recover, self.decode_escape(rest, *loc))?.unwrap_or(raw);
+                        body.push(character);
+                        length
+                    } else {
+                        body.push(next.unwrap_or_default());
+                        character.len_utf8() + next.map_or(0, char::len_utf8)
+                    };
+                    self.advance(loc, &rest[..length]);
+                    index += length;
+                    continue;
+                } else if let Some(body) = decoded.as_mut() {
+                    body.push(character);
+                }
+            }
+
+            self.advance(loc, &rest[..character.len_utf8()]);
+            index += character.len_utf8();
+        }
+
+        // the end of the input flushes whatever is still open, the loop above never looks past it
+        if state == State::Section && finish {
+            let body = decoded.take().map_or(Cow::Borrowed(&source[token.start..index]), Cow::Owned);
+            self.unterminated(run, &mut scanned, section[0], source, section_start, body, fragments, index, *loc, false)?;
+        } else if state == State::Normal && finish && !token.is_empty() {
+            self.flush(run, &mut scanned, source, token, token_loc)?;
+        }
+
+        if finish && !heredocs.is_empty() {
+            index = self.heredoc_bodies(run, &mut scanned, source, index, source.len(), std::mem::take(&mut heredocs), loc, true)?.unwrap_or(index);
+        }
+
+        if finish && self.offside {
+            if line_has_tokens && !at_line_start {
+                scanned.push(TokenRef::Newline(*loc), index..index);
+            }
+            for _ in run.indents.drain(..) {
+                scanned.push(TokenRef::Dedent(*loc), index..index);
+            }
+        }
+
+        if finish && self.emit_eof {
+            scanned.push(TokenRef::Eof(*loc), index..index);
+        }
+        self.check_scan_limits(None, index, run.tokens + scanned.count, *loc)?;
+        run.tokens += scanned.count;
+
+        scanned.end = index;
+        if state == State::Section {
+            scanned.closes = Some(section.iter().flat_map(|section| [section.end.as_str(), if section.allow_newlines { "" } else { "\n" }]).filter(|end| !end.is_empty()).collect());
+        } else if !heredocs.is_empty() || (extended && !stopped) {
+            // the heredoc bodies, or the tokens after a carried section, wait for the line to end
+            scanned.closes = Some(vec!["\n"]);
+        }
+        return Ok((scanned, state == State::Section || !heredocs.is_empty() || (extended && !stopped)));
+    }
+
+    pub(crate) fn finish_token(&self, token: TokenRef) -> Option<Token> {
+        let token = Token::from(token);
+        #[cfg(feature = "unicode")]
+        let token = match token {
+            Token::Ident(ident, loc) if self.normalize_idents => Token::Ident(nfc(&ident).into_owned(), loc),
+            token => token,
+        };
+        return match self.map_token {
+            Some(map_token) => map_token(token),
+            None => Some(token),
+        };
+    }
+
+    pub fn tokenize(&self) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let mut tokens = Vec::new();
+        self.tokenize_into(&mut tokens)?;
+        return Ok(tokens);
+    }
+
+    // clears tokens and fills it like tokenize would, so a vector can be reused across inputs
+    pub fn tokenize_into(&self, tokens: &mut Vec<Token>) -> Result<(), Box<dyn std::error::Error>> {
+        return self.tokenizer(std::str::from_utf8(self.source_bytes())?).tokenize_into(tokens);
+    }
+
+    // like tokenize, dropping the tokens keep rejects as they are produced
+    pub fn tokenize_filtered(&self, mut keep: impl FnMut(&Token) -> bool) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        return Ok(self.tokenize_ref()?.into_iter().filter_map(|token| self.finish_token(token)).filter(|token| keep(token)).collect());
+    }
+
+    // lexes the reader incrementally, one batch of complete lines at a time
+    pub fn tokenize_reader<R: Read>(&self, reader: R) -> TokenReader<'_, R> {
+        return TokenReader::new(self, reader);
+    }
+
+    // never fails, problems become Token::Error where they occurred and are collected in order.
+    // invalid utf-8 is replaced with U+FFFD so a literal U+FFFD in the input is reported as well.
+    // offsets still refer to the raw input, while columns count a replaced sequence as one U+FFFD
+    pub fn tokenize_lossy(&self) -> (Vec<Token>, Vec<LexError>) {
+        let bytes = self.source_bytes();
+        let source = String::from_utf8_lossy(bytes);
+        let (tokens, errors) = self.tokenizer(&source).tokenize_lossy();
+        if let Cow::Borrowed(_) = source {
+            return (tokens, errors);
+        }
+
+        // the offset each replacement starts at in the lexed and the raw input and the length of
+        // the invalid bytes it stands for
+        let mut replaced = Vec::new();
+        let (mut lexed, mut raw) = (0, 0);
+        for chunk in bytes.utf8_chunks() {
+            lexed += chunk.valid().len();
+            raw += chunk.valid().len();
+            if !chunk.invalid().is_empty() {
+                replaced.push((lexed, raw, chunk.invalid().len()));
+                lexed += char::REPLACEMENT_CHARACTER.len_utf8();
+                raw += chunk.invalid().len();
+            }
+        }
+        let to_raw = |loc: Loc| {
+            let offset = match replaced.partition_point(|(start, _, _)| *start <= loc.offset).checked_sub(1).map(|index| replaced[index]) {
+                Some((start, raw, _)) if loc.offset < start + char::REPLACEMENT_CHARACTER.len_utf8() => raw,
+                Some((start, raw, length)) => loc.offset - start - char::REPLACEMENT_CHARACTER.len_utf8() + raw + length,
+                None => loc.offset,
+            };
+            return Loc { offset, ..loc };
+        };
+        return (tokens.into_iter().map(|token| token.map_loc(&to_raw)).collect(), errors.into_iter().map(|error| error.map_loc(&to_raw)).collect());
+    }
+
+    pub fn tokenize_ref(&self) -> Result<Vec<TokenRef<'_>>, Box<dyn std::error::Error>> {
+        return Ok(self.scan(std::str::from_utf8(self.source_bytes())?)?);
+    }
+
+    // sections with an embedded language are lexed again and attached to the file, see TokenFile::embedded.
+    // a byte order mark and, with shebang set, a `#!` first line are left out of the tokens and the
+    // file lines and recorded in the metadata instead
+    pub fn tokenize_file(&self) -> Result<TokenFile, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let mut origin = Loc::new(self.file);
+        let (index, shebang) = self.preamble(source, &mut origin);
+        let mut rest = &source[index..];
+        // the line break after the shebang goes with it, so the lines of the file start after it
+        if shebang.is_some() && rest.starts_with('\n') {
+            self.advance(&mut origin, "\n");
+            rest = &rest[1..];
+        }
+
+        let mut metadata = FileMetadata {
+            bom: source.starts_with('\u{feff}'),
+            shebang: shebang.map(|(line, _)| line.to_string()),
+            trailing_newline: source.ends_with('\n'),
+            ..FileMetadata::default()
+        };
+        let file = self.lex_file(rest, origin)?;
+        metadata.banner = file.iter()
+            .map_while(|token| if let Token::Comment(_, text, _) = token { Some(text.clone()) } else { None })
+            .collect();
+        return Ok(file.with_metadata(metadata));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: u32, col: u32, offset: usize) -> Loc {
+        return Loc { line, col, offset, ..Loc::default() };
+    }
+
+    #[test]
+    fn load_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &["def".to_string(), "if".to_string(), "return".to_string()],
+            &[Section::new("string", "\"", "\"")],
+            &[(':', "column".to_string()), ('(', "openbrace".to_string()), (')', "closebrace".to_string()), (' ', "space".to_string())],
+            true,
+        );
 
         //lexer.load_str("def test(): \" return 0 ");
         lexer.load_str("\"  ");
 
-        println!("tokens: {:?}", lexer.tokenize()?);
+        println!("{}", format_token_table(&lexer.tokenize()?));
+        return Ok(());
+    }
+
+    #[test]
+    fn numeric_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+
+        lexer.load_str("1e9 6.022e23 1_000_000 inf 1e ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[0].is_float()?, 1e9);
+        assert_eq!(tokens[1].is_float()?, 6.022e23);
+        assert_eq!(tokens[2].is_integer()?, 1_000_000);
+        assert_eq!(tokens[3].is_ident()?, "inf");
+        assert_eq!(tokens[4].is_ident()?, "1e");
+
+        lexer.allow_exponent = false;
+        lexer.allow_digit_separators = false;
+        lexer.load_str("1e9 1_000 ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "1e9");
+        assert_eq!(tokens[1].is_ident()?, "1_000");
+        return Ok(());
+    }
+
+    #[test]
+    fn numeric_bytes_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('→', "arrow".to_string())], false);
+
+        // numbers are scanned byte by byte, a character that is not ascii ends the literal
+        lexer.load_str("1é 2_5 3.5e+2 → x→y ٣ 7");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens, vec![
+            Token::Ident("1é".to_string(), at(1, 2, 1)),
+            Token::Integer(25, at(1, 6, 6)),
+            Token::Float(350.0, at(1, 13, 13)),
+            Token::Symbol('→', "arrow".to_string(), at(1, 15, 15)),
+            Token::Ident("x".to_string(), at(1, 17, 19)),
+            Token::Symbol('→', "arrow".to_string(), at(1, 18, 20)),
+            Token::Ident("y".to_string(), at(1, 19, 23)),
+            Token::Ident("٣".to_string(), at(1, 21, 25)),
+            Token::Integer(7, at(1, 23, 28)),
+        ]);
+        return Ok(());
+    }
+
+    #[test]
+    fn integer_overflow_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.load_str("1 340_282_366_920_938_463_463_374_607_431_768_211_456 2.5");
+        assert!(lexer.tokenize()?[1].is_float()? > 3.4e38);
+
+        lexer.integer_overflow = IntegerOverflow::BigInteger;
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_integer()?, 1);
+        assert_eq!(tokens[1].is_big_integer()?, "340282366920938463463374607431768211456");
+        assert!(tokens[1].kind().is_numeric());
+        assert_eq!(tokens[2].is_float()?, 2.5);
+
+        lexer.integer_overflow = IntegerOverflow::Error;
+        let err = lexer.tokenize().map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(err, Err("1:53: integer literal too large: 340_282_366_920_938_463_463_374_607_431_768_211_456".to_string()));
+
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert!(matches!(&tokens[1], Token::Error(text, _) if text.starts_with("340_")));
+        assert_eq!(errors.len(), 1);
+        return Ok(());
+    }
+
+    #[test]
+    fn escape_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut string = Section::new("string", "\"", "\"");
+        string.escapes = Escapes::Decoded('\\');
+        let mut lexer = Lexer::new(&[], &[string], &[], false);
+
+        lexer.load_str("\"a\\n\\t\\\"\\\\\\u{1F600}\" ");
+        assert_eq!(lexer.tokenize()?[0].is_section("string")?, "a\n\t\"\\\u{1F600}");
+
+        lexer.load_str("\"\\q\" ");
+        let error = lexer.tokenize().unwrap_err();
+        assert_eq!(error.downcast_ref::<LexError>(), Some(&LexError::InvalidEscape("\\q".to_string(), at(1, 2, 1))));
+        return Ok(());
+    }
+
+    #[test]
+    fn pragma_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.pragma_prefix = Some("#lexin:".to_string());
+
+        lexer.load_str("async\n#lexin: keyword async\nasync\n#lexin: allow_exponent off\n1e9\n ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "async");
+        assert_eq!(tokens[1], Token::Pragma("keyword async".to_string(), at(2, 1, 6)));
+        tokens[2].is_keyword("async")?;
+        assert_eq!(tokens[4].is_ident()?, "1e9");
+
+        lexer.load_str("#lexin: allow_whitespace maybe\n");
+        assert!(lexer.tokenize().is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn line_directive_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('=', "equal".to_string())], false);
+        lexer.line_directive = Some("#line".to_string());
+
+        lexer.load_str("a\n#line 10 \"gen.src\"\nb = c\n#line 20\nd");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].loc().file, lexer.file);
+        let file = FileId::new("gen.src");
+        assert_eq!(tokens[1], Token::Ident("b".to_string(), Loc { file, line: 10, col: 1, offset: 21 }));
+        assert_eq!(tokens[3].loc(), Loc { file, line: 10, col: 5, offset: 25 });
+        assert_eq!(tokens[4], Token::Ident("d".to_string(), Loc { file, line: 20, col: 1, offset: 36 }));
+
+        lexer.load_str("#line ten\na");
+        assert!(lexer.tokenize().is_err());
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(tokens[0], Token::Error("#line ten".to_string(), at(1, 1, 0)));
+        assert_eq!(tokens[1].loc().line, 2);
+        assert_eq!(errors, vec![LexError::InvalidLineDirective("ten".to_string(), at(1, 1, 0))]);
+        return Ok(());
+    }
+
+    #[test]
+    fn token_table_test() {
+        let tokens = vec![
+            Token::Keyword("def".to_string(), at(1, 1, 0)),
+            Token::Symbol('(', "openbrace".to_string(), at(1, 10, 9)),
+        ];
+
+        assert_eq!(format_token_table(&tokens), concat!(
+            "index  kind     name       text   line:col\n",
+            "0      Keyword  -          \"def\"  1:1\n",
+            "1      Symbol   openbrace  \"(\"    1:10\n",
+        ));
+    }
+
+    #[test]
+    fn spanned_token_table_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["def".to_string()], &[Section::new("string", "\"", "\"")], &[('(', "openbrace".to_string())], false);
+        lexer.load_str("def  f(\"a b\"");
+
+        assert_eq!(format_spanned_token_table(&lexer.tokenize_spanned()?), concat!(
+            "index  kind     name       text   line:col  span\n",
+            "0      Keyword  -          \"def\"  1:3       0..3\n",
+            "1      Ident    -          \"f\"    1:6       5..6\n",
+            "2      Symbol   openbrace  \"(\"    1:7       6..7\n",
+            "3      Section  string     \"a b\"  1:12      7..12\n",
+        ));
         return Ok(());
     }
-}
 
+    #[test]
+    fn multi_char_section_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &[],
+            &[Section::new("string", "\"", "\""), Section::new("docstring", "\"\"\"", "\"\"\""), Section::new("comment", "/*", "*/"), Section::new("html", "<!--", "-->")],
+            &[],
+            false,
+        );
+
+        lexer.load_str("\"\"\"a \"b\" c\"\"\" /* d */ <!-- e --> \"f\"");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_section("docstring")?, "a \"b\" c");
+        assert_eq!(tokens[1].is_section("comment")?, " d ");
+        assert_eq!(tokens[2].is_section("html")?, " e ");
+        assert_eq!(tokens[3].is_section("string")?, "f");
+        return Ok(());
+    }
+
+    #[test]
+    fn utf8_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "«", "»")], &[('→', "arrow".to_string())], false);
+
+        lexer.load_str("café→中 «😀» x");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "café");
+        tokens[1].is_symbol("arrow")?;
+        assert_eq!(tokens[2].is_ident()?, "中");
+        assert_eq!(tokens[3], Token::Section("string".to_string(), "😀".to_string(), at(1, 10, 18)));
+        return Ok(());
+    }
+
+    #[test]
+    fn excerpt_test() {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.load_str("first\nsecond line\nthird\nfourth");
+
+        let token = Token::Ident("line".to_string(), at(2, 8, 13));
+        assert_eq!(lexer.excerpt(&token, 1), "1 | first\n2 | second line\n  |        ^\n3 | third\n");
+    }
+
+    #[test]
+    fn borrowed_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[], false);
+        lexer.load_str("let abc \"def\" \"g\\\"h\"");
+
+        let tokens = lexer.tokenize_ref()?;
+        assert!(matches!(tokens[0], TokenRef::Keyword(Cow::Borrowed("let"), _)));
+        assert!(matches!(tokens[1], TokenRef::Ident("abc", _)));
+        assert!(matches!(&tokens[2], TokenRef::Section("string", Cow::Borrowed("def"), _)));
+        assert!(matches!(&tokens[3], TokenRef::Section("string", Cow::Owned(value), _) if value == "g\"h"));
+        assert_eq!(tokens[1].to_owned(), Token::Ident("abc".to_string(), tokens[1].loc()));
+        return Ok(());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_test() -> Result<(), Box<dyn std::error::Error>> {
+        let path = std::env::temp_dir().join("lib_lexin_mmap_test.txt");
+        fs::write(&path, "let x = 1\n ")?;
+
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_file_mmap(path.to_str().unwrap())?;
+        let tokens = lexer.tokenize_ref()?;
+        assert!(matches!(tokens[1], TokenRef::Ident("x", _)));
+        assert_eq!(tokens.len(), 4);
+
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+
+    #[test]
+    fn rule_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('=', "equal".to_string())], false);
+        lexer.add_rule("version", |input: &str| {
+            let length = input.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(input.len());
+            return Some(length).filter(|length| input[..*length].matches('.').count() == 2);
+        });
+
+        lexer.load_str("v = 1.2.3 w = 1.2 ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[2].is_custom("version")?, "1.2.3");
+        assert_eq!(tokens[5].is_float()?, 1.2);
+        return Ok(());
+    }
+
+    #[test]
+    fn keyword_tag_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.add_keyword_tagged("if", &["control-flow"]);
+        lexer.add_keyword_tagged("while", &["control-flow", "loop"]);
+        lexer.add_keyword_tagged("int", &["type"]);
+
+        lexer.load_str("while int x ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(lexer.tags(&tokens[0]), vec!["control-flow", "loop"]);
+        assert!(lexer.has_tag(&tokens[1], "type"));
+        assert!(lexer.tags(&tokens[2]).is_empty());
+        assert_eq!(lexer.keywords_tagged("control-flow"), vec!["if", "while"]);
+
+        #[derive(Debug, PartialEq)]
+        enum Tag {
+            ControlFlow,
+            Type,
+        }
+
+        lexer.add_keyword_typed("while", Tag::ControlFlow);
+        lexer.add_keyword_typed("int", Tag::Type);
+        lexer.add_keyword_typed("int", 4usize);
+        lexer.add_keyword_typed("float", Tag::Type);
+        lexer.load_str("while int x float");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(lexer.keyword_tag::<Tag>(&tokens[0]), Some(&Tag::ControlFlow));
+        assert_eq!(lexer.keyword_tag::<Tag>(&tokens[1]), Some(&Tag::Type));
+        assert_eq!(lexer.keyword_tag::<usize>(&tokens[1]), Some(&4));
+        assert_eq!(lexer.keyword_tag::<usize>(&tokens[0]), None);
+        assert_eq!(lexer.keyword_tag::<Tag>(&tokens[2]), None);
+        assert_eq!(lexer.keyword_tag::<Tag>(&tokens[3]), Some(&Tag::Type));
+
+        // both kinds of tag are kept side by side in keyword_tags
+        assert_eq!(lexer.tags(&tokens[0]), vec!["control-flow", "loop"]);
+        assert_eq!(lexer.keyword_tag::<String>(&tokens[1]), Some(&"type".to_string()));
+        assert_eq!(lexer.keyword_tags.iter().filter(|(keyword, _)| keyword == "int").count(), 3);
+
+        let mut base = Lexer::new(&[], &[], &[], false);
+        base.add_keyword_tagged("while", &["loop"]);
+        base.extend(&lexer);
+        base.load_str("while int");
+        let tokens = base.tokenize()?;
+        assert_eq!(base.tags(&tokens[0]), vec!["loop", "control-flow"]);
+        assert_eq!(base.keyword_tag::<Tag>(&tokens[0]), Some(&Tag::ControlFlow));
+        assert_eq!(base.keyword_tag::<usize>(&tokens[1]), Some(&4));
+        assert_eq!(base.config().keyword_tags.len(), 4);
+        return Ok(());
+    }
+
+    #[test]
+    fn keyword_alias_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.add_keyword_alias("func", "fn");
+        lexer.add_keyword_alias("function", "fn");
+        lexer.add_keyword_tagged("fn", &["item"]);
+
+        lexer.load_str("fn func function funct");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens.iter().filter_map(|token| token.is_keyword("fn").ok()).count(), 3);
+        assert!(lexer.has_tag(&tokens[2], "item"));
+        tokens[3].is_ident()?;
+        assert_eq!(lexer.tokenize_lossless()?[1].text, "func");
+        assert_eq!(tokens[1], Token::Keyword("fn".to_string(), at(1, 7, 6)));
+        let spellings = tokens.iter().map(|token| lexer.keyword_spelling(token)).collect::<Vec<Option<&str>>>();
+        assert_eq!(spellings, [Some("fn"), Some("func"), Some("function"), None]);
+
+        lexer.case_insensitive_keywords = true;
+        lexer.load_str("FUNC");
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("fn")?;
+        assert_eq!(lexer.keyword_spelling(&tokens[0]), Some("FUNC"));
+
+        lexer.keywords.clear();
+        assert!(lexer.tokenize()?[0].is_ident().is_ok());
+        return Ok(());
+    }
+
+    #[test]
+    fn contextual_keyword_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["fn".to_string()], &[], &[], false);
+        lexer.add_contextual_keyword("async");
+        lexer.load_str("async fn async");
+
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "async");
+        assert!(tokens[0].ident_is_keyword_candidate(&lexer));
+        assert!(!tokens[1].ident_is_keyword_candidate(&lexer));
+        assert_eq!(lexer.contextual_keyword(&tokens[0]), Some(Token::Keyword("async".to_string(), at(1, 5, 4))));
+        assert_eq!(lexer.contextual_keyword(&tokens[1]), None);
+
+        lexer.case_insensitive_keywords = true;
+        lexer.load_str("ASYNC");
+        lexer.contextual_keyword(&lexer.tokenize()?[0]).ok_or("not a keyword")?.is_keyword("async")?;
+        return Ok(());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_rule_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('#', "hash".to_string())], false);
+        lexer.add_regex_rule("hexcolor", r"#[0-9a-fA-F]{6}")?;
+
+        lexer.load_str("#ff00aa #zz ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_custom("hexcolor")?, "#ff00aa");
+        tokens[1].is_symbol("hash")?;
+        assert!(lexer.add_regex_rule("broken", "(").is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn edge_input_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        for input in ["", "   ", "\n\n", " \n \n"] {
+            lexer.load_str(input);
+            assert_eq!(lexer.tokenize()?, Vec::new());
+        }
+
+        lexer.load_str("x");
+        assert_eq!(lexer.tokenize()?, vec![Token::Ident("x".to_string(), at(1, 1, 0))]);
+
+        lexer.emit_eof = true;
+        lexer.load_str("");
+        assert_eq!(lexer.tokenize()?, vec![Token::Eof(at(1, 1, 0))]);
+        lexer.load_str("  \n");
+        assert_eq!(lexer.tokenize()?, vec![Token::Eof(at(2, 1, 3))]);
+        return Ok(());
+    }
+
+    #[test]
+    fn mode_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('{', "open".to_string())], false);
+        lexer.add_mode(LexMode::new("expr", &["if".to_string()], &[Section::new("string", "\"", "\"")], &[('}', "close".to_string()), ('+', "plus".to_string())]));
+        lexer.add_transition(None, "open", ModeAction::Push("expr".to_string()));
+        lexer.add_transition(Some("expr"), "close", ModeAction::Pop);
+
+        lexer.load_str("if a+b {if a+\"b\"} if");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "if");
+        assert_eq!(tokens[1].is_ident()?, "a+b");
+        tokens[2].is_symbol("open")?;
+        tokens[3].is_keyword("if")?;
+        tokens[5].is_symbol("plus")?;
+        assert_eq!(tokens[6].is_section("string")?, "b");
+        tokens[7].is_symbol("close")?;
+        assert_eq!(tokens[8].is_ident()?, "if");
+        return Ok(());
+    }
+
+    #[test]
+    fn keyword_case_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["select".to_string()], &[Section::new("string", "'", "'")], &[], false);
+        lexer.pragma_prefix = Some("#pragma ".to_string());
+        lexer.load_str("SELECT Select 'SELECT'\n#pragma keyword From\nfrom FROM");
+
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "SELECT");
+
+        lexer.case_insensitive_keywords = true;
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("select")?;
+        tokens[1].is_keyword("select")?;
+        assert_eq!(tokens[2].is_section("string")?, "SELECT");
+        tokens[4].is_keyword("From")?;
+        tokens[5].is_keyword("From")?;
+        return Ok(());
+    }
+
+    #[test]
+    fn keyword_boundary_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["if".to_string(), "return".to_string()], &[], &[], false);
+        lexer.load_str("if-x return5 ifx a.if.b 1.5");
+
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "if-x");
+        assert_eq!(tokens[1].is_ident()?, "return5");
+
+        lexer.split_keywords = true;
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("if")?;
+        assert_eq!(tokens[1].is_ident()?, "-x");
+        assert_eq!(tokens[2].is_ident()?, "return5");
+        assert_eq!(tokens[3].is_ident()?, "ifx");
+        assert_eq!(tokens[4].is_ident()?, "a.");
+        tokens[5].is_keyword("if")?;
+        assert_eq!(tokens[6].is_ident()?, ".b");
+        assert_eq!(tokens[7].is_float()?, 1.5);
+
+        lexer.ident_char = |character| is_ident_char(character) || character == '-';
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, "if-x");
+        return Ok(());
+    }
+
+    #[test]
+    fn ident_symbol_test() -> Result<(), Box<dyn std::error::Error>> {
+        let symbols = [('-', "minus"), ('>', "greater"), ('?', "question"), ('(', "openparen"), (')', "closeparen")];
+        let mut lexer = Lexer::new(&[], &[], &symbols.map(|(symbol, name)| (symbol, name.to_string())), false);
+        lexer.add_ident_symbol('-', IdentSymbol::Inner);
+        lexer.add_ident_symbol('>', IdentSymbol::Inner);
+        lexer.add_ident_symbol('?', IdentSymbol::Trailing);
+        let source = "(list->vec foo-bar) (empty? a - b) x- 1-2 ?y -z";
+        lexer.load_str(source);
+        let tokens = lexer.tokenize()?;
+
+        let texts = tokens.iter().map(Token::as_string).collect::<Vec<String>>();
+        assert_eq!(texts, ["(", "list->vec", "foo-bar", ")", "(", "empty?", "a", "-", "b", ")", "x", "-", "1", "-", "2", "?", "y", "-", "z"]);
+        assert!(matches!(tokens[7], Token::Symbol('-', _, _)));
+
+        // a chunk boundary between the identifier and its symbols changes nothing
+        let mut push = lexer.push_lexer();
+        let mut pushed = Vec::new();
+        for chunk in source.as_bytes().chunks(1) {
+            pushed.extend(push.feed(chunk)?);
+        }
+        pushed.extend(push.finish()?);
+        assert_eq!(pushed, tokens);
+        return Ok(());
+    }
+
+    #[test]
+    fn lossy_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut section = Section::new("string", "\"", "\"");
+        section.escapes = Escapes::Decoded('\\');
+
+        let mut lexer = Lexer::new(&[], &[section], &[], false);
+        lexer.pragma_prefix = Some("#pragma ".to_string());
+        lexer.load_reader(&b"a \"b\\qc\"\n#pragma bogus\nd \xff e\n\"open"[..])?;
+        assert!(lexer.tokenize().is_err());
+
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(tokens[0].is_ident()?, "a");
+        assert_eq!(tokens[1].is_section("string")?, "bqc");
+        assert_eq!(tokens[2], Token::Error("bogus".to_string(), at(2, 1, 9)));
+        assert_eq!(tokens[3].is_ident()?, "d");
+        assert!(matches!(&tokens[4], Token::Error(text, loc) if text == "\u{FFFD}" && *loc == at(3, 3, 25)));
+        // offsets after the invalid byte still point into the raw input
+        assert_eq!(tokens[5], Token::Ident("e".to_string(), at(3, 5, 27)));
+        assert!(matches!(&tokens[6], Token::Error(text, _) if text == "\"open"));
+
+        assert!(matches!(errors[0], LexError::InvalidEscape(..)));
+        assert!(matches!(errors[1], LexError::InvalidPragma(..)));
+        assert_eq!(errors[2], LexError::InvalidUtf8(at(3, 3, 25)));
+        assert!(matches!(&errors[3], LexError::UnterminatedSection(name, _) if name == "string"));
+        assert_eq!(errors.len(), 4);
+        return Ok(());
+    }
+
+    #[test]
+    fn lossy_offset_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[], false);
+        // two invalid bytes after a multi-byte character and a \r\n, each lexed as a three byte U+FFFD
+        lexer.load_reader(&b"\xc3\xa9\r\n\xff\xfe a \"open"[..])?;
+
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(tokens, vec![
+            Token::Ident("é".to_string(), at(1, 1, 0)),
+            Token::Error("\u{FFFD}".to_string(), at(2, 1, 4)),
+            Token::Error("\u{FFFD}".to_string(), at(2, 2, 5)),
+            Token::Ident("a".to_string(), at(2, 4, 7)),
+            Token::Error("\"open".to_string(), at(2, 11, 14)),
+        ]);
+        assert_eq!(errors, vec![
+            LexError::InvalidUtf8(at(2, 1, 4)),
+            LexError::InvalidUtf8(at(2, 2, 5)),
+            LexError::UnterminatedSection("string".to_string(), at(2, 6, 9)),
+        ]);
+
+        // the same locs as valid input with a one byte character for each invalid byte
+        lexer.load_str("é\r\n?? a \"open");
+        let (valid, _) = lexer.tokenize_lossy();
+        assert_eq!(valid.iter().skip(2).map(Token::loc).collect::<Vec<Loc>>(), tokens.iter().skip(3).map(Token::loc).collect::<Vec<Loc>>());
+        return Ok(());
+    }
+
+    #[test]
+    fn token_kind_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\""), Section::new("comment", "#", "\n")], &[('=', "equal".to_string())], false);
+        lexer.comment_sections.push("comment".to_string());
+        lexer.load_str("let a = \"b\" # note\nlet c = 1.5");
+
+        let tokens = lexer.tokenize()?;
+        let kinds = tokens.iter().map(Token::kind).collect::<Vec<TokenKind>>();
+        assert_eq!(kinds.iter().filter(|kind| kind.is_literal()).count(), 2);
+        assert_eq!(kinds.iter().filter(|kind| kind.is_numeric()).count(), 1);
+        assert_eq!(kinds.iter().filter(|kind| kind.is_trivia()).count(), 1);
+        assert_eq!(kinds.iter().filter(|kind| **kind == TokenKind::Keyword).count(), 2);
+        return Ok(());
+    }
+
+    #[test]
+    fn end_of_input_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(
+            &["def".to_string()],
+            &[Section::new("string", "\"", "\""), Section::new("comment", "/*", "*/")],
+            &[('=', "equal".to_string()), ('(', "openparen".to_string()), (')', "closeparen".to_string())],
+            false,
+        );
+        // every token with the column and offset of its last character
+        let inputs = [
+            ("\"def\"", vec![("def", 5, 4)]),
+            ("def", vec![("def", 3, 2)]),
+            ("\"x\"def", vec![("x", 3, 2), ("def", 6, 5)]),
+            ("def\"x\"", vec![("def", 3, 2), ("x", 6, 5)]),
+            ("a = 1.5", vec![("a", 1, 0), ("=", 3, 2), ("1.5", 7, 6)]),
+            ("f()", vec![("f", 1, 0), ("(", 2, 1), (")", 3, 2)]),
+            ("a/* b */", vec![("a", 1, 0), (" b ", 7, 6)]),
+            ("x \u{e9}", vec![("x", 1, 0), ("\u{e9}", 3, 2)]),
+            ("\"a\\\"\"", vec![("a\"", 5, 4)]),
+        ];
+
+        // the last token comes out the same however the input ends and however it is fed
+        for (input, expected) in inputs {
+            let expected = expected.into_iter().map(|(text, col, offset)| (text.to_string(), at(1, col, offset))).collect::<Vec<(String, Loc)>>();
+            for ending in ["", " ", "\n", "\r\n", "\n\n"] {
+                let source = format!("{}{}", input, ending);
+                let mut lexer = lexer.clone();
+                lexer.load_str(&source);
+                let tokens = lexer.tokenize()?;
+                assert_eq!(tokens.iter().map(|token| (token.as_string(), token.loc())).collect::<Vec<(String, Loc)>>(), expected, "{:?}", source);
+
+                let mut push = lexer.push_lexer();
+                let mut pushed = Vec::new();
+                for chunk in source.as_bytes().chunks(1) {
+                    pushed.extend(push.feed(chunk)?);
+                }
+                pushed.extend(push.finish()?);
+                assert_eq!(pushed, tokens, "{:?}", source);
+            }
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn map_token_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("comment", "#", "\n")], &[('=', "equal".to_string())], true);
+        lexer.load_str("let a = 1 # one\n");
+
+        let tokens = lexer.tokenize_filtered(|token| token.is_ident().map_or(true, |ident| ident != " "))?;
+        assert_eq!(tokens.len(), 5);
+        tokens[4].is_section("comment")?;
 
+        lexer.map_token = Some(|token| match token {
+            Token::Section(name, _, _) if name == "comment" => None,
+            Token::Ident(ident, loc) => Some(Token::Ident(ident.to_uppercase(), loc)),
+            token => Some(token),
+        });
+        let tokens = lexer.tokenize_filtered(|token| !matches!(token, Token::Ident(ident, _) if ident == " "))?;
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].is_ident()?, "A");
+        assert_eq!(lexer.tokenize_reader("let a".as_bytes()).filter_map(Result::ok).nth(2).map(|token| token.as_string()), Some("A".to_string()));
+        return Ok(());
+    }
+
+    #[test]
+    fn offside_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["if".to_string()], &[Section::new("comment", "#", "\n"), Section::new("string", "\"", "\"")], &[(':', "colon".to_string())], false);
+        lexer.comment_sections.push("comment".to_string());
+        lexer.offside = true;
+        lexer.load_str("if a: # check\n    b\n\n  # note\n\tif \"c\n\":\n\t  d\ne");
+
+        let kinds = lexer.tokenize()?.iter().map(Token::kind).collect::<Vec<TokenKind>>();
+        assert_eq!(kinds, [
+            TokenKind::Keyword, TokenKind::Ident, TokenKind::Symbol, TokenKind::Comment, TokenKind::Newline,
+            TokenKind::Indent, TokenKind::Ident, TokenKind::Newline,
+            TokenKind::Comment,
+            TokenKind::Keyword, TokenKind::Section, TokenKind::Symbol, TokenKind::Newline,
+            TokenKind::Indent, TokenKind::Ident, TokenKind::Newline,
+            TokenKind::Dedent, TokenKind::Dedent, TokenKind::Ident, TokenKind::Newline,
+        ]);
+
+        lexer.load_str("a\n    b\n  c");
+        assert!(matches!(lexer.tokenize().map_err(|error| error.downcast::<LexError>().map(|error| *error)), Err(Ok(LexError::InconsistentIndent(_)))));
+
+        lexer.load_str("a\n  b");
+        let tokens = lexer.tokenize()?;
+        assert!(matches!(tokens[..], [Token::Ident(..), Token::Newline(_), Token::Indent(_), Token::Ident(..), Token::Newline(_), Token::Dedent(_)]));
+        assert_eq!(lexer.tokenize_reader("a\n  b".as_bytes()).collect::<Result<Vec<Token>, _>>()?, tokens);
+        return Ok(());
+    }
+
+    #[test]
+    fn newline_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("comment", ";", "\n"), Section::new("string", "\"", "\"")], &[(',', "comma".to_string())], false);
+        lexer.emit_newlines = true;
+        lexer.load_str("mov a, 1 ; load\n\nret \"x\ny\"\n");
+
+        let tokens = lexer.tokenize()?;
+        let kinds = tokens.iter().map(Token::kind).collect::<Vec<TokenKind>>();
+        assert_eq!(kinds, [
+            TokenKind::Ident, TokenKind::Ident, TokenKind::Symbol, TokenKind::Integer, TokenKind::Section, TokenKind::Newline,
+            TokenKind::Newline,
+            TokenKind::Ident, TokenKind::Section, TokenKind::Newline,
+        ]);
+        assert_eq!(tokens[6].loc().line, 2);
+        assert_eq!(tokens[9].loc().line, 4);
+        return Ok(());
+    }
+
+    #[test]
+    fn eof_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[], false);
+        lexer.emit_eof = true;
+        lexer.load_str("a \"b\"\nc\n");
+
+        let tokens = lexer.tokenize()?;
+        tokens[tokens.len() - 1].is_eof()?;
+        assert_eq!(tokens.iter().filter(|token| token.is_eof().is_ok()).count(), 1);
+        assert_eq!(tokens.last().map(Token::loc), Some(at(3, 1, 8)));
+
+        let streamed = lexer.tokenize_reader(lexer.buffer.as_slice()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(streamed, tokens);
+        assert_eq!(lexer.tokenize_lossy().0, tokens);
+        assert!(tokens[0].is_eof().is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn tokenize_into_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        let mut tokens = Vec::new();
+
+        lexer.load_str("let a = 1\nlet b = 2");
+        lexer.tokenize_into(&mut tokens)?;
+        assert_eq!(tokens, lexer.tokenize()?);
+        let capacity = tokens.capacity();
+
+        lexer.load_str("x");
+        lexer.tokenize_into(&mut tokens)?;
+        assert_eq!(tokens, vec![Token::Ident("x".to_string(), at(1, 1, 0))]);
+        assert_eq!(tokens.capacity(), capacity);
+        return Ok(());
+    }
+
+    #[test]
+    fn shared_lexer_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = 1");
+        let config = lexer.config_hash();
+
+        let lexer = &lexer;
+        let results = std::thread::scope(|scope| {
+            let handles = (0..4).map(|_| scope.spawn(move || lexer.tokenize().map_err(|error| error.to_string()))).collect::<Vec<_>>();
+            return handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Result<Vec<_>, _>>();
+        })?;
+        assert!(results.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(lexer.config_hash(), config);
+
+        let mut lexer = lexer.clone();
+        lexer.reset();
+        assert!(lexer.tokenize()?.is_empty());
+        assert_eq!(lexer.config_hash(), config);
+        return Ok(());
+    }
+
+    #[test]
+    fn preamble_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[], false);
+        lexer.load_str("\u{feff}#!/bin/run -x\r\nlet a");
+        assert_eq!(lexer.tokenize()?[0], Token::Ident("#!/bin/run".to_string(), at(1, 10, 12)));
+
+        lexer.shebang = true;
+        assert_eq!(lexer.tokenize()?, vec![Token::Keyword("let".to_string(), at(2, 3, 20)), Token::Ident("a".to_string(), at(2, 5, 22))]);
+
+        lexer.emit_shebang = true;
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0], Token::Shebang("#!/bin/run -x".to_string(), at(1, 13, 15)));
+        assert!(tokens[0].kind().is_trivia());
+        assert_eq!(tokens[1], Token::Keyword("let".to_string(), at(2, 3, 20)));
+
+        let utf16 = "\u{feff}let é".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+        assert!(lexer.load_reader(utf16.as_slice()).is_err());
+        lexer.transcode_utf16 = true;
+        lexer.load_reader(utf16.as_slice())?;
+        assert_eq!(lexer.tokenize()?[1], Token::Ident("é".to_string(), at(1, 5, 7)));
+        return Ok(());
+    }
+
+    #[test]
+    fn strict_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.strict = true;
+
+        lexer.load_str("a = 1e-5 \"$\" b_2");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[2].is_float()?, 1e-5);
+        assert_eq!(tokens[3].is_section("string")?, "$");
+        assert_eq!(tokens[4].is_ident()?, "b_2");
+
+        lexer.load_str("a = b$c");
+        let error = lexer.tokenize().unwrap_err();
+        assert_eq!(error.downcast_ref::<LexError>(), Some(&LexError::UnexpectedChar('$', at(1, 6, 5))));
+
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(errors, vec![LexError::UnexpectedChar('$', at(1, 6, 5))]);
+        assert_eq!(tokens[2].is_ident()?, "b");
+        assert_eq!(tokens[3], Token::Error("$".to_string(), at(1, 6, 5)));
+        assert_eq!(tokens[4].is_ident()?, "c");
+
+        lexer.strict = false;
+        assert_eq!(lexer.tokenize()?[2].is_ident()?, "b$c");
+
+        lexer.emit_unknown = true;
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[3], Token::Error("$".to_string(), at(1, 6, 5)));
+        assert_eq!(tokens[4].is_ident()?, "c");
+        return Ok(());
+    }
+
+    #[test]
+    fn emit_unknown_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.emit_unknown = true;
+
+        let source = "a = €1 @b\"$\"";
+        lexer.load_str(source);
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert!(errors.is_empty());
+        assert_eq!(tokens[2], Token::Error("€".to_string(), at(1, 5, 4)));
+        assert_eq!(tokens[3].is_integer()?, 1);
+        assert_eq!(tokens[4], Token::Error("@".to_string(), at(1, 8, 9)));
+        assert_eq!(tokens[5].is_ident()?, "b");
+        assert_eq!(tokens[6].is_section("string")?, "$");
+
+        // every character but the whitespace is covered by a token
+        let text = lexer.tokenize_spanned()?.iter().map(|(_, span)| span.text(source)).collect::<String>();
+        assert_eq!(text, source.replace(' ', ""));
+
+        lexer.strict = true;
+        assert!(lexer.tokenize().is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn number_options_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.allow_leading_dot = true;
+        lexer.number_suffixes = vec!["u32".to_string(), "f".to_string(), "32".to_string()];
+
+        lexer.load_str("10u32 1.5f .5 10 1x");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_custom("u32")?, "10");
+        assert_eq!(tokens[1].is_custom("f")?, "1.5");
+        assert_eq!(tokens[2].is_float()?, 0.5);
+        assert_eq!(tokens[3].is_integer()?, 10);
+        assert_eq!(tokens[4].is_ident()?, "1x");
+
+        lexer.map_number = Some(|text, loc| {
+            return Some(TokenRef::Custom("version", text, loc)).filter(|_| text.matches('.').count() == 2);
+        });
+        lexer.load_str("1.2.3 1.2");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_custom("version")?, "1.2.3");
+        assert_eq!(tokens[1].is_float()?, 1.2);
+
+        lexer.allow_numbers = false;
+        lexer.load_str("1.2.3 1.2 .5");
+        assert!(lexer.tokenize()?.iter().all(|token| token.is_ident().is_ok()));
+        return Ok(());
+    }
+
+    #[test]
+    fn char_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('<', "lt".to_string()), ('>', "gt".to_string())], false);
+        lexer.char_quote = Some('\'');
+
+        lexer.load_str("'a' '\\n' '\\'' Foo<'b> 'é'");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0], Token::Char('a', at(1, 3, 2)));
+        assert_eq!(tokens[1].is_char()?, '\n');
+        assert_eq!(tokens[2].is_char()?, '\'');
+        assert_eq!(tokens[5].is_ident()?, "'b");
+        assert_eq!(tokens[7].is_char()?, 'é');
+        for token in &tokens {
+            lexer.load_str(&lexer.lexeme(token));
+            let relexed = lexer.tokenize()?;
+            assert_eq!((relexed[0].kind(), relexed[0].as_string()), (token.kind(), token.as_string()));
+        }
+
+        lexer.load_str("'\\q' x");
+        assert!(matches!(lexer.tokenize().unwrap_err().downcast_ref::<LexError>(), Some(LexError::InvalidEscape(..))));
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(tokens[0], Token::Error("'\\q'".to_string(), at(1, 4, 3)));
+
+        // a backslash ending the input is not a char literal
+        for input in ["'\\", "x'\\", "'\\'"] {
+            lexer.load_str(input);
+            assert!(lexer.tokenize()?.iter().all(|token| token.kind() != TokenKind::Char), "{:?}", input);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn raw_section_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut string = Section::new("string", "\"", "\"");
+        string.escapes = Escapes::Decoded('\\');
+        let mut lexer = Lexer::new(&[], &[Section::new("raw", "r\"", "\"").escapes(Escapes::Off), string], &[], false);
+
+        lexer.load_str("r\"C:\\dir\\\" \"a\\tb\" r\"\\d+\\n\"");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_section("raw")?, "C:\\dir\\");
+        assert_eq!(tokens[1].is_section("string")?, "a\tb");
+        assert_eq!(tokens[2].is_section("raw")?, "\\d+\\n");
+        assert_eq!(lexer.lexeme(&tokens[0]), "r\"C:\\dir\\\"");
+        return Ok(());
+    }
+
+    #[test]
+    fn section_options_test() -> Result<(), Box<dyn std::error::Error>> {
+        let sections = [
+            Section::new("string", "\"", "\"").single_line().escapes(Escapes::Decoded('\\')),
+            Section::new("template", "`", "`").escapes(Escapes::Stripped('^')),
+            Section::new("regex", "/", "/").escapes(Escapes::Kept('\\')),
+            Section::new("attribute", "#[", "]").keep_delimiters(),
+        ];
+        let mut lexer = Lexer::new(&[], &sections, &[], false);
+
+        lexer.load_str("`a^`b^^` /\\d\\/x/ #[inline]");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_section("template")?, "a`b^");
+        assert_eq!(tokens[1].is_section("regex")?, "\\d\\/x");
+        assert_eq!(tokens[2].is_section("attribute")?, "#[inline]");
+        assert_eq!(lexer.lexeme(&tokens[0]), "`a^`b^^`");
+        assert_eq!(lexer.lexeme(&tokens[1]), "/\\d\\/x/");
+        assert_eq!(lexer.lexeme(&tokens[2]), "#[inline]");
+
+        lexer.load_str("\"a\\tb\" \"open\nnext");
+        assert!(lexer.tokenize().is_err());
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(tokens[0].is_section("string")?, "a\tb");
+        assert_eq!(tokens[1], Token::Error("\"open".to_string(), at(1, 13, 12)));
+        assert_eq!(tokens[2].is_ident()?, "next");
+        assert_eq!(errors, vec![LexError::UnterminatedSection("string".to_string(), at(1, 8, 7))]);
+        return Ok(());
+    }
+
+    #[test]
+    fn heredoc_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &["cat".to_string()],
+            &[Section::new("heredoc", "<<", "").heredoc(Heredoc::Keep), Section::new("indented", "<<~", "").heredoc(Heredoc::StripIndent)],
+            &[('|', "pipe".to_string())],
+            false,
+        );
+
+        lexer.load_str("cat <<EOF | x\nhello\n  EOF\nEOF\ncat <<~END\n    a\n      b\n    END\ny");
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("cat")?;
+        tokens[1].is_symbol("pipe")?;
+        assert_eq!(tokens[2].is_ident()?, "x");
+        assert_eq!(tokens[3], Token::Section("heredoc".to_string(), "hello\n  EOF\n".to_string(), at(4, 1, 26)));
+        tokens[4].is_keyword("cat")?;
+        assert_eq!(tokens[5].is_section("indented")?, "a\n  b\n");
+        assert_eq!(tokens[6].is_ident()?, "y");
+        assert_eq!(tokens.len(), 7);
+
+        let streamed = lexer.tokenize_reader(lexer.buffer.as_slice()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(streamed, tokens);
+
+        lexer.load_str("cat <<EOF\nhello");
+        assert_eq!(lexer.tokenize()?[1].is_section("heredoc")?, "hello");
+        lexer.sections[0].unterminated = Unterminated::Error;
+        assert!(lexer.tokenize().is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn interpolation_test() -> Result<(), Box<dyn std::error::Error>> {
+        let string = Section::new("string", "\"", "\"").interpolate("${", "}");
+        let mut lexer = Lexer::new(&[], &[string], &[('+', "plus".to_string()), ('{', "open".to_string()), ('}', "close".to_string())], false);
+
+        lexer.load_str("\"a ${x + \"}\"} b ${ {1} }\\${c}\" ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens.len(), 1);
+        let Token::Interpolated(name, fragments, _) = &tokens[0] else {
+            return Err("expected an interpolated section".into());
+        };
+        assert_eq!(name, "string");
+        assert_eq!(fragments[0], Fragment::Text("a ".to_string()));
+        let Fragment::Tokens(inner) = &fragments[1] else {
+            return Err("expected tokens".into());
+        };
+        assert_eq!(inner[0], Token::Ident("x".to_string(), at(1, 6, 5)));
+        inner[1].is_symbol("plus")?;
+        assert_eq!(inner[2], Token::Interpolated("string".to_string(), vec![Fragment::Text("}".to_string())], at(1, 12, 11)));
+        assert_eq!(fragments[2], Fragment::Text(" b ".to_string()));
+        assert_eq!(fragments[3], Fragment::Tokens(vec![
+            Token::Symbol('{', "open".to_string(), at(1, 20, 19)),
+            Token::Integer(1, at(1, 21, 20)),
+            Token::Symbol('}', "close".to_string(), at(1, 22, 21)),
+        ]));
+        assert_eq!(fragments[4], Fragment::Text("${c}".to_string()));
+        assert_eq!(tokens[0].loc(), at(1, 30, 29));
+
+        lexer.load_str(&lexer.lexeme(&tokens[0]));
+        assert_eq!(lexer.tokenize()?[0].as_string(), tokens[0].as_string());
+
+        lexer.load_str("\"${x\" ");
+        assert_eq!(lexer.tokenize()?[0], Token::Interpolated("string".to_string(), vec![Fragment::Text("${x".to_string())], at(1, 5, 4)));
+        return Ok(());
+    }
+
+    #[test]
+    fn nested_section_test() -> Result<(), Box<dyn std::error::Error>> {
+        let string = Section::new("string", "\"", "\"").interpolate("${", "}");
+        let mut lexer = Lexer::new(&[], &[string], &[], false);
+
+        // the string inside the inner interpolation ends neither of the outer strings
+        lexer.load_str("\"a ${ \"b ${ \"c\" } d\" } e\" ");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens.len(), 1);
+        let Token::Interpolated(_, fragments, _) = &tokens[0] else {
+            return Err("expected an interpolated section".into());
+        };
+        assert_eq!(fragments.len(), 3);
+        assert_eq!(fragments[2], Fragment::Text(" e".to_string()));
+        let Fragment::Tokens(inner) = &fragments[1] else {
+            return Err("expected tokens".into());
+        };
+        assert_eq!(inner[0].as_string(), "b c d");
+
+        let mut lexer = Lexer::new(&[], &[Section::new("expr", "${", "}").nest(&["string", "paren"]), Section::new("string", "'", "'"), Section::new("paren", "(", ")")], &[], false);
+        lexer.load_str("${ f('}', (a})) } x");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_section("expr")?, " f('}', (a})) ");
+        tokens[1].is_ident()?;
+        return Ok(());
+    }
+
+    #[test]
+    fn unterminated_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[], false);
+        lexer.load_str("a\n b \"open\nrest");
+        assert_eq!(lexer.tokenize()?[2].is_section("string")?, "open\nrest");
+
+        lexer.sections[0].unterminated = Unterminated::Token;
+        assert_eq!(lexer.tokenize()?[2], Token::Error("\"open\nrest".to_string(), at(3, 5, 15)));
+
+        lexer.sections[0].unterminated = Unterminated::Error;
+        let error = lexer.tokenize().unwrap_err().to_string();
+        assert_eq!(error, "2:4: unterminated section: string");
+
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert!(matches!(tokens[2], Token::Error(..)));
+        assert_eq!(errors, [LexError::UnterminatedSection("string".to_string(), at(2, 4, 5))]);
+        return Ok(());
+    }
+
+    #[test]
+    fn whitespace_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("a\tb c,d");
+        assert_eq!(lexer.tokenize()?.iter().map(Token::as_string).collect::<Vec<String>>(), ["a\tb", "c,d"]);
+
+        lexer.whitespace = vec!['\t', ','];
+        assert_eq!(lexer.tokenize()?.iter().map(Token::as_string).collect::<Vec<String>>(), ["a", "b c", "d"]);
+        assert_eq!(lexer.tokenize_bytes()?.len(), 3);
+
+        lexer.strict = true;
+        lexer.load_str("a,b=c");
+        assert_eq!(lexer.tokenize()?.len(), 4);
+        return Ok(());
+    }
+
+    #[test]
+    fn line_section_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::line("include", "#include"), Section::line("annotation", "@")], &[], false);
+        lexer.sections[1].unterminated = Unterminated::Error;
+        lexer.load_str("#include <a.h>\r\nint a\n@inline fast");
+
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_section("include")?, " <a.h>");
+        assert_eq!(tokens[0].loc(), at(1, 16, 15));
+        tokens[1].is_ident()?;
+        assert_eq!(tokens[3].is_section("annotation")?, "inline fast");
+
+        let (tokens, errors) = lexer.tokenize_lossy();
+        assert_eq!(tokens.len(), 4);
+        assert!(errors.is_empty());
+        return Ok(());
+    }
+}
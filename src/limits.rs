@@ -0,0 +1,187 @@
+use crate::{LexError, Lexer, Loc};
+
+use std::fmt;
+use std::io::Read;
+use std::ops::Range;
+
+// guards for lexing untrusted input, None leaves a limit off. hitting one fails with
+// LexError::LimitExceeded even in tokenize_lossy, as carrying on is what the limits prevent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Limits {
+    // in bytes, counted from the start of the input so readers lexing in batches are covered
+    pub max_input: Option<usize>,
+    // in bytes, of identifiers, numbers and keywords
+    pub max_token_length: Option<usize>,
+    // in bytes, of a section including its delimiters
+    pub max_section_length: Option<usize>,
+    // counted from the start of the input as well, however many batches the input is lexed in
+    pub max_tokens: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Input,
+    TokenLength,
+    SectionLength,
+    Tokens,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Limit::Input => write!(f, "input size"),
+            Limit::TokenLength => write!(f, "token length"),
+            Limit::SectionLength => write!(f, "section length"),
+            Limit::Tokens => write!(f, "token count"),
+        };
+    }
+}
+
+fn exceeds(limit: Option<usize>, value: usize) -> bool {
+    return limit.is_some_and(|limit| value > limit);
+}
+
+impl Lexer {
+    // length is that of the input available from loc on, to its end
+    pub(crate) fn check_input_limit(&self, length: usize, loc: Loc) -> Result<(), LexError> {
+        if exceeds(self.limits.max_input, loc.offset + length) {
+            return Err(LexError::LimitExceeded(Limit::Input, loc));
+        }
+        return Ok(());
+    }
+
+    // reads no more than one byte past max_input, so an oversized input fails without being read whole
+    pub(crate) fn read_limited(&self, reader: impl Read, loc: Loc) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut buffer = Vec::new();
+        let limit = self.limits.max_input.map_or(u64::MAX, |max_input| max_input as u64 + 1);
+        reader.take(limit).read_to_end(&mut buffer)?;
+        self.check_input_limit(buffer.len(), loc)?;
+        return Ok(buffer);
+    }
+
+    // checked as the token grows, so the limit fails before the whole token has been read
+    pub(crate) fn check_token_limit(&self, token: &Range<usize>, loc: Loc) -> Result<(), LexError> {
+        if exceeds(self.limits.max_token_length, token.len()) {
+            return Err(LexError::LimitExceeded(Limit::TokenLength, loc));
+        }
+        return Ok(());
+    }
+
+    // section is where the open section started, if any
+    pub(crate) fn check_scan_limits(&self, section: Option<(usize, Loc)>, index: usize, tokens: usize, loc: Loc) -> Result<(), LexError> {
+        if exceeds(self.limits.max_tokens, tokens) {
+            return Err(LexError::LimitExceeded(Limit::Tokens, loc));
+        } else if let Some((_, start)) = section.filter(|(start, _)| exceeds(self.limits.max_section_length, index - start)) {
+            return Err(LexError::LimitExceeded(Limit::SectionLength, start));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LexError, Lexer, Limit, Loc, Section, Token};
+
+    use std::io::Read;
+    use std::ops::ControlFlow;
+
+    fn exceeded(lexer: &Lexer, input: &str) -> Option<(Limit, Loc)> {
+        let mut lexer = lexer.clone();
+        lexer.load_str(input);
+        return match lexer.tokenize().map_err(|error| error.downcast::<LexError>().map(|error| *error)) {
+            Err(Ok(LexError::LimitExceeded(limit, loc))) => Some((limit, loc)),
+            _ => None,
+        };
+    }
+
+    #[test]
+    fn limits_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.limits.max_input = Some(16);
+        lexer.limits.max_token_length = Some(4);
+        lexer.limits.max_section_length = Some(6);
+        lexer.limits.max_tokens = Some(3);
+
+        assert_eq!(exceeded(&lexer, "ab = \"cd\""), None);
+        assert_eq!(exceeded(&lexer, "a = bbbbb").map(|(limit, loc)| (limit, loc.col)), Some((Limit::TokenLength, 9)));
+        assert_eq!(exceeded(&lexer, "a = \"bcdefg\"").map(|(limit, loc)| (limit, loc.col)), Some((Limit::SectionLength, 5)));
+        assert_eq!(exceeded(&lexer, "a = b c").map(|(limit, _)| limit), Some(Limit::Tokens));
+        assert_eq!(exceeded(&lexer, &"a".repeat(17)).map(|(limit, _)| limit), Some(Limit::Input));
+
+        lexer.load_str("a = bbbbb");
+        assert_eq!(lexer.tokenize_lossy().1, vec![LexError::LimitExceeded(Limit::TokenLength, Loc { file: lexer.file, line: 1, col: 9, offset: 8 })]);
+        return Ok(());
+    }
+
+    #[test]
+    fn loader_limits_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.limits.max_input = Some(16);
+        lexer.limits.max_token_length = Some(4);
+        lexer.limits.max_section_length = Some(6);
+        lexer.limits.max_tokens = Some(3);
+
+        let bytes_exceeded = |input: &str| {
+            let mut lexer = lexer.clone();
+            lexer.load_str(input);
+            return match lexer.tokenize_bytes() {
+                Err(LexError::LimitExceeded(limit, _)) => Some(limit),
+                _ => None,
+            };
+        };
+        assert_eq!(bytes_exceeded("ab = \"cd\""), None);
+        assert_eq!(bytes_exceeded("a = bbbbb"), Some(Limit::TokenLength));
+        assert_eq!(bytes_exceeded("a = \"bcdefg\""), Some(Limit::SectionLength));
+        assert_eq!(bytes_exceeded("a = b c"), Some(Limit::Tokens));
+        assert_eq!(bytes_exceeded(&"a".repeat(17)), Some(Limit::Input));
+
+        // an endless reader is only read up to the limit
+        let error = lexer.load_reader(std::io::repeat(b'a')).err().ok_or("the limit was not hit")?;
+        assert!(matches!(error.downcast_ref::<LexError>(), Some(LexError::LimitExceeded(Limit::Input, _))));
+        lexer.load_reader("a = b".as_bytes())?;
+        assert_eq!(lexer.tokenize()?.len(), 3);
+
+        let path = std::env::temp_dir().join("lib_lexin_limits_test.txt");
+        std::fs::write(&path, "a".repeat(17))?;
+        let error = lexer.load_file(path.to_str().ok_or("temp path is not utf-8")?).err().ok_or("the limit was not hit")?;
+        assert!(matches!(error.downcast_ref::<LexError>(), Some(LexError::LimitExceeded(Limit::Input, _))));
+        std::fs::remove_file(&path)?;
+        return Ok(());
+    }
+
+    // hands out a line at a time, so every read is a batch of its own
+    struct Lines<'a>(std::str::SplitInclusive<'a, char>);
+
+    impl Read for Lines<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            let line = self.0.next().unwrap_or_default();
+            buffer[..line.len()].copy_from_slice(line.as_bytes());
+            return Ok(line.len());
+        }
+    }
+
+    #[test]
+    fn streamed_token_limit_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.limits.max_tokens = Some(50);
+        let source = "a b\n".repeat(30);
+
+        let tokens = lexer.tokenize_reader(Lines(source.split_inclusive('\n'))).collect::<Result<Vec<Token>, _>>();
+        let error = tokens.err().ok_or("the limit was not hit")?.downcast::<LexError>().map_err(|_| "not a lex error")?;
+        assert!(matches!(*error, LexError::LimitExceeded(Limit::Tokens, loc) if loc.line == 26));
+
+        lexer.load_str(&source);
+        let mut seen = 0;
+        assert!(lexer.tokenize_with(|_| {
+            seen += 1;
+            return ControlFlow::Continue(());
+        }).is_err());
+        assert_eq!(seen, 50);
+
+        lexer.load_str(&source[..source.len() / 2]);
+        assert_eq!(lexer.tokenize_with_progress(8, |_, _| {})?.len(), 30);
+        return Ok(());
+    }
+}
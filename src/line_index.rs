@@ -0,0 +1,105 @@
+use crate::{FileId, Lexer, Loc};
+
+// maps byte offsets to lines and columns and back, columns count chars like Loc does
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    file: FileId,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str, file: FileId) -> LineIndex<'a> {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(index, _)| index + 1));
+        return LineIndex {
+            source,
+            file,
+            line_starts,
+        };
+    }
+
+    pub fn line_count(&self) -> usize {
+        return self.line_starts.len();
+    }
+
+    // the text of a 1-indexed line without its line ending
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map_or(self.source.len(), |end| end - 1);
+        return Some(self.source[start..end].trim_end_matches('\r'));
+    }
+
+    // the offset of the first byte of a line
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        return self.line_starts.get(line.checked_sub(1)?).copied();
+    }
+
+    // offsets inside a character or past the end of the source have no position
+    pub fn loc(&self, offset: usize) -> Option<Loc> {
+        if offset > self.source.len() || !self.source.is_char_boundary(offset) {
+            return None;
+        }
+
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let col = self.source[self.line_starts[line - 1]..offset].chars().count() + 1;
+        return Some(Loc {
+            file: self.file,
+            line: line as u32,
+            col: col as u32,
+            offset,
+        });
+    }
+
+    // the column may point one past the last character of the line, at its line ending
+    pub fn offset(&self, line: usize, col: usize) -> Option<usize> {
+        let start = self.line_start(line)?;
+        let end = self.line_starts.get(line).map_or(self.source.len(), |end| end - 1);
+        let text = &self.source[start..end];
+        return text.char_indices()
+            .map(|(index, _)| index)
+            .chain(std::iter::once(text.len()))
+            .nth(col.checked_sub(1)?)
+            .map(|index| start + index);
+    }
+}
+
+impl Lexer {
+    pub fn line_index(&self) -> Result<LineIndex<'_>, Box<dyn std::error::Error>> {
+        return Ok(LineIndex::new(std::str::from_utf8(self.source_bytes())?, self.file));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FileId, Lexer, LineIndex};
+
+    #[test]
+    fn line_index_test() -> Result<(), Box<dyn std::error::Error>> {
+        let index = LineIndex::new("let a\r\nbär = 1\n\nend", FileId::default());
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line(1), Some("let a"));
+        assert_eq!(index.line(2), Some("bär = 1"));
+        assert_eq!(index.line(3), Some(""));
+        assert_eq!(index.line(5), None);
+
+        let loc = index.loc(10).ok_or("no loc")?;
+        assert_eq!((loc.line, loc.col), (2, 3));
+        assert_eq!(index.offset(2, 3), Some(10));
+        assert_eq!(index.loc(9), None);
+        assert_eq!(index.offset(3, 1), Some(16));
+        assert_eq!(index.offset(3, 2), None);
+        assert_eq!(index.loc(20).map(|loc| (loc.line, loc.col)), Some((4, 4)));
+
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = 1\nlet b = 2");
+        let tokens = lexer.tokenize()?;
+        let index = lexer.line_index()?;
+        for token in tokens {
+            let loc = token.loc();
+            assert_eq!(index.loc(loc.offset), Some(loc));
+            assert_eq!(index.offset(loc.line as usize, loc.col as usize), Some(loc.offset));
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{Lexer, Loc, Token};
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub severity: Severity,
+    pub loc: Loc,
+    pub message: String,
+}
+
+// a check over the tokens of an input, with the source for checks about its layout. lexer is the
+// lexer the tokens came from, see line_loc
+pub trait TokenLint {
+    fn check(&self, lexer: &Lexer, source: &str, tokens: &[Token], lints: &mut Vec<Lint>);
+}
+
+// indentation mixing tabs and spaces on one line
+pub struct MixedIndent;
+
+pub struct TrailingWhitespace;
+
+// typographic quotes outside of sections, usually pasted from a word processor
+pub struct NonAsciiQuotes;
+
+// lines longer than the amount of characters
+pub struct LongLines(pub usize);
+
+// the lints run by Linter::run, in the order they were added
+#[derive(Default)]
+pub struct Linter {
+    lints: Vec<Box<dyn TokenLint>>,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        };
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}: {}: {}", self.loc, self.severity, self.message);
+    }
+}
+
+// the position column bytes into the line starting at offset start, counted the way lexer counts
+// columns. line directives are not applied, lints are about the input as it is
+pub fn line_loc(lexer: &Lexer, source: &str, line: u32, start: usize, column: usize) -> Loc {
+    let mut loc = Loc {
+        file: lexer.file,
+        line,
+        col: 1,
+        offset: start,
+    };
+    lexer.advance(&mut loc, &source[start..start + column]);
+    return loc;
+}
+
+// every line with its 1-indexed number and offset, without the line ending
+fn lines(source: &str) -> impl Iterator<Item = (u32, usize, &str)> {
+    let mut start = 0;
+    return source.split_inclusive('\n').enumerate().map(move |(index, line)| {
+        let offset = start;
+        start += line.len();
+        return (index as u32 + 1, offset, line.trim_end_matches('\n').trim_end_matches('\r'));
+    });
+}
+
+impl TokenLint for MixedIndent {
+    fn check(&self, lexer: &Lexer, source: &str, _: &[Token], lints: &mut Vec<Lint>) {
+        for (line, start, text) in lines(source) {
+            let indent = &text[..text.len() - text.trim_start_matches([' ', '\t']).len()];
+            if indent.contains(' ') && indent.contains('\t') {
+                lints.push(Lint {
+                    severity: Severity::Warning,
+                    loc: line_loc(lexer, source, line, start, 0),
+                    message: "indentation mixes tabs and spaces".to_string(),
+                });
+            }
+        }
+    }
+}
+
+impl TokenLint for TrailingWhitespace {
+    fn check(&self, lexer: &Lexer, source: &str, _: &[Token], lints: &mut Vec<Lint>) {
+        for (line, start, text) in lines(source) {
+            let trimmed = text.trim_end_matches([' ', '\t']);
+            if trimmed.len() < text.len() {
+                lints.push(Lint {
+                    severity: Severity::Warning,
+                    loc: line_loc(lexer, source, line, start, trimmed.len()),
+                    message: "trailing whitespace".to_string(),
+                });
+            }
+        }
+    }
+}
+
+impl TokenLint for NonAsciiQuotes {
+    fn check(&self, _: &Lexer, _: &str, tokens: &[Token], lints: &mut Vec<Lint>) {
+        for token in tokens {
+            let (Token::Ident(text, loc) | Token::Error(text, loc) | Token::Custom(_, text, loc)) = token else {
+                continue;
+            };
+            if let Some(quote) = text.chars().find(|character| matches!(character, '\u{2018}' | '\u{2019}' | '\u{201c}' | '\u{201d}')) {
+                let ascii = if matches!(quote, '\u{2018}' | '\u{2019}') { '\'' } else { '"' };
+                lints.push(Lint {
+                    severity: Severity::Warning,
+                    loc: *loc,
+                    message: format!("non-ascii quote {:?}, did you mean {:?}", quote, ascii),
+                });
+            }
+        }
+    }
+}
+
+impl TokenLint for LongLines {
+    fn check(&self, lexer: &Lexer, source: &str, _: &[Token], lints: &mut Vec<Lint>) {
+        for (line, start, text) in lines(source) {
+            if let Some((column, _)) = text.char_indices().nth(self.0) {
+                lints.push(Lint {
+                    severity: Severity::Info,
+                    loc: line_loc(lexer, source, line, start, column),
+                    message: format!("line is longer than {} characters", self.0),
+                });
+            }
+        }
+    }
+}
+
+impl Linter {
+    pub fn new() -> Linter {
+        return Linter::default();
+    }
+
+    // the built in lints, with lines limited to 100 characters
+    pub fn builtin() -> Linter {
+        let mut linter = Linter::new();
+        linter.add(MixedIndent);
+        linter.add(TrailingWhitespace);
+        linter.add(NonAsciiQuotes);
+        linter.add(LongLines(100));
+        return linter;
+    }
+
+    pub fn add(&mut self, lint: impl TokenLint + 'static) {
+        self.lints.push(Box::new(lint));
+    }
+
+    // the findings of every lint ordered by where they are in the source
+    pub fn run(&self, lexer: &Lexer, source: &str, tokens: &[Token]) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for lint in &self.lints {
+            lint.check(lexer, source, tokens, &mut lints);
+        }
+        lints.sort_by_key(|lint| lint.loc.offset);
+        return lints;
+    }
+}
+
+impl Lexer {
+    // tokenizes the loaded input and runs the linter over it
+    pub fn tokenize_linted(&self, linter: &Linter) -> Result<(Vec<Token>, Vec<Lint>), Box<dyn std::error::Error>> {
+        let tokens = self.tokenize()?;
+        let lints = linter.run(self, std::str::from_utf8(self.source_bytes())?, &tokens);
+        return Ok((tokens, lints));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Lint, Linter, LongLines, Severity, Token, TokenLint};
+
+    struct NoTodo;
+
+    impl TokenLint for NoTodo {
+        fn check(&self, _: &Lexer, _: &str, tokens: &[Token], lints: &mut Vec<Lint>) {
+            lints.extend(tokens.iter().filter(|token| matches!(token, Token::Ident(ident, _) if ident == "todo")).map(|token| Lint {
+                severity: Severity::Error,
+                loc: token.loc(),
+                message: "todo left in".to_string(),
+            }));
+        }
+    }
+
+    #[test]
+    fn lint_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('=', "equal".to_string())], false);
+        lexer.whitespace = vec![' ', '\t', '\r'];
+        lexer.load_str("a = \u{201c}b\u{201d}  \n \tc = todo\nlong line here\n");
+
+        let mut linter = Linter::builtin();
+        linter.add(LongLines(4));
+        linter.add(NoTodo);
+        let (_, lints) = lexer.tokenize_linted(&linter)?;
+        let found = lints.iter().map(|lint| (lint.severity, lint.loc.line, lint.loc.col)).collect::<Vec<_>>();
+        assert_eq!(found, [(Severity::Info, 1, 5), (Severity::Warning, 1, 7), (Severity::Warning, 1, 8), (Severity::Warning, 2, 1), (Severity::Info, 2, 5), (Severity::Error, 2, 10), (Severity::Info, 3, 5)]);
+        assert_eq!(lints[1].message, "non-ascii quote '\u{201c}', did you mean '\"'");
+        assert_eq!(lints[5].to_string(), "2:10: error: todo left in");
+        return Ok(());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::{Lexer, Token};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// what a FileId stands for, a named file or the expansion of a macro at a callsite. a released
+// expansion leaves its slot free for the next one, see FileId::release
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    Name(&'static str),
+    Expansion(Loc),
+    Released,
+}
+
+// file names are interned for the life of the process so a FileId can be copied around freely.
+// each name is stored once, however often it is interned
+#[derive(Default)]
+struct Files {
+    // with the generation of the slot, counting the expansions that took it
+    sources: Vec<(Source, u32)>,
+    names: HashMap<&'static str, u32>,
+    expansions: HashMap<Loc, FileId>,
+    released: Vec<u32>,
+}
+
+static FILES: OnceLock<RwLock<Files>> = OnceLock::new();
+
+fn files() -> RwLockReadGuard<'static, Files> {
+    return FILES.get_or_init(RwLock::default).read().unwrap_or_else(|poisoned| poisoned.into_inner());
+}
+
+fn files_mut() -> RwLockWriteGuard<'static, Files> {
+    return FILES.get_or_init(RwLock::default).write().unwrap_or_else(|poisoned| poisoned.into_inner());
+}
+
+impl Files {
+    fn push(&mut self, source: Source) -> u32 {
+        self.sources.push((source, 0));
+        return self.sources.len() as u32;
+    }
+}
+
+// identifies the file a location belongs to, the default id is input that has no file name. the
+// slot is followed by its generation, so an id kept past FileId::release never names the
+// expansion that took its slot next. ids only mean something within the process, serde writes the
+// file name instead, see the Serialize impl
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct FileId(u32, u32);
+
+// a position in the input, line and col count from 1 and col counts chars unless the lexer says
+// otherwise, see Lexer::column_unit. offset is the byte offset into the input the position refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Loc {
+    pub file: FileId,
+    pub line: u32,
+    pub col: u32,
+    pub offset: usize,
+}
+
+// where the tokens of an input come from. tokens of a macro expansion are located within the
+// expansion, in a file of their own that remembers the callsite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    File(FileId),
+    Macro { callsite: Loc },
+}
+
+// what Loc::col counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColumnUnit {
+    // unicode scalar values
+    #[default]
+    Char,
+    // what lsp positions count by default
+    Utf16,
+    Byte,
+}
+
+impl FileId {
+    // the same name always gives the same id. names are never freed, so interning an unbounded
+    // number of distinct names, like generated ones, grows the process for good
+    pub fn new(name: &str) -> FileId {
+        if let Some(id) = files().names.get(name) {
+            return FileId(*id, 0);
+        }
+
+        let mut files = files_mut();
+        // another thread may have interned the name in between
+        if let Some(id) = files.names.get(name) {
+            return FileId(*id, 0);
+        }
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        let id = files.push(Source::Name(name));
+        files.names.insert(name, id);
+        return FileId(id, 0);
+    }
+
+    // the file of a macro expansion at callsite, the same callsite always gives the same id
+    pub fn expansion(callsite: Loc) -> FileId {
+        if let Some(id) = files().expansions.get(&callsite) {
+            return *id;
+        }
+
+        let mut files = files_mut();
+        if let Some(id) = files.expansions.get(&callsite) {
+            return *id;
+        }
+        let id = match files.released.pop() {
+            Some(slot) => {
+                let (source, generation) = &mut files.sources[slot as usize - 1];
+                *source = Source::Expansion(callsite);
+                *generation += 1;
+                FileId(slot, *generation)
+            },
+            None => FileId(files.push(Source::Expansion(callsite)), 0),
+        };
+        files.expansions.insert(callsite, id);
+        return id;
+    }
+
+    // frees the slot of a macro expansion once its tokens are dropped, so expanding macros all over
+    // a large project doesn't keep every callsite around. the slot goes to the next expansion under
+    // a new generation, a loc still in the released file is then in a file of its own without a
+    // name or callsite. false for ids that are not an expansion
+    pub fn release(&self) -> bool {
+        let mut files = files_mut();
+        let Some(Source::Expansion(callsite)) = self.slot(&files).map(|(source, _)| *source) else {
+            return false;
+        };
+        files.sources[self.0 as usize - 1].0 = Source::Released;
+        files.expansions.remove(&callsite);
+        files.released.push(self.0);
+        return true;
+    }
+
+    // the slot of the id while it is of the id's generation
+    fn slot<'f>(&self, files: &'f Files) -> Option<&'f (Source, u32)> {
+        return self.0.checked_sub(1).and_then(|index| files.sources.get(index as usize)).filter(|(_, generation)| *generation == self.1);
+    }
+
+    fn source(&self) -> Option<Source> {
+        return self.slot(&files()).map(|(source, _)| *source);
+    }
+
+    // None for input without a file name and for macro expansions
+    pub fn name(&self) -> Option<&'static str> {
+        return match self.source() {
+            Some(Source::Name(name)) => Some(name),
+            _ => None,
+        };
+    }
+
+    pub fn origin(&self) -> Origin {
+        return match self.source() {
+            Some(Source::Expansion(callsite)) => Origin::Macro { callsite },
+            _ => Origin::File(*self),
+        };
+    }
+}
+
+impl Origin {
+    // the file tokens from this origin are located in
+    pub fn file(&self) -> FileId {
+        return match self {
+            Origin::File(file) => *file,
+            Origin::Macro { callsite } => FileId::expansion(*callsite),
+        };
+    }
+}
+
+impl Loc {
+    // the start of the given file
+    pub fn new(file: FileId) -> Loc {
+        return Loc {
+            file,
+            line: 1,
+            col: 1,
+            offset: 0,
+        };
+    }
+
+    pub fn origin(&self) -> Origin {
+        return self.file.origin();
+    }
+
+    // the position in a file that was read rather than generated, following the callsites of
+    // nested expansions. error messages should point here
+    pub fn callsite(&self) -> Loc {
+        let mut loc = *self;
+        while let Origin::Macro { callsite } = loc.origin() {
+            loc = callsite;
+        }
+        return loc;
+    }
+
+    // moves past text, which may span several lines. with a tab width a tab moves col to the next
+    // multiple of it past the start of the line
+    pub(crate) fn advance(&mut self, text: &str, unit: ColumnUnit, tab_width: Option<usize>) {
+        for character in text.chars() {
+            self.col += match character {
+                '\n' => {
+                    self.line += 1;
+                    self.col = 1;
+                    0
+                },
+                '\t' if tab_width.is_some() => {
+                    let width = tab_width.unwrap_or_default().max(1) as u32;
+                    width - (self.col - 1) % width
+                },
+                _ => match unit {
+                    ColumnUnit::Char => 1,
+                    ColumnUnit::Utf16 => character.len_utf16() as u32,
+                    ColumnUnit::Byte => character.len_utf8() as u32,
+                },
+            };
+        }
+        self.offset += text.len();
+    }
+}
+
+impl Lexer {
+    // moves loc past text counting columns the way the lexer is configured to
+    pub(crate) fn advance(&self, loc: &mut Loc, text: &str) {
+        loc.advance(text, self.column_unit, self.expand_tabs.then_some(self.tab_width));
+    }
+
+    // lexes source instead of the loaded input, with every token in the file of origin. errors
+    // inside a macro expansion are shown at its callsite
+    pub fn tokenize_from(&self, source: &str, origin: Origin) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let mut run = self.new_run();
+        run.skip_spans = true;
+        let (scanned, _) = self.scan_until(source, source.len(), &mut run, &mut Loc::new(origin.file()), true)?;
+        return Ok(scanned.tokens.into_iter().filter_map(|token| self.finish_token(token)).collect());
+    }
+}
+
+// as the file name, None for input without one and for macro expansions, whose callsites don't
+// outlive the process either. reading the name back interns it, like the token cache does
+#[cfg(feature = "serde")]
+impl serde::Serialize for FileId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return self.name().serialize(serializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FileId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<FileId, D::Error> {
+        let name = Option::<String>::deserialize(deserializer)?;
+        return Ok(name.map_or(FileId::default(), |name| FileId::new(&name)));
+    }
+}
+
+impl fmt::Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.name().unwrap_or("<input>"));
+    }
+}
+
+// file:line:col, or just line:col for input without a file name. a loc in a macro expansion is
+// shown at the callsite with its position in the expansion after it
+impl fmt::Display for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Origin::Macro { callsite } = self.origin() {
+            return write!(f, "{} (in expansion at {}:{})", callsite, self.line, self.col);
+        }
+        if let Some(name) = self.file.name() {
+            write!(f, "{}:", name)?;
+        }
+        return write!(f, "{}:{}", self.line, self.col);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ColumnUnit, FileId, Lexer, Loc, Origin, Section, TextEdit, Token, TokenCache, Unterminated};
+
+    #[test]
+    fn loc_test() -> Result<(), Box<dyn std::error::Error>> {
+        let file = FileId::new("foo.src");
+        assert_eq!(file, FileId::new("foo.src"));
+        assert_ne!(file, FileId::new("bar.src"));
+        assert_eq!(file.name(), Some("foo.src"));
+        assert_eq!(FileId::default().name(), None);
+
+        // threads interning the same name at once agree on its id
+        let ids = std::thread::scope(|scope| {
+            let handles = (0..4).map(|_| scope.spawn(|| FileId::new("shared.src"))).collect::<Vec<_>>();
+            return handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<FileId>>();
+        });
+        assert!(ids.iter().all(|id| *id == FileId::new("shared.src")));
+
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a\n  = bär b");
+        lexer.file = file;
+
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].loc().file, file);
+        assert_eq!(tokens[2].loc().to_string(), "foo.src:2:3");
+        assert_eq!(tokens[3].loc(), Loc { file, line: 2, col: 7, offset: 13 });
+
+        lexer.load_str("a");
+        assert_eq!(lexer.file, FileId::default());
+        assert_eq!(lexer.tokenize()?[0].loc().to_string(), "1:1");
+        return Ok(());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn file_id_serde_test() -> Result<(), Box<dyn std::error::Error>> {
+        // the name is written, not the id, which another process would have given another file
+        let loc = Loc { file: FileId::new("serde.src"), line: 2, col: 3, offset: 10 };
+        let json = serde_json::to_string(&loc)?;
+        assert_eq!(json, r#"{"file":"serde.src","line":2,"col":3,"offset":10}"#);
+        assert_eq!(serde_json::from_str::<Loc>(&json)?, loc);
+        assert_eq!(serde_json::from_str::<FileId>(r#""other.src""#)?.name(), Some("other.src"));
+
+        assert_eq!(serde_json::to_string(&FileId::default())?, "null");
+        assert_eq!(serde_json::to_string(&FileId::expansion(loc))?, "null");
+        assert_eq!(serde_json::from_str::<FileId>("null")?, FileId::default());
+        return Ok(());
+    }
+
+    #[test]
+    fn column_unit_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('=', "equal".to_string())], false);
+        lexer.whitespace = vec![' ', '\t'];
+        lexer.load_str("\ta😀 = b");
+        assert_eq!(lexer.tokenize()?[1].loc().col, 5);
+
+        lexer.column_unit = ColumnUnit::Utf16;
+        assert_eq!(lexer.tokenize()?[1].loc().col, 6);
+        lexer.column_unit = ColumnUnit::Byte;
+        assert_eq!(lexer.tokenize()?[1].loc().col, 8);
+
+        lexer.column_unit = ColumnUnit::Char;
+        lexer.expand_tabs = true;
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].loc().col, 6);
+        assert_eq!(tokens[1].loc(), Loc { file: lexer.file, line: 1, col: 8, offset: 7 });
+        return Ok(());
+    }
+
+    #[test]
+    fn multi_line_loc_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\""), Section::new("comment", "/*", "*/")], &[('=', "equal".to_string())], false);
+        lexer.whitespace = vec![' ', '\t', '\r'];
+        lexer.expand_tabs = true;
+        let source = "a = \"x\r\n\ty\" b /* é\n\n */\tc\n\"\"\"\n\" ü";
+        lexer.load_str(source);
+
+        // every position agrees with counting the input up to its offset from the start
+        let tokens = lexer.tokenize()?;
+        for token in &tokens {
+            let mut loc = Loc::new(lexer.file);
+            lexer.advance(&mut loc, &source[..token.loc().offset]);
+            assert_eq!(token.loc(), loc);
+        }
+        let locs = tokens.iter().map(|token| (token.loc().line, token.loc().col)).collect::<Vec<(u32, u32)>>();
+        assert_eq!(locs, [(1, 1), (1, 3), (2, 6), (2, 8), (4, 2), (4, 5), (5, 2), (6, 1), (6, 3)]);
+
+        // the same positions when lexing is split at line breaks, which must not land inside é or ü
+        let mut push = lexer.push_lexer();
+        let mut pushed = Vec::new();
+        for chunk in source.as_bytes().chunks(1) {
+            pushed.extend(push.feed(chunk)?);
+        }
+        pushed.extend(push.finish()?);
+        assert_eq!(pushed, tokens);
+
+        let mut cache = TokenCache::new(&lexer, "a = 1\n\"\n\" ü")?;
+        lexer.relex(&mut cache, TextEdit::new(0..6, &source[..source.len() - "\"\n\" ü".len()]))?;
+        assert_eq!(cache.tokens(), tokens);
+        assert!(matches!(&cache.tokens()[8], Token::Ident(ident, loc) if ident == "ü" && loc.offset == source.len() - "ü".len()));
+        return Ok(());
+    }
+
+    #[test]
+    fn origin_test() -> Result<(), Box<dyn std::error::Error>> {
+        let file = FileId::new("main.src");
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.sections[0].unterminated = Unterminated::Error;
+        let callsite = Loc { file, line: 3, col: 5, offset: 20 };
+
+        let tokens = lexer.tokenize_from("let a = b\nlet c", Origin::Macro { callsite })?;
+        assert!(tokens.iter().all(|token| token.loc().origin() == Origin::Macro { callsite }));
+        assert_eq!((tokens[4].loc().line, tokens[4].loc().col), (2, 3));
+        assert_eq!(tokens[4].loc().callsite(), callsite);
+        assert_eq!(tokens[4].loc().to_string(), "main.src:3:5 (in expansion at 2:3)");
+
+        // an expansion inside an expansion still leads back to the file
+        let nested = lexer.tokenize_from("a", Origin::Macro { callsite: tokens[1].loc() })?;
+        assert_eq!(nested[0].loc().callsite(), callsite);
+
+        let err = lexer.tokenize_from("\"a", Origin::Macro { callsite }).map(|_| ()).map_err(|err| err.to_string());
+        assert!(err.is_err_and(|err| err.contains("main.src:3:5")));
+
+        let tokens = lexer.tokenize_from("let", Origin::File(file))?;
+        assert_eq!(tokens[0].loc().origin(), Origin::File(file));
+        assert_eq!(tokens[0].loc().callsite(), tokens[0].loc());
+        assert_eq!(FileId::expansion(callsite).name(), None);
+
+        // a released expansion is no longer an expansion, and a new one may take its id
+        let released = Loc { file, line: 9, col: 1, offset: 90 };
+        let expansion = FileId::expansion(released);
+        assert!(expansion.release());
+        assert!(!expansion.release());
+        assert!(!file.release());
+        assert_ne!(expansion.origin(), Origin::Macro { callsite: released });
+        assert_eq!(FileId::expansion(released).origin(), Origin::Macro { callsite: released });
+
+        // a loc kept from a released expansion doesn't lead to the expansion that took its slot
+        let stale = lexer.tokenize_from("a", Origin::Macro { callsite: released })?[0].loc();
+        assert!(FileId::expansion(released).release());
+        let other = Loc { file, line: 12, col: 1, offset: 120 };
+        let reused = FileId::expansion(other);
+        assert_ne!(reused, stale.file);
+        assert_eq!(reused.origin(), Origin::Macro { callsite: other });
+        assert_eq!(stale.origin(), Origin::File(stale.file));
+        assert_eq!(stale.callsite(), stale);
+        assert!(!stale.file.release());
+        assert_eq!(reused.origin(), Origin::Macro { callsite: other });
+        return Ok(());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{LexMode, Lexer};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// hashed lookups for the keywords, aliases and symbols of the lexer and each of its modes, so
+// matching a token doesn't scan every rule. built by the first run and kept on the lexer until the
+// rules change, see Lexer::new_run
+#[derive(Debug, Default)]
+pub(crate) struct RuleIndex {
+    // the base rules first, then one per mode
+    sets: Vec<SetIndex>,
+    // alias spelling, lowercased for case insensitive keywords, to its entries in keyword_aliases
+    aliases: HashMap<String, Vec<usize>>,
+    // the rules the index was built from, the fields holding them can be changed directly
+    rules: IndexedRules,
+}
+
+#[derive(Debug, Default)]
+struct IndexedRules {
+    keywords: Vec<String>,
+    symbols: Vec<(char, String)>,
+    modes: Vec<LexMode>,
+    keyword_aliases: Vec<(String, String)>,
+    case_insensitive_keywords: bool,
+}
+
+// the index of the last run, a clone of the lexer starts out with the same one
+#[derive(Default)]
+pub(crate) struct IndexCache(Mutex<Option<Arc<RuleIndex>>>);
+
+#[derive(Debug, Default)]
+pub(crate) struct SetIndex {
+    pub(crate) keywords: HashSet<String>,
+    // lowercased keyword to its position in the keywords, the first spelling wins
+    pub(crate) folded: HashMap<String, usize>,
+    // symbol to its position in the symbols, the first entry wins
+    pub(crate) symbols: HashMap<char, usize>,
+}
+
+impl Clone for IndexCache {
+    fn clone(&self) -> IndexCache {
+        return IndexCache(Mutex::new(self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()));
+    }
+}
+
+impl fmt::Debug for IndexCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.debug_tuple("IndexCache").finish_non_exhaustive();
+    }
+}
+
+impl IndexedRules {
+    // compares without cloning, as this runs at the start of every run
+    fn matches(&self, lexer: &Lexer) -> bool {
+        return self.keywords == lexer.keywords
+            && self.symbols == lexer.symbols
+            && self.modes == lexer.modes
+            && self.keyword_aliases == lexer.keyword_aliases
+            && self.case_insensitive_keywords == lexer.case_insensitive_keywords;
+    }
+}
+
+impl IndexCache {
+    // the cached index while it still matches the lexer's rules, a new one otherwise
+    pub(crate) fn get(&self, lexer: &Lexer) -> Arc<RuleIndex> {
+        let mut cached = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(index) = cached.as_ref().filter(|index| index.rules.matches(lexer)) {
+            return index.clone();
+        }
+        let index = RuleIndex::new(lexer);
+        *cached = Some(index.clone());
+        return index;
+    }
+}
+
+impl SetIndex {
+    fn new(keywords: &[String], symbols: &[(char, String)]) -> SetIndex {
+        let mut index = SetIndex {
+            keywords: keywords.iter().cloned().collect(),
+            ..SetIndex::default()
+        };
+        for (position, keyword) in keywords.iter().enumerate() {
+            index.folded.entry(keyword.to_ascii_lowercase()).or_insert(position);
+        }
+        for (position, (symbol, _)) in symbols.iter().enumerate() {
+            index.symbols.entry(*symbol).or_insert(position);
+        }
+        return index;
+    }
+}
+
+impl RuleIndex {
+    fn new(lexer: &Lexer) -> Arc<RuleIndex> {
+        let mut index = RuleIndex {
+            sets: vec![SetIndex::new(&lexer.keywords, &lexer.symbols)],
+            aliases: HashMap::new(),
+            rules: IndexedRules {
+                keywords: lexer.keywords.clone(),
+                symbols: lexer.symbols.clone(),
+                modes: lexer.modes.clone(),
+                keyword_aliases: lexer.keyword_aliases.clone(),
+                case_insensitive_keywords: lexer.case_insensitive_keywords,
+            },
+        };
+        index.sets.extend(lexer.modes.iter().map(|mode| SetIndex::new(&mode.keywords, &mode.symbols)));
+
+        for (position, (alias, _)) in lexer.keyword_aliases.iter().enumerate() {
+            let key = if lexer.case_insensitive_keywords { alias.to_ascii_lowercase() } else { alias.clone() };
+            index.aliases.entry(key).or_default().push(position);
+        }
+        return Arc::new(index);
+    }
+
+    // the set of the innermost mode, or of the base rules outside of modes
+    pub(crate) fn set(&self, mode: Option<usize>) -> &SetIndex {
+        return &self.sets[mode.map_or(0, |mode| mode + 1)];
+    }
+
+    // the entries in keyword_aliases for a spelling, lowercased for case insensitive keywords
+    pub(crate) fn aliases(&self, key: &str) -> &[usize] {
+        return self.aliases.get(key).map_or(&[], Vec::as_slice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ByteToken, LexMode, Lexer, ModeAction};
+
+    use std::sync::Arc;
+
+    #[test]
+    fn rule_index_test() -> Result<(), Box<dyn std::error::Error>> {
+        let keywords = (0..500).map(|index| format!("kw{}", index)).collect::<Vec<String>>();
+        let mut lexer = Lexer::new(&keywords, &[], &[('{', "open".to_string()), ('{', "shadowed".to_string())], false);
+        lexer.add_mode(LexMode::new("inner", &["Inner".to_string()], &[], &[('}', "close".to_string())]));
+        lexer.add_transition(None, "open", ModeAction::Push("inner".to_string()));
+        lexer.add_keyword_alias("KW7", "kw7");
+
+        lexer.load_str("kw499 kw500 { inner Inner }");
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("kw499")?;
+        tokens[1].is_ident()?;
+        tokens[2].is_symbol("open")?;
+        tokens[3].is_ident()?;
+        tokens[4].is_keyword("Inner")?;
+        tokens[5].is_symbol("close")?;
+
+        lexer.case_insensitive_keywords = true;
+        lexer.load_str("KW499 kw7 { INNER");
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("kw499")?;
+        tokens[1].is_keyword("kw7")?;
+        tokens[3].is_keyword("Inner")?;
+        return Ok(());
+    }
+
+    #[test]
+    fn index_cache_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        let index = lexer.new_run().index;
+        assert!(Arc::ptr_eq(&index, &lexer.new_run().index));
+        assert!(Arc::ptr_eq(&index, &lexer.clone().new_run().index));
+
+        // the rules can be changed directly, which the next run notices
+        lexer.keywords.push("in".to_string());
+        let changed = lexer.new_run().index;
+        assert!(!Arc::ptr_eq(&index, &changed));
+        assert!(Arc::ptr_eq(&changed, &lexer.new_run().index));
+
+        lexer.load_str("let a = in");
+        let tokens = lexer.tokenize()?;
+        tokens[3].is_keyword("in")?;
+
+        lexer.symbols[0].1 = "assign".to_string();
+        lexer.load_str("a = b");
+        lexer.tokenize()?[1].is_symbol("assign")?;
+        lexer.load_reader(&b"a = b"[..])?;
+        assert!(matches!(lexer.tokenize_bytes()?[1], ByteToken::Symbol('=', "assign", _)));
+        return Ok(());
+    }
+}
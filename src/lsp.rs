@@ -0,0 +1,85 @@
+use crate::{Lexer, LineIndex, Loc, Token, TokenRef};
+
+use lsp_types::{SemanticToken, SemanticTokens};
+
+impl Lexer {
+    // lexes the loaded input into relative encoded semantic tokens. token_type maps a token to the
+    // index of its type in the legend the server registered, tokens mapped to None are left out.
+    // positions and lengths count utf-16 code units and tokens spanning lines are split per line
+    pub fn semantic_tokens(&self, token_type: impl Fn(&Token) -> Option<u32>) -> Result<SemanticTokens, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let (scanned, _) = self.scan_until(source, source.len(), &mut self.new_run(), &mut Loc::new(self.file), true)?;
+        let index = LineIndex::new(source, self.file);
+
+        let mut data = Vec::new();
+        let (mut previous_line, mut previous_start) = (0, 0);
+        for (token, span) in scanned.tokens.into_iter().zip(scanned.spans) {
+            if span.is_empty() || matches!(token, TokenRef::Eof(_)) {
+                continue;
+            }
+
+            let Some(token_type) = token_type(&Token::from(token)) else {
+                continue;
+            };
+
+            let mut start = span.start;
+            for piece in source[span].split_inclusive('\n') {
+                let loc = index.loc(start).ok_or("token outside the source")?;
+                let line = loc.line - 1;
+                let column = index.line_start(loc.line as usize).map_or(0, |line_start| source[line_start..start].encode_utf16().count()) as u32;
+                let length = piece.trim_end_matches(['\n', '\r']).encode_utf16().count() as u32;
+                start += piece.len();
+                if length == 0 {
+                    continue;
+                }
+
+                data.push(SemanticToken {
+                    delta_line: line - previous_line,
+                    delta_start: if line == previous_line { column - previous_start } else { column },
+                    length,
+                    token_type,
+                    token_modifiers_bitset: 0,
+                });
+                (previous_line, previous_start) = (line, column);
+            }
+        }
+
+        return Ok(SemanticTokens {
+            result_id: None,
+            data,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section, Token};
+
+    #[test]
+    fn semantic_tokens_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = \"é\"\n  let b = \"x\ny\"");
+
+        // keyword 0, string 1, symbols left out
+        let tokens = lexer.semantic_tokens(|token| match token {
+            Token::Keyword(..) => Some(0),
+            Token::Section(..) => Some(1),
+            Token::Ident(..) => Some(2),
+            _ => None,
+        })?;
+
+        let encoded = tokens.data.iter()
+            .map(|token| (token.delta_line, token.delta_start, token.length, token.token_type))
+            .collect::<Vec<_>>();
+        assert_eq!(encoded, [
+            (0, 0, 3, 0),
+            (0, 4, 1, 2),
+            (0, 4, 3, 1),
+            (1, 2, 3, 0),
+            (0, 4, 1, 2),
+            (0, 4, 2, 1),
+            (1, 0, 2, 1),
+        ]);
+        return Ok(());
+    }
+}
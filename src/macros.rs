@@ -0,0 +1,52 @@
+// declares a whole lexer in one place, every part is optional but they must come in this order.
+// delimiters may be char or string literals, symbol names are written as identifiers
+//
+// lexer! {
+//     keywords: ["def", "if"],
+//     symbols: { ':' => Colon, '(' => OpenParen },
+//     sections: { string: '"' .. '"', comment: "//" .. "\n" },
+//     allow_whitespace: false,
+// }
+#[macro_export]
+macro_rules! lexer {
+    (
+        $(keywords: [$($keyword:literal),* $(,)?] $(,)?)?
+        $(symbols: { $($symbol:literal => $name:ident),* $(,)? } $(,)?)?
+        $(sections: { $($section:ident : $start:literal .. $end:literal),* $(,)? } $(,)?)?
+        $(allow_whitespace: $allow_whitespace:literal $(,)?)?
+    ) => {
+        $crate::Lexer::new(
+            &[$($($keyword.to_string()),*)?],
+            &[$($($crate::Section::new(stringify!($section), &$start.to_string(), &$end.to_string())),*)?],
+            &[$($(($symbol, stringify!($name).to_string())),*)?],
+            false $(|| $allow_whitespace)?,
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn lexer_macro_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = lexer! {
+            keywords: ["def", "if"],
+            symbols: { ':' => Colon, '(' => OpenParen, ')' => CloseParen },
+            sections: { string: '"' .. '"', comment: "//" .. "\n" },
+        };
+        assert_eq!(lexer.sections[1].start, "//");
+        assert!(!lexer.allow_whitespace);
+
+        lexer.load_str("def f(): \"x\" // y\n");
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("def")?;
+        tokens[2].is_symbol("OpenParen")?;
+        tokens[4].is_symbol("Colon")?;
+        assert_eq!(tokens[5].is_section("string")?, "x");
+        assert_eq!(tokens[6].is_section("comment")?, " y");
+
+        let lexer = lexer! { allow_whitespace: true };
+        assert!(lexer.keywords.is_empty());
+        assert!(lexer.allow_whitespace);
+        return Ok(());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceLoc {
+    pub source: SourceId,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceToken {
+    pub source: SourceId,
+    pub token: Token,
+}
+
+impl SourceToken {
+    pub fn loc(&self) -> SourceLoc {
+        let loc = self.token.loc();
+        return SourceLoc {
+            source: self.source,
+            line: loc.line as usize,
+            col: loc.col as usize,
+        };
+    }
+}
+
+// concatenates the streams in the order given, tokens keep their order within each stream.
+// only the Eof of the last stream that has one is kept, so the merged stream ends exactly once
+pub fn merge_streams(streams: Vec<(SourceId, Vec<Token>)>) -> Vec<SourceToken> {
+    let last_eof = streams.iter().rposition(|(_, tokens)| tokens.iter().any(|token| matches!(token, Token::Eof(_))));
+
+    let mut merged = Vec::with_capacity(streams.iter().map(|(_, tokens)| tokens.len()).sum());
+    for (index, (source, tokens)) in streams.into_iter().enumerate() {
+        for token in tokens {
+            if matches!(token, Token::Eof(_)) && Some(index) != last_eof {
+                continue;
+            }
+            merged.push(SourceToken { source, token });
+        }
+    }
+    return merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{merge_streams, Lexer, SourceId, SourceLoc, Token};
+
+    #[test]
+    fn merge_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["use".to_string()], &[], &[], false);
+        lexer.emit_eof = true;
+
+        lexer.load_str("use std");
+        let prelude = lexer.tokenize()?;
+        lexer.load_str("a\nb");
+        let user = lexer.tokenize()?;
+
+        let merged = merge_streams(vec![(SourceId(0), prelude), (SourceId(1), user)]);
+        assert_eq!(merged.len(), 5);
+        merged[0].token.is_keyword("use")?;
+        assert_eq!(merged[1].source, SourceId(0));
+        assert_eq!(merged[2].source, SourceId(1));
+        assert_eq!(merged[3].loc(), SourceLoc { source: SourceId(1), line: 2, col: merged[3].token.loc().col as usize });
+        assert!(matches!(merged[4].token, Token::Eof(_)));
+        assert_eq!(merged.iter().filter(|token| matches!(token.token, Token::Eof(_))).count(), 1);
+        return Ok(());
+    }
+}
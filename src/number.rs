@@ -0,0 +1,87 @@
+use crate::Lexer;
+
+// the syntax of float literals as a whole, see Lexer::set_number_style. the default is the style of
+// a new lexer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NumberStyle {
+    pub decimal_separator: char,
+    pub allow_exponent: bool,
+    pub allow_leading_dot: bool,
+    pub allow_trailing_dot: bool,
+}
+
+impl Default for NumberStyle {
+    fn default() -> NumberStyle {
+        return NumberStyle {
+            decimal_separator: '.',
+            allow_exponent: true,
+            allow_leading_dot: false,
+            allow_trailing_dot: true,
+        };
+    }
+}
+
+impl NumberStyle {
+    // 3,14 with digits on both sides of the comma and no exponents, for data written the way much
+    // of europe writes numbers. ',' must not be a symbol for these to lex as one token
+    pub fn decimal_comma() -> NumberStyle {
+        return NumberStyle {
+            decimal_separator: ',',
+            allow_exponent: false,
+            allow_leading_dot: false,
+            allow_trailing_dot: false,
+        };
+    }
+}
+
+impl Lexer {
+    pub fn number_style(&self) -> NumberStyle {
+        return NumberStyle {
+            decimal_separator: self.decimal_separator,
+            allow_exponent: self.allow_exponent,
+            allow_leading_dot: self.allow_leading_dot,
+            allow_trailing_dot: self.allow_trailing_dot,
+        };
+    }
+
+    pub fn set_number_style(&mut self, style: NumberStyle) {
+        self.decimal_separator = style.decimal_separator;
+        self.allow_exponent = style.allow_exponent;
+        self.allow_leading_dot = style.allow_leading_dot;
+        self.allow_trailing_dot = style.allow_trailing_dot;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, NumberStyle};
+
+    #[test]
+    fn number_style_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[(';', "semicolon".to_string())], false);
+        assert_eq!(lexer.number_style(), NumberStyle::default());
+        lexer.load_str("1. 2.5 3,14");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_float()?, 1.0);
+        assert_eq!(tokens[2].is_ident()?, "3,14");
+
+        lexer.set_number_style(NumberStyle::decimal_comma());
+        lexer.load_str("2,25;0,5;1,;2e3;12 1.5");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_float()?, 2.25);
+        assert_eq!(tokens[2].is_float()?, 0.5);
+        assert_eq!(tokens[4].is_ident()?, "1,");
+        assert_eq!(tokens[6].is_ident()?, "2e3");
+        assert_eq!(tokens[8].is_integer()?, 12);
+        assert_eq!(tokens[9].is_ident()?, "1.5");
+
+        lexer.set_number_style(NumberStyle {
+            allow_leading_dot: true,
+            ..NumberStyle::decimal_comma()
+        });
+        lexer.load_str(",25");
+        assert_eq!(lexer.tokenize()?[0].is_float()?, 0.25);
+        return Ok(());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Lexer, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Associativity {
+    #[default]
+    Left,
+    Right,
+    // chaining the operator without parentheses is an error, like a == b == c
+    Neither,
+}
+
+// parser metadata for an operator symbol, the lexer only stores it. a higher precedence binds tighter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolInfo {
+    pub precedence: u32,
+    pub associativity: Associativity,
+    pub arity: u8,
+}
+
+impl SymbolInfo {
+    pub fn binary(precedence: u32, associativity: Associativity) -> SymbolInfo {
+        return SymbolInfo {
+            precedence,
+            associativity,
+            arity: 2,
+        };
+    }
+
+    // prefix and postfix operators
+    pub fn unary(precedence: u32) -> SymbolInfo {
+        return SymbolInfo {
+            precedence,
+            associativity: Associativity::Right,
+            arity: 1,
+        };
+    }
+}
+
+impl Lexer {
+    // a symbol may get one entry per arity, like a unary and a binary minus
+    pub fn add_symbol_info(&mut self, name: &str, info: SymbolInfo) {
+        self.symbol_info.retain(|(existing, existing_info)| existing != name || existing_info.arity != info.arity);
+        self.symbol_info.push((name.to_string(), info));
+    }
+
+    // the metadata registered for a symbol token, one entry per arity, empty for any other token
+    pub fn symbol_info(&self, token: &Token) -> Vec<&SymbolInfo> {
+        if let Token::Symbol(_, name, _) = token {
+            return self.symbol_info.iter().filter(|(symbol, _)| symbol == name).map(|(_, info)| info).collect();
+        }
+        return Vec::new();
+    }
+
+    pub fn symbol_info_with_arity(&self, token: &Token, arity: u8) -> Option<&SymbolInfo> {
+        return self.symbol_info(token).into_iter().find(|info| info.arity == arity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Associativity, Lexer, SymbolInfo};
+
+    #[test]
+    fn symbol_info_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('-', "minus".to_string()), ('^', "caret".to_string()), ('(', "openparen".to_string()), (')', "closeparen".to_string())], false);
+        lexer.add_symbol_info("minus", SymbolInfo::binary(10, Associativity::Left));
+        lexer.add_symbol_info("minus", SymbolInfo::unary(30));
+        lexer.add_symbol_info("caret", SymbolInfo::binary(20, Associativity::Left));
+        lexer.add_symbol_info("caret", SymbolInfo::binary(20, Associativity::Right));
+
+        lexer.load_str("-a ^ (b - c)");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(lexer.symbol_info_with_arity(&tokens[0], 1), Some(&SymbolInfo::unary(30)));
+        assert_eq!(lexer.symbol_info_with_arity(&tokens[5], 2).map(|info| info.precedence), Some(10));
+        assert_eq!(lexer.symbol_info(&tokens[2]), vec![&SymbolInfo::binary(20, Associativity::Right)]);
+        assert!(lexer.symbol_info(&tokens[3]).is_empty());
+        assert!(lexer.symbol_info(&tokens[1]).is_empty());
+        return Ok(());
+    }
+}
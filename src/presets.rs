@@ -0,0 +1,130 @@
+// ready made lexers for common families of languages, meant as a starting point to tweak. the
+// options they set are public fields, so a preset can be changed like any other lexer
+use crate::{Escapes, Lexer, Section};
+
+fn keywords(keywords: &[&str]) -> Vec<String> {
+    return keywords.iter().map(|keyword| keyword.to_string()).collect();
+}
+
+fn symbols(symbols: &[(char, &str)]) -> Vec<(char, String)> {
+    return symbols.iter().map(|(symbol, name)| (*symbol, name.to_string())).collect();
+}
+
+fn string(name: &str, quote: &str) -> Section {
+    let mut section = Section::new(name, quote, quote);
+    section.escapes = Escapes::Decoded('\\');
+    return section;
+}
+
+// c, java, javascript and friends. line and block comments are comment tokens and 'c' a char
+pub fn c_like() -> Lexer {
+    let mut lexer = Lexer::new(
+        &keywords(&[
+            "if", "else", "while", "for", "do", "switch", "case", "default", "break", "continue", "return", "goto",
+            "struct", "union", "enum", "typedef", "const", "static", "extern", "void", "sizeof",
+        ]),
+        &[string("string", "\""), Section::line("comment", "//"), Section::new("blockcomment", "/*", "*/")],
+        &symbols(&[
+            ('(', "LeftParen"), (')', "RightParen"), ('{', "LeftBrace"), ('}', "RightBrace"), ('[', "LeftBracket"), (']', "RightBracket"),
+            (';', "Semicolon"), (',', "Comma"), ('.', "Dot"), ('=', "Equal"), ('+', "Plus"), ('-', "Minus"), ('*', "Star"),
+            ('/', "Slash"), ('%', "Percent"), ('<', "Less"), ('>', "Greater"), ('!', "Bang"), ('&', "Ampersand"),
+            ('|', "Pipe"), ('^', "Caret"), ('~', "Tilde"), ('?', "Question"), (':', "Colon"),
+        ]),
+        false,
+    );
+    lexer.whitespace = vec![' ', '\t', '\r'];
+    lexer.comment_sections = vec!["comment".to_string(), "blockcomment".to_string()];
+    lexer.char_quote = Some('\'');
+    return lexer;
+}
+
+// true, false and null are keywords. a negative number is a Minus symbol followed by the number
+pub fn json() -> Lexer {
+    let mut lexer = Lexer::new(
+        &keywords(&["true", "false", "null"]),
+        &[string("string", "\"")],
+        &symbols(&[('{', "LeftBrace"), ('}', "RightBrace"), ('[', "LeftBracket"), (']', "RightBracket"), (':', "Colon"), (',', "Comma"), ('-', "Minus")]),
+        false,
+    );
+    lexer.whitespace = vec![' ', '\t', '\r'];
+    lexer.strict = true;
+    return lexer;
+}
+
+// [section] headers are header sections, ; and # start comments. values are not quoted so the
+// words of a value come out as separate tokens
+pub fn ini() -> Lexer {
+    let mut lexer = Lexer::new(
+        &[],
+        &[Section::new("header", "[", "]"), Section::line("comment", ";"), Section::line("hashcomment", "#"), string("string", "\"")],
+        &symbols(&[('=', "Equal")]),
+        false,
+    );
+    lexer.whitespace = vec![' ', '\t', '\r'];
+    lexer.comment_sections = vec!["comment".to_string(), "hashcomment".to_string()];
+    return lexer;
+}
+
+fn is_lisp_ident_char(character: char) -> bool {
+    return character.is_alphanumeric() || "_-+*/<>=!?$%&~^.:".contains(character);
+}
+
+// parentheses and quotes are the only symbols, so names like set-car! and <= are identifiers
+pub fn lisp_like() -> Lexer {
+    let mut lexer = Lexer::new(
+        &[],
+        &[string("string", "\""), Section::line("comment", ";")],
+        &symbols(&[('(', "LeftParen"), (')', "RightParen"), ('[', "LeftBracket"), (']', "RightBracket"), ('\'', "Quote"), ('`', "Backquote"), (',', "Unquote")]),
+        false,
+    );
+    lexer.whitespace = vec![' ', '\t', '\r'];
+    lexer.comment_sections = vec!["comment".to_string()];
+    lexer.ident_char = is_lisp_ident_char;
+    return lexer;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Token;
+
+    #[test]
+    fn presets_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = c_like();
+        lexer.load_str("if (a >= 'b') {\n\treturn \"c\\n\"; // done\n}");
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("if")?;
+        tokens[4].is_symbol("Equal")?;
+        assert!(matches!(tokens[5], Token::Char('b', _)));
+        assert_eq!(tokens[9].is_section("string")?, "c\n");
+        assert!(matches!(&tokens[11], Token::Comment(name, _, _) if name == "comment"));
+        assert!(lexer.validate().iter().all(|error| error.is_warning()));
+
+        let mut lexer = json();
+        lexer.load_str("{\"a\": [1, -2.5e3, true, null]}");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[1].is_section("string")?, "a");
+        assert_eq!(tokens[4].is_integer()?, 1);
+        tokens[6].is_symbol("Minus")?;
+        assert_eq!(tokens[7].is_float()?, 2.5e3);
+        tokens[9].is_keyword("true")?;
+        lexer.load_str("{\"a\": @}");
+        assert!(lexer.tokenize().is_err());
+
+        let mut lexer = ini();
+        lexer.load_str("; settings\n[server]\nport = 80\n");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[1].is_section("header")?, "server");
+        assert_eq!(tokens[2].is_ident()?, "port");
+        assert_eq!(tokens[4].is_integer()?, 80);
+
+        let mut lexer = lisp_like();
+        lexer.load_str("(define (set-x! v) '(<= v 1)) ; note");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[3].is_ident()?, "set-x!");
+        tokens[6].is_symbol("Quote")?;
+        assert_eq!(tokens[8].is_ident()?, "<=");
+        return Ok(());
+    }
+}
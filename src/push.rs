@@ -0,0 +1,201 @@
+use crate::{LexError, Lexer, Loc, Run, Token};
+
+use std::str::Utf8Error;
+
+// lexes input handed over in chunks as it arrives. only complete lines are lexed, the rest waits
+// for the next chunk, so a section or an utf-8 character may be split anywhere. a section open at
+// the end of a line is carried on to the line it ends on, and is only lexed again once a chunk
+// brings something that could end it. with custom rules there is no bound on how far a rule may
+// look ahead, see Lexer::required_lookahead, so nothing is lexed before finish
+pub struct PushLexer<'a> {
+    lexer: &'a Lexer,
+    bytes: Vec<u8>,
+    pending: String,
+    run: Run,
+    loc: Loc,
+    // where the last scan was left open in pending and the strings it waits for, see Scanned::closes
+    waiting: Option<(usize, Option<Vec<String>>)>,
+}
+
+impl<'a> PushLexer<'a> {
+    pub fn new(lexer: &'a Lexer) -> PushLexer<'a> {
+        return PushLexer {
+            lexer,
+            bytes: Vec::new(),
+            pending: String::new(),
+            run: lexer.new_run(),
+            loc: Loc::new(lexer.file),
+            waiting: None,
+        };
+    }
+
+    // whether the input after the last open scan could let a scan get further
+    fn can_close(&self) -> bool {
+        let Some((end, closes)) = &self.waiting else {
+            return true;
+        };
+        let Some(closes) = closes else {
+            return true;
+        };
+
+        // a string may have started before the end of the last scan
+        let longest = closes.iter().map(String::len).max().unwrap_or_default();
+        let mut start = end.saturating_sub(longest);
+        while !self.pending.is_char_boundary(start) {
+            start -= 1;
+        }
+        return closes.iter().any(|close| self.pending[start..].contains(close.as_str()));
+    }
+
+    // the tokens of every line the chunk completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        self.extend(chunk);
+        self.decode(false)?;
+        return Ok(self.lex(false)?);
+    }
+
+    // lexes whatever is left as the end of the input and starts over, so the next feed begins new input
+    pub fn finish(&mut self) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let tokens = match self.decode(true) {
+            Ok(()) => self.lex(true).map_err(Into::into),
+            Err(err) => Err(err.into()),
+        };
+        *self = PushLexer::new(self.lexer);
+        return tokens;
+    }
+
+    pub(crate) fn extend(&mut self, chunk: &[u8]) {
+        self.bytes.extend_from_slice(chunk);
+    }
+
+    // the bytes fed that have not been lexed yet
+    pub fn buffered(&self) -> usize {
+        return self.pending.len() + self.bytes.len();
+    }
+
+    // moves the valid utf-8 prefix of the raw bytes into pending, keeping an incomplete trailing character
+    pub(crate) fn decode(&mut self, finished: bool) -> Result<(), Utf8Error> {
+        let valid = match std::str::from_utf8(&self.bytes) {
+            Ok(string) => string.len(),
+            Err(err) if err.error_len().is_none() && !finished => err.valid_up_to(),
+            Err(err) => return Err(err),
+        };
+
+        self.pending.push_str(std::str::from_utf8(&self.bytes[..valid])?);
+        self.bytes.drain(..valid);
+        return Ok(());
+    }
+
+    // lexes every complete line that doesn't end inside a section
+    pub(crate) fn lex(&mut self, finished: bool) -> Result<Vec<Token>, LexError> {
+        let limit = if finished {
+            self.pending.len()
+        } else {
+            // the last character must stay behind as lookahead
+            let last = self.pending.char_indices().next_back().map_or(0, |(last, _)| last);
+            let limit = match self.pending[..last].rfind('\n') {
+                Some(newline) => newline + 1,
+                None => return Ok(Vec::new()),
+            };
+
+            // everything before the limit must see its full lookahead, without a bound wait for the end
+            match self.lexer.required_lookahead() {
+                Some(lookahead) if self.pending.len() - limit >= lookahead && self.can_close() => limit,
+                _ => return Ok(Vec::new()),
+            }
+        };
+
+        // a section open at the limit is carried on in the same scan, like next_batch does
+        let mut run = Run {
+            extend: true,
+            ..self.run.clone()
+        };
+        let mut loc = self.loc;
+        let (scanned, open) = self.lexer.scan_until(&self.pending, limit, &mut run, &mut loc, finished)?;
+        let lookahead = self.lexer.required_lookahead().unwrap_or_default();
+        if open && !finished {
+            self.waiting = Some((scanned.end, scanned.closes.map(|closes| closes.into_iter().map(str::to_string).collect())));
+            return Ok(Vec::new());
+        } else if !finished && self.pending.len() - scanned.end < lookahead {
+            // carried on too close to the end of the input, the lookahead past it is still missing
+            self.waiting = None;
+            return Ok(Vec::new());
+        }
+
+        let tokens = scanned.tokens.into_iter().filter_map(|token| self.lexer.finish_token(token)).collect();
+        self.pending.drain(..scanned.end);
+        self.run = run;
+        self.loc = loc;
+        self.waiting = None;
+        return Ok(tokens);
+    }
+}
+
+impl Lexer {
+    pub fn push_lexer(&self) -> PushLexer<'_> {
+        return PushLexer::new(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section};
+
+    #[test]
+    fn push_lexer_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        let source = "let a = \"multi\nline é\"\nlet b = 2\n";
+
+        let mut push = lexer.push_lexer();
+        let mut tokens = Vec::new();
+        for chunk in source.as_bytes().chunks(3) {
+            tokens.extend(push.feed(chunk)?);
+        }
+        assert!(push.buffered() > 0);
+        tokens.extend(push.finish()?);
+        assert_eq!(push.buffered(), 0);
+
+        let mut batch = lexer.clone();
+        batch.load_str(source);
+        assert_eq!(tokens, batch.tokenize()?);
+
+        // mid-section after the first chunk, nothing is emitted until the section ends
+        assert!(push.feed(b"let c = \"open\n")?.is_empty());
+        assert_eq!(push.feed(b"still\" 1\nmore input")?.len(), 5);
+        return Ok(());
+    }
+
+    #[test]
+    fn open_section_push_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(&[], &[Section::new("comment", "/*", "*/"), Section::new("string", "\"", "\"")], &[], false);
+        let mut push = lexer.push_lexer();
+        assert_eq!(push.feed(b"a /* open\n")?.len(), 0);
+        assert_eq!(push.feed(b"x\n")?.len(), 0);
+
+        // lines that can't end the comment are not lexed again
+        for _ in 0..1000 {
+            assert!(push.feed(b" still open\n")?.is_empty());
+            assert!(!push.can_close());
+        }
+        assert_eq!(push.feed(b"*/ b\nmore input")?.len(), 3);
+        assert_eq!(push.finish()?.len(), 2);
+
+        // every chunking lexes like the whole input
+        let alphabet = ['/', '*', '\n', '\\', '"', 'a', ' '];
+        let mut sources = vec![String::new()];
+        for _ in 0..5 {
+            sources = sources.iter().flat_map(|source| alphabet.iter().map(move |character| format!("{}{}", source, character))).collect();
+            for source in sources.iter().map(|source| [source.as_str(); 4].join("\n")) {
+                let mut full = lexer.clone();
+                full.load_str(&source);
+                let mut tokens = Vec::new();
+                for chunk in source.as_bytes().chunks(1) {
+                    tokens.extend(push.feed(chunk)?);
+                }
+                tokens.extend(push.finish()?);
+                assert_eq!(tokens, full.tokenize()?, "{:?}", source);
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{Lexer, Section, Token};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+#[pyclass(name = "Section", from_py_object)]
+#[derive(Clone)]
+pub struct PySection {
+    section: Section,
+}
+
+#[pymethods]
+impl PySection {
+    #[new]
+    fn new(name: &str, start: &str, end: &str) -> PySection {
+        return PySection {
+            section: Section::new(name, start, end),
+        };
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("Section({:?}, {:?}, {:?})", self.section.name, self.section.start, self.section.end);
+    }
+}
+
+// name is the section, symbol, custom or comment name, None for other tokens
+#[pyclass(name = "Token", get_all, skip_from_py_object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PyToken {
+    kind: String,
+    text: String,
+    name: Option<String>,
+    line: u32,
+    col: u32,
+}
+
+impl From<&Token> for PyToken {
+    fn from(token: &Token) -> PyToken {
+        return PyToken {
+            kind: token.kind().to_string(),
+            text: token.as_string(),
+            name: token.name().map(str::to_string),
+            line: token.loc().line,
+            col: token.loc().col,
+        };
+    }
+}
+
+#[pymethods]
+impl PyToken {
+    fn __repr__(&self) -> String {
+        return format!("Token({}, {:?}, {}:{})", self.kind, self.text, self.line, self.col);
+    }
+}
+
+#[pyclass(name = "Lexer")]
+pub struct PyLexer {
+    lexer: Lexer,
+}
+
+#[pymethods]
+impl PyLexer {
+    #[new]
+    #[pyo3(signature = (keywords = Vec::new(), sections = Vec::new(), symbols = Vec::new(), allow_whitespace = false))]
+    fn new(keywords: Vec<String>, sections: Vec<PySection>, symbols: Vec<(char, String)>, allow_whitespace: bool) -> PyLexer {
+        let sections = sections.into_iter().map(|section| section.section).collect::<Vec<Section>>();
+        return PyLexer {
+            lexer: Lexer::new(&keywords, &sections, &symbols, allow_whitespace),
+        };
+    }
+
+    fn tokenize(&mut self, input: &str) -> PyResult<Vec<PyToken>> {
+        self.lexer.load_str(input);
+        let tokens = self.lexer.tokenize().map_err(|err| PyValueError::new_err(err.to_string()))?;
+        return Ok(tokens.iter().map(PyToken::from).collect());
+    }
+}
+
+// the python module, build it as an extension with maturin or `cargo rustc --features python --crate-type cdylib`
+#[pymodule]
+fn lexin(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyLexer>()?;
+    module.add_class::<PySection>()?;
+    module.add_class::<PyToken>()?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn py_lexer_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = PyLexer::new(vec!["let".to_string()], vec![PySection::new("string", "\"", "\"")], vec![('=', "equal".to_string())], false);
+
+        let tokens = lexer.tokenize("let a = \"b\"")?;
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0], PyToken { kind: "Keyword".to_string(), text: "let".to_string(), name: None, line: 1, col: 3 });
+        assert_eq!(tokens[2].name.as_deref(), Some("equal"));
+        assert_eq!(tokens[3].text, "b");
+        return Ok(());
+    }
+}
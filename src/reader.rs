@@ -0,0 +1,104 @@
+use crate::{Lexer, PushLexer, Token};
+
+use std::collections::VecDeque;
+use std::io::Read;
+
+const CHUNK_SIZE: usize = 8192;
+
+pub struct TokenReader<'a, R: Read> {
+    reader: R,
+    push: PushLexer<'a>,
+    tokens: VecDeque<Token>,
+    finished: bool,
+}
+
+impl<'a, R: Read> TokenReader<'a, R> {
+    pub(crate) fn new(lexer: &'a Lexer, reader: R) -> TokenReader<'a, R> {
+        return TokenReader {
+            reader,
+            push: lexer.push_lexer(),
+            tokens: VecDeque::new(),
+            finished: false,
+        };
+    }
+
+    // reads another chunk and lexes every complete line that doesn't end inside a section
+    fn fill(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut chunk = [0; CHUNK_SIZE];
+        let read = self.reader.read(&mut chunk)?;
+        self.finished = read == 0;
+
+        let tokens = if self.finished { self.push.finish()? } else { self.push.feed(&chunk[..read])? };
+        self.tokens.extend(tokens);
+        return Ok(());
+    }
+}
+
+impl<R: Read> Iterator for TokenReader<'_, R> {
+    type Item = Result<Token, Box<dyn std::error::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.tokens.is_empty() && !self.finished {
+            if let Err(err) = self.fill() {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        }
+        return self.tokens.pop_front().map(Ok);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section};
+
+    use std::io::Read;
+
+    #[test]
+    fn reader_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+
+        let mut source = String::new();
+        for index in 0..2000 {
+            source.push_str(&format!("let x{} = \"multi\nline é\" 1_000\n", index));
+        }
+        source.push(' ');
+
+        let mut batch = lexer.clone();
+        batch.load_str(&source);
+
+        let streamed = lexer.tokenize_reader(source.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(streamed, batch.tokenize()?);
+        return Ok(());
+    }
+
+    // hands out a single byte per read so every position is a chunk boundary
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buffer[0] = *first;
+            self.0 = rest;
+            return Ok(1);
+        }
+    }
+
+    #[test]
+    fn lookahead_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("directive", "\n##", "\n")], &[(';', "semi".to_string())], false);
+        assert_eq!(lexer.required_lookahead(), Some(8));
+
+        let source = "a\n##x\nb;\n##y\n;c\n";
+        let streamed = lexer.tokenize_reader(Trickle(source.as_bytes())).collect::<Result<Vec<_>, _>>()?;
+        lexer.load_str(source);
+        assert_eq!(streamed, lexer.tokenize()?);
+        assert_eq!(streamed[1].is_section("directive")?, "x");
+
+        lexer.add_rule("any", |_| None);
+        assert_eq!(lexer.required_lookahead(), None);
+        return Ok(());
+    }
+}
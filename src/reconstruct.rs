@@ -0,0 +1,192 @@
+use crate::{Escapes, Fragment, Lexer, Section, Token};
+
+// the delimiters a token is written with when there is no lexer to ask, see Token::lexeme
+fn default_section(name: &str, interpolated: bool) -> Section {
+    let mut section = Section::new(name, "\"", "\"");
+    if interpolated {
+        section.interpolation = Some(("{".to_string(), "}".to_string()));
+    }
+    return section;
+}
+
+// escapes the body so lexing it again with the section yields the same body
+fn escape_body(section: &Section, body: &str) -> String {
+    let (Escapes::Stripped(escape) | Escapes::Decoded(escape)) = section.escapes else {
+        return body.to_string();
+    };
+    let decoded = matches!(section.escapes, Escapes::Decoded(_));
+
+    let mut escaped = String::new();
+    let mut rest = body;
+    while let Some(character) = rest.chars().next() {
+        let interpolation = section.interpolation.as_ref().is_some_and(|(open, _)| rest.starts_with(open.as_str()));
+        if (!section.end.is_empty() && rest.starts_with(&section.end)) || character == escape || interpolation {
+            escaped.push(escape);
+        }
+
+        match character {
+            '\n' if decoded && section.end.contains('\n') => escaped.extend([escape, 'n']),
+            '\0' if decoded => escaped.extend([escape, '0']),
+            _ => escaped.push(character),
+        }
+        rest = &rest[character.len_utf8()..];
+    }
+    return escaped;
+}
+
+impl Lexer {
+    // the source text of a single token, using this lexer's delimiters for sections and comments
+    pub fn lexeme(&self, token: &Token) -> String {
+        return match token {
+            Token::Section(name, body, _) | Token::Comment(name, body, _) => {
+                match self.sections.iter().chain(self.modes.iter().flat_map(|mode| &mode.sections)).find(|section| section.name == *name) {
+                    Some(section) if section.keep_delimiters => body.clone(),
+                    Some(section) => format!("{}{}{}", section.start, escape_body(section, body), section.end),
+                    None => body.clone(),
+                }
+            },
+            Token::Interpolated(name, fragments, _) => {
+                let Some(section) = self.sections.iter().chain(self.modes.iter().flat_map(|mode| &mode.sections)).find(|section| section.name == *name) else {
+                    return token.as_string();
+                };
+                let (open, close) = section.interpolation.clone().unwrap_or_default();
+                let body = fragments.iter().map(|fragment| match fragment {
+                    Fragment::Text(text) => escape_body(section, text),
+                    Fragment::Tokens(tokens) => format!("{}{}{}", open, tokens.iter().map(|token| self.lexeme(token)).collect::<Vec<String>>().join(" "), close),
+                }).collect::<String>();
+                format!("{}{}{}", section.start, body, section.end)
+            },
+            Token::Char(character, _) => char_lexeme(*character, self.char_quote.unwrap_or('\'')),
+            Token::Pragma(directive, _) => format!("{}{}", self.pragma_prefix.as_deref().unwrap_or_default(), directive),
+            _ => token.as_string(),
+        };
+    }
+
+    // re-emits source for the tokens, keeping their lines but separating tokens on a line by a
+    // single space. original spacing and number formatting is lost, see tokenize_lossless for that
+    pub fn reconstruct(&self, tokens: &[Token]) -> String {
+        return layout(tokens, |token| self.lexeme(token));
+    }
+}
+
+impl Token {
+    // the source text of the token as Lexer::lexeme would give it for a lexer with the usual
+    // delimiters: sections and interpolated sections in double quotes with {} around the
+    // interpolations, comments between /* and */ and chars in single quotes, escaped with a backslash
+    pub fn lexeme(&self) -> String {
+        return match self {
+            Token::Section(name, body, _) => {
+                let section = default_section(name, false);
+                format!("{}{}{}", section.start, escape_body(&section, body), section.end)
+            },
+            Token::Comment(_, text, _) => format!("/*{}*/", text),
+            Token::Interpolated(name, fragments, _) => {
+                let section = default_section(name, true);
+                let body = fragments.iter().map(|fragment| match fragment {
+                    Fragment::Text(text) => escape_body(&section, text),
+                    Fragment::Tokens(tokens) => format!("{{{}}}", tokens.iter().map(Token::lexeme).collect::<Vec<String>>().join(" ")),
+                }).collect::<String>();
+                format!("{}{}{}", section.start, body, section.end)
+            },
+            Token::Char(character, _) => char_lexeme(*character, '\''),
+            _ => self.as_string(),
+        };
+    }
+
+    // like Lexer::reconstruct with the delimiters of Token::lexeme
+    pub fn reconstruct(tokens: &[Token]) -> String {
+        return layout(tokens, Token::lexeme);
+    }
+}
+
+fn char_lexeme(character: char, quote: char) -> String {
+    let escaped = match character {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        '\r' => "\\r".to_string(),
+        '\0' => "\\0".to_string(),
+        '\\' => "\\\\".to_string(),
+        character if character == quote => format!("\\{}", character),
+        character => character.to_string(),
+    };
+    return format!("{}{}{}", quote, escaped, quote);
+}
+
+// lays the lexemes of the tokens out on their lines, a single space apart
+fn layout(tokens: &[Token], lexeme: impl Fn(&Token) -> String) -> String {
+    let mut source = String::new();
+    let mut line = 1;
+    let mut first = true;
+    for token in tokens.iter().filter(|token| !matches!(token, Token::Indent(_) | Token::Dedent(_) | Token::Newline(_) | Token::Eof(_))) {
+        let lexeme = lexeme(token);
+        let mut end = token.loc().line as usize;
+
+        // sections, comments and pragmas ending in a newline were flushed on the next line
+        if lexeme.ends_with('\n') {
+            end = end.saturating_sub(1);
+        }
+        let start = end.saturating_sub(lexeme.matches('\n').count());
+
+        if start > line {
+            source.push_str(&"\n".repeat(start - line));
+        } else if !first && !source.ends_with('\n') {
+            source.push(' ');
+        }
+
+        source.push_str(&lexeme);
+        line = line.max(start) + lexeme.matches('\n').count();
+        first = false;
+    }
+    return source;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Escapes, Lexer, Section, Token};
+
+    #[test]
+    fn reconstruct_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut string = Section::new("string", "\"", "\"");
+        string.escapes = Escapes::Decoded('\\');
+
+        let mut lexer = Lexer::new(
+            &["let".to_string()],
+            &[string, Section::new("comment", "//", "\n")],
+            &[('=', "equals".to_string()), (';', "semi".to_string())],
+            false,
+        );
+        lexer.comment_sections.push("comment".to_string());
+        lexer.emit_eof = true;
+
+        lexer.load_str("let a = \"x\\\"y\\\\\";  // note\n\nlet   b=1;");
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[3].as_string(), "x\"y\\");
+        assert_eq!(tokens[3].to_string(), "\"x\\\"y\\\\\"");
+
+        let source = lexer.reconstruct(&tokens);
+        assert_eq!(source, "let a = \"x\\\"y\\\\\" ; // note\n\nlet b = 1 ;");
+
+        lexer.load_str(&source);
+        assert_eq!(lexer.tokenize()?.iter().map(|token| token.as_string()).collect::<Vec<_>>(), tokens.iter().map(|token| token.as_string()).collect::<Vec<_>>());
+        return Ok(());
+    }
+
+    #[test]
+    fn token_reconstruct_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &["let".to_string()],
+            &[Section::new("string", "\"", "\""), Section::new("comment", "/*", "*/")],
+            &[('=', "equals".to_string()), (';', "semi".to_string())],
+            false,
+        );
+        lexer.comment_sections.push("comment".to_string());
+        lexer.char_quote = Some('\'');
+
+        let source = "let a = \"x\\\"y\\\\\" ; /* note */\nlet b = '\\n' ;";
+        lexer.load_str(source);
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens.iter().map(|token| token.to_string()).collect::<Vec<String>>(), ["let", "a", "=", "\"x\\\"y\\\\\"", ";", "/* note */", "let", "b", "=", "'\\n'", ";"]);
+        assert_eq!(Token::reconstruct(&tokens), source);
+        return Ok(());
+    }
+}
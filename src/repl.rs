@@ -0,0 +1,235 @@
+use crate::{group_tokens, GroupError, LexError, Lexer, Loc, Run, Token};
+
+// what a submitted line did to the input of a LineLexer
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineStatus {
+    // the tokens of every line since the last complete input
+    Complete(Vec<Token>),
+    // a section or a delimiter pair is still open, the next line continues the input
+    Incomplete,
+}
+
+// lexes the input of a repl one line at a time. modes and positions carry over from one complete
+// input to the next, and input left open waits for more lines before any of it is lexed for good.
+// every line is lexed once, as it would be as part of the whole input, a section left open is lexed
+// again only once a line brings something that could end it
+pub struct LineLexer<'a> {
+    lexer: &'a Lexer,
+    // open and close symbol names that must balance, like in group_tokens
+    pairs: Vec<(String, String)>,
+    // the lines of the input so far and a line break after them, standing in for the lookahead of
+    // the next line so that the last line break is lexed
+    pending: String,
+    // the tokens of the lines of the input lexed so far and where lexing got to
+    lexed: Vec<Token>,
+    unlexed: usize,
+    progress: (Run, Loc),
+    // how far into pending a section was left open and the strings it waits for, see Scanned::closes
+    waiting: Option<(usize, Vec<String>)>,
+    // the state after the last complete input
+    run: Run,
+    loc: Loc,
+}
+
+impl<'a> LineLexer<'a> {
+    pub fn new(lexer: &'a Lexer, pairs: &[(&str, &str)]) -> LineLexer<'a> {
+        // a section open at the end of a line is carried on to the line it ends on
+        let run = Run {
+            extend: true,
+            ..lexer.new_run()
+        };
+        return LineLexer {
+            lexer,
+            pairs: pairs.iter().map(|(open, close)| (open.to_string(), close.to_string())).collect(),
+            pending: String::new(),
+            lexed: Vec::new(),
+            unlexed: 0,
+            progress: (run.clone(), Loc::new(lexer.file)),
+            waiting: None,
+            run,
+            loc: Loc::new(lexer.file),
+        };
+    }
+
+    // line is taken without its line ending. an error drops the pending input, like a repl would
+    pub fn feed_line(&mut self, line: &str) -> Result<LineStatus, Box<dyn std::error::Error>> {
+        self.pending.pop();
+        self.pending.push_str(line);
+        self.pending.push_str("\n\n");
+
+        if let Err(err) = self.lex() {
+            self.cancel();
+            return Err(err.into());
+        } else if self.waiting.is_some() {
+            return Ok(LineStatus::Incomplete);
+        }
+
+        let pairs = self.pairs.iter().map(|(open, close)| (open.as_str(), close.as_str())).collect::<Vec<(&str, &str)>>();
+        if let Err(errors) = group_tokens(&self.lexed, &pairs) {
+            if errors.iter().any(|error| matches!(error, GroupError::Unclosed(..))) {
+                return Ok(LineStatus::Incomplete);
+            }
+        }
+
+        (self.run, self.loc) = self.progress.clone();
+        let tokens = std::mem::take(&mut self.lexed);
+        self.cancel();
+        return Ok(LineStatus::Complete(tokens));
+    }
+
+    // lexes the lines after the last one lexed, unless a section is open that they can't end
+    fn lex(&mut self) -> Result<(), LexError> {
+        if let Some((searched, closes)) = &self.waiting {
+            // only the new line can bring what the section waits for, it starts after a line break
+            let start = searched - 1;
+            if !closes.iter().any(|close| self.pending[start..].contains(close.as_str())) {
+                return Ok(());
+            }
+        }
+
+        let source = &self.pending[self.unlexed..];
+        let (mut run, mut loc) = self.progress.clone();
+        let (scanned, open) = self.lexer.scan_until(source, source.len() - 1, &mut run, &mut loc, false)?;
+        if open {
+            let closes = scanned.closes.unwrap_or_else(|| vec!["\n"]);
+            self.waiting = Some((self.pending.len() - 1, closes.into_iter().map(str::to_string).collect()));
+            return Ok(());
+        }
+
+        self.lexed.extend(scanned.tokens.into_iter().filter_map(|token| self.lexer.finish_token(token)));
+        self.unlexed += scanned.end;
+        self.progress = (run, loc);
+        self.waiting = None;
+        return Ok(());
+    }
+
+    // lexes the pending input, complete or not, as the end of the input: sections still open end
+    // as they would at the end of a file and the offside rule closes its blocks. the next line
+    // starts new input
+    pub fn finish(&mut self) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let source = self.pending.strip_suffix('\n').unwrap_or_default();
+        let (mut run, mut loc) = self.progress.clone();
+        let scanned = self.lexer.scan_until(&source[self.unlexed..], source.len() - self.unlexed, &mut run, &mut loc, true);
+
+        let mut tokens = std::mem::take(&mut self.lexed);
+        let result = scanned.map(|(scanned, _)| tokens.extend(scanned.tokens.into_iter().filter_map(|token| self.lexer.finish_token(token))));
+        self.run = Run {
+            extend: true,
+            ..self.lexer.new_run()
+        };
+        self.loc = Loc::new(self.lexer.file);
+        self.cancel();
+        result?;
+        return Ok(tokens);
+    }
+
+    // whether lines were submitted that have not made up a complete input yet, a repl shows its
+    // continuation prompt while this is set
+    pub fn is_incomplete(&self) -> bool {
+        return !self.pending.is_empty();
+    }
+
+    // drops the pending input, keeping the state of the inputs completed before it
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+        self.lexed.clear();
+        self.unlexed = 0;
+        self.progress = (self.run.clone(), self.loc);
+        self.waiting = None;
+    }
+}
+
+impl Lexer {
+    pub fn line_lexer(&self, pairs: &[(&str, &str)]) -> LineLexer<'_> {
+        return LineLexer::new(self, pairs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LineStatus, Lexer, Section, Token};
+
+    #[test]
+    fn line_lexer_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(
+            &["def".to_string()],
+            &[Section::new("string", "\"", "\"")],
+            &[('(', "openparen".to_string()), (')', "closeparen".to_string()), ('=', "equal".to_string()), (',', "comma".to_string())],
+            false,
+        );
+        let mut repl = lexer.line_lexer(&[("openparen", "closeparen")]);
+
+        let LineStatus::Complete(tokens) = repl.feed_line("def a = 1")? else {
+            return Err("expected a complete input".into());
+        };
+        assert_eq!(tokens.len(), 4);
+        assert!(!repl.is_incomplete());
+
+        assert_eq!(repl.feed_line("def b = f(1,")?, LineStatus::Incomplete);
+        assert_eq!(repl.feed_line("\"multi")?, LineStatus::Incomplete);
+        assert!(repl.is_incomplete());
+        let LineStatus::Complete(tokens) = repl.feed_line("line\")")? else {
+            return Err("expected a complete input".into());
+        };
+        assert_eq!(tokens[7].is_section("string")?, "multi\nline");
+        assert_eq!(tokens[7].loc().line, 4);
+
+        assert_eq!(repl.feed_line("(")?, LineStatus::Incomplete);
+        repl.cancel();
+        let LineStatus::Complete(tokens) = repl.feed_line("x")? else {
+            return Err("expected a complete input".into());
+        };
+        assert_eq!(tokens[0].loc().line, 5);
+        return Ok(());
+    }
+
+    // the tokens of feeding source a line at a time and finishing, or None on an error
+    fn feed_lines(lexer: &Lexer, pairs: &[(&str, &str)], source: &str) -> Option<Vec<Token>> {
+        let mut repl = lexer.line_lexer(pairs);
+        let mut tokens = Vec::new();
+        for line in source.strip_suffix('\n').unwrap_or(source).split('\n') {
+            if let LineStatus::Complete(complete) = repl.feed_line(line).ok()? {
+                tokens.extend(complete);
+            }
+        }
+        tokens.extend(repl.finish().ok()?);
+        return Some(tokens);
+    }
+
+    #[test]
+    fn line_lexer_offside_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("comment", "/*", "*/"), Section::new("string", "\"", "\"")], &[('(', "openparen".to_string()), (')', "closeparen".to_string())], false);
+        lexer.offside = true;
+
+        // the dedent closing the block comes with the line after it, where tokenize puts it
+        let mut full = lexer.clone();
+        full.load_str(" (\n\n");
+        assert_eq!(feed_lines(&lexer, &[], " (\n\n"), Some(full.tokenize()?));
+        assert_eq!(full.tokenize()?.last().map(Token::loc).map(|loc| (loc.line, loc.col)), Some((3, 1)));
+
+        // an open comment is only lexed again once a line has its end
+        let mut repl = lexer.line_lexer(&[]);
+        assert_eq!(repl.feed_line("a /*")?, LineStatus::Incomplete);
+        for _ in 0..1000 {
+            assert_eq!(repl.feed_line("still open")?, LineStatus::Incomplete);
+        }
+        let LineStatus::Complete(tokens) = repl.feed_line("*/ b")? else {
+            return Err("expected a complete input".into());
+        };
+        assert_eq!(tokens.len(), 4);
+
+        // every input fed a line at a time lexes like the whole input
+        let alphabet = [' ', '(', ')', 'a', '"', '/', '*', '\n'];
+        let mut sources = vec![String::new()];
+        for _ in 0..4 {
+            sources = sources.iter().flat_map(|source| alphabet.iter().map(move |character| format!("{}{}", source, character))).collect();
+            for source in sources.iter().map(|source| format!("{}\n", source)) {
+                full.load_str(&source);
+                for pairs in [&[][..], &[("openparen", "closeparen")][..]] {
+                    assert_eq!(feed_lines(&lexer, pairs, &source), full.tokenize().ok(), "{:?}", source);
+                }
+            }
+        }
+        return Ok(());
+    }
+}
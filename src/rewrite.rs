@@ -0,0 +1,128 @@
+use crate::{LosslessToken, TextEdit};
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Keep,
+    Replace(String),
+    Remove,
+}
+
+// collects edits to tokens by index and applies them to the source in one go, so earlier edits
+// never shift the offsets of later ones. the tokens must come from tokenize_lossless on source,
+// edits to a token index out of range panic like indexing does
+pub struct Rewriter<'a> {
+    source: &'a str,
+    spans: Vec<Range<usize>>,
+    changes: Vec<Change>,
+    inserts: Vec<String>,
+}
+
+impl<'a> Rewriter<'a> {
+    pub fn new(source: &'a str, tokens: &[LosslessToken]) -> Rewriter<'a> {
+        let mut cursor = 0;
+        let spans = tokens.iter().map(|token| {
+            let start = cursor + token.leading.iter().map(|trivia| trivia.text.len()).sum::<usize>();
+            cursor = start + token.text.len() + token.trailing.iter().map(|trivia| trivia.text.len()).sum::<usize>();
+            return start..start + token.text.len();
+        }).collect::<Vec<Range<usize>>>();
+
+        return Rewriter {
+            source,
+            changes: vec![Change::Keep; spans.len()],
+            inserts: vec![String::new(); spans.len()],
+            spans,
+        };
+    }
+
+    // a later replace or remove of the same token wins
+    pub fn replace(&mut self, index: usize, text: &str) {
+        self.changes[index] = Change::Replace(text.to_string());
+    }
+
+    // the trivia around the token is kept
+    pub fn remove(&mut self, index: usize) {
+        self.changes[index] = Change::Remove;
+    }
+
+    // text goes right before the token and after its leading trivia, inserts before the same
+    // token are kept in order
+    pub fn insert_before(&mut self, index: usize, text: &str) {
+        self.inserts[index].push_str(text);
+    }
+
+    // the edits in source order as offsets into the original source, for Lexer::relex
+    pub fn edits(&self) -> Vec<TextEdit> {
+        let mut edits = Vec::new();
+        for (index, span) in self.spans.iter().enumerate() {
+            let replacement = match &self.changes[index] {
+                Change::Keep => None,
+                Change::Replace(text) => Some(text.as_str()),
+                Change::Remove => Some(""),
+            };
+
+            match replacement {
+                Some(text) => edits.push(TextEdit::new(span.clone(), &format!("{}{}", self.inserts[index], text))),
+                None if !self.inserts[index].is_empty() => edits.push(TextEdit::new(span.start..span.start, &self.inserts[index])),
+                None => {},
+            }
+        }
+        return edits;
+    }
+
+    // the patched source with the span of every token in it, None for removed tokens
+    pub fn finish(&self) -> (String, Vec<Option<Range<usize>>>) {
+        let mut output = String::new();
+        let mut spans = Vec::new();
+        let mut cursor = 0;
+        for (index, span) in self.spans.iter().enumerate() {
+            output.push_str(&self.source[cursor..span.start]);
+            output.push_str(&self.inserts[index]);
+
+            let start = output.len();
+            match &self.changes[index] {
+                Change::Keep => output.push_str(&self.source[span.clone()]),
+                Change::Replace(text) => output.push_str(text),
+                Change::Remove => {},
+            }
+            spans.push((self.changes[index] != Change::Remove).then_some(start..output.len()));
+            cursor = span.end;
+        }
+        output.push_str(&self.source[cursor..]);
+        return (output, spans);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Rewriter, TextEdit, TokenCache};
+
+    #[test]
+    fn rewrite_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string()), (';', "semi".to_string())], false);
+        let source = "let a = 1;\nlet b = a;\n";
+        lexer.load_str(source);
+
+        let tokens = lexer.tokenize_lossless()?;
+        let mut rewriter = Rewriter::new(source, &tokens);
+        rewriter.replace(1, "count");
+        rewriter.replace(8, "count");
+        rewriter.insert_before(5, "mut ");
+        rewriter.remove(9);
+
+        let (output, spans) = rewriter.finish();
+        assert_eq!(output, "let count = 1;\nmut let b = count\n");
+        assert_eq!(spans[1], Some(4..9));
+        assert_eq!(&output[spans[5].clone().ok_or("removed")?], "let");
+        assert_eq!(spans[9], None);
+
+        assert_eq!(rewriter.edits()[0], TextEdit::new(4..5, "count"));
+        let mut cache = TokenCache::new(&lexer, source)?;
+        for edit in rewriter.edits().into_iter().rev() {
+            lexer.relex(&mut cache, edit)?;
+        }
+        assert_eq!(cache.source(), output);
+        return Ok(());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{FileId, Lexer, Loc, Token};
+
+// the tokens of one file
+type FileTokens = (FileId, Vec<Token>);
+
+// the sources of a project by name, every loc lexed from a file carries its FileId
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<(FileId, String)>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        return SourceMap::default();
+    }
+
+    // adding a name again replaces its source and keeps its id
+    pub fn add(&mut self, name: &str, source: &str) -> FileId {
+        let file = FileId::new(name);
+        match self.files.iter_mut().find(|(existing, _)| *existing == file) {
+            Some((_, existing)) => *existing = source.to_string(),
+            None => self.files.push((file, source.to_string())),
+        }
+        return file;
+    }
+
+    pub fn add_file(&mut self, path: &str) -> Result<FileId, Box<dyn std::error::Error>> {
+        let source = std::fs::read_to_string(path)?;
+        return Ok(self.add(path, &source));
+    }
+
+    pub fn source(&self, file: FileId) -> Option<&str> {
+        return self.files.iter().find(|(existing, _)| *existing == file).map(|(_, source)| source.as_str());
+    }
+
+    pub fn file(&self, name: &str) -> Option<FileId> {
+        return self.files.iter().map(|(file, _)| *file).find(|file| file.name() == Some(name));
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = FileId> + '_ {
+        return self.files.iter().map(|(file, _)| *file);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.files.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.files.is_empty();
+    }
+
+    // the source line a loc points into
+    pub fn line(&self, loc: Loc) -> Option<&str> {
+        return self.source(loc.file)?.lines().nth((loc.line as usize).checked_sub(1)?);
+    }
+
+    // lexes every file in the order they were added, the loaded input of the lexer is ignored
+    pub fn tokenize_all(&self, lexer: &Lexer) -> Result<Vec<FileTokens>, Box<dyn std::error::Error>> {
+        let mut files = Vec::with_capacity(self.files.len());
+        for (file, source) in &self.files {
+            let (scanned, _) = lexer.scan_until(source, source.len(), &mut lexer.new_run(), &mut Loc::new(*file), true)?;
+            files.push((*file, scanned.tokens.into_iter().filter_map(|token| lexer.finish_token(token)).collect()));
+        }
+        return Ok(files);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, SourceMap};
+
+    #[test]
+    fn source_map_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(&["include".to_string()], &[], &[(';', "semicolon".to_string())], false);
+
+        let mut sources = SourceMap::new();
+        let main = sources.add("main.src", "include util;\nrun;");
+        let util = sources.add("util.src", "helper;");
+        assert_ne!(main, util);
+        assert_eq!(sources.add("util.src", "helper;\nother;"), util);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources.file("util.src"), Some(util));
+
+        let files = sources.tokenize_all(&lexer)?;
+        assert_eq!(files.iter().map(|(file, _)| *file).collect::<Vec<_>>(), [main, util]);
+
+        let (_, tokens) = &files[0];
+        tokens[0].is_keyword("include")?;
+        assert!(tokens.iter().all(|token| token.loc().file == main));
+        assert_eq!(sources.line(tokens[3].loc()), Some("run;"));
+
+        let (_, tokens) = &files[1];
+        assert_eq!(tokens.len(), 4);
+        assert!(tokens[3].loc().to_string().starts_with("util.src:2:"));
+        return Ok(());
+    }
+}
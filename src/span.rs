@@ -0,0 +1,241 @@
+use crate::{Lexer, Loc, Run, Token};
+
+use std::ops::Range;
+
+// the full extent of a token in the source, delimiters included for sections and comments. end is
+// the position just past the last character, so an empty span starts and ends at the same place
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: Loc,
+    pub end: Loc,
+    pub range: Range<usize>,
+}
+
+impl Span {
+    pub fn len(&self) -> usize {
+        return self.range.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.range.is_empty();
+    }
+
+    // the token exactly as written, with its delimiters and the number formatting as_string loses.
+    // source is the input the span was lexed from
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        return &source[self.range.clone()];
+    }
+}
+
+impl Lexer {
+    // like tokenize, with the span of each token. the token keeps its usual loc, which for a section
+    // is its end delimiter. spans count the input as it is, line directives don't move them
+    pub fn tokenize_spanned(&self) -> Result<Vec<(Token, Span)>, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let (scanned, _) = self.scan_until(source, source.len(), &mut self.new_run(), &mut Loc::new(self.file), true)?;
+
+        let mut offsets = scanned.spans.iter().flat_map(|span| [span.start, span.end]).collect::<Vec<usize>>();
+        offsets.sort_unstable();
+        offsets.dedup();
+
+        let mut loc = Loc::new(self.file);
+        let locs = offsets.iter().map(|offset| {
+            let text = &source[loc.offset..*offset];
+            self.advance(&mut loc, text);
+            return loc;
+        }).collect::<Vec<Loc>>();
+        let at = |offset: usize| locs[offsets.binary_search(&offset).unwrap_or_default()];
+
+        return Ok(scanned.tokens.into_iter().zip(scanned.spans).filter_map(|(token, range)| {
+            let span = Span {
+                start: at(range.start),
+                end: at(range.end),
+                range,
+            };
+            return self.finish_token(token).map(|token| (token, span));
+        }).collect());
+    }
+
+    // whether a line can be lexed differently depending on the lines before it, because a section,
+    // mode or custom rule may still be open at its start or a line directive may have moved it
+    fn carries_lines(&self) -> bool {
+        let mut sections = self.sections.iter().chain(self.modes.iter().flat_map(|mode| &mode.sections));
+        return self.line_directive.is_some() || !self.transitions.is_empty() || !self.rules.is_empty() || sections.any(|section| section.heredoc.is_some() || (section.allow_newlines && !section.line));
+    }
+
+    // the token covering offset, with its byte range in source. when no line depends on the ones
+    // before it only the lines from the one offset is on to where the token ends are lexed. else
+    // the lines before it are lexed as well to find where it starts, which a TokenCache already
+    // knows, see Lexer::cached_token_at
+    pub fn token_at(&self, source: &str, offset: usize) -> Option<(Token, Range<usize>)> {
+        if offset > source.len() || !source.is_char_boundary(offset) {
+            return None;
+        }
+
+        if self.carries_lines() {
+            let (start, run, origin) = self.line_state(source, offset).ok()?;
+            return self.token_from(source, start, run, origin, offset);
+        }
+
+        let start = source[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+        let origin = Loc {
+            file: self.file,
+            line: source[..start].bytes().filter(|byte| *byte == b'\n').count() as u32 + 1,
+            col: 1,
+            offset: start,
+        };
+        return self.token_from(source, start, self.new_run(), origin, offset);
+    }
+
+    // lexes from a line start at or before offset, with run and origin as they are at that line
+    pub(crate) fn token_from(&self, source: &str, start: usize, run: Run, origin: Loc, offset: usize) -> Option<(Token, Range<usize>)> {
+        if offset > source.len() || !source.is_char_boundary(offset) {
+            return None;
+        }
+        let rest = &source[start..];
+
+        // a section still open at the end of the line is lexed on to where it ends, see next_batch
+        let last = rest.char_indices().next_back().map_or(0, |(last, _)| last);
+        let mut search = offset - start;
+        let scanned = loop {
+            let limit = rest.get(search..last).and_then(|line| line.find('\n')).map(|newline| search + newline + 1);
+            let mut run = Run {
+                extend: true,
+                ..run.clone()
+            };
+            let (scanned, open) = self.scan_until(rest, limit.unwrap_or(rest.len()), &mut run, &mut origin.clone(), limit.is_none()).ok()?;
+            match limit {
+                Some(limit) if open => search = scanned.end.max(limit),
+                _ => break scanned,
+            }
+        };
+
+        // layout tokens like Indent take up no input, so they cover no offset
+        let relative = offset - start;
+        return scanned.tokens.into_iter()
+            .zip(scanned.spans)
+            .find(|(_, span)| span.contains(&relative))
+            .and_then(|(token, span)| Some((self.finish_token(token)?, start + span.start..start + span.end)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Section, TokenCache};
+
+    #[test]
+    fn tokenize_spanned_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a =\n  \"multi\nline\" a");
+        let tokens = lexer.tokenize_spanned()?;
+
+        let (token, span) = &tokens[3];
+        assert_eq!(token.is_section("string")?, "multi\nline");
+        assert_eq!(span.range, 10..22);
+        assert_eq!((span.start.line, span.start.col), (2, 3));
+        assert_eq!((span.end.line, span.end.col), (3, 6));
+        assert_eq!(span.start.offset, 10);
+
+        let (_, span) = &tokens[4];
+        assert_eq!((span.start.line, span.start.col, span.len()), (3, 7, 1));
+        assert_eq!(tokens.iter().map(|(token, _)| token.clone()).collect::<Vec<_>>(), lexer.tokenize()?);
+
+        let source = "a = 1.50 \"x\\\"\"";
+        lexer.load_str(source);
+        let tokens = lexer.tokenize_spanned()?;
+        assert_eq!(tokens.iter().map(|(_, span)| span.text(source)).collect::<Vec<&str>>(), ["a", "=", "1.50", "\"x\\\"\""]);
+        assert_eq!(tokens[2].0.as_string(), "1.5");
+        return Ok(());
+    }
+
+    #[test]
+    fn span_text_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\""), Section::new("comment", "/*", "*/")], &[('=', "equal".to_string())], false);
+        lexer.comment_sections.push("comment".to_string());
+        lexer.char_quote = Some('\'');
+
+        let source = "let é = 0x1_0 /* a\r\nb */ 1e3\r\n\"ü\\n\" '\\''";
+        lexer.load_str(source);
+        let texts = lexer.tokenize_spanned()?.into_iter().map(|(token, span)| (token.as_string(), span.text(source))).collect::<Vec<(String, &str)>>();
+        assert_eq!(texts, [
+            ("let".to_string(), "let"),
+            ("é".to_string(), "é"),
+            ("=".to_string(), "="),
+            ("0x1_0".to_string(), "0x1_0"),
+            (" a\r\nb ".to_string(), "/* a\r\nb */"),
+            ("1000".to_string(), "1e3"),
+            ("ün".to_string(), "\"ü\\n\""),
+            ("'".to_string(), "'\\''"),
+        ]);
+        return Ok(());
+    }
+
+    #[test]
+    fn token_at_test() -> Result<(), Box<dyn std::error::Error>> {
+        let lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        let source = "let a = 1\nlet bc = \"two\nlines\" d\ne f";
+
+        let (token, range) = lexer.token_at(source, 15).ok_or("no token at 15")?;
+        assert_eq!(token.is_ident()?, "bc");
+        assert_eq!(range, 14..16);
+        assert_eq!((token.loc().line, token.loc().col), (2, 6));
+        assert_eq!(lexer.token_at(source, 14).map(|(_, range)| range), Some(14..16));
+
+        let (token, range) = lexer.token_at(source, 20).ok_or("no token at 20")?;
+        assert_eq!(token.is_section("string")?, "two\nlines");
+        assert_eq!(&source[range], "\"two\nlines\"");
+
+        // the same token as lexing everything would give
+        let mut full = lexer.clone();
+        full.load_str(source);
+        let (token, _) = lexer.token_at(source, 35).ok_or("no token at 35")?;
+        assert_eq!(Some(&token), full.tokenize()?.last());
+
+        assert!(lexer.token_at(source, 3).is_none());
+        assert!(lexer.token_at(source, source.len() + 1).is_none());
+
+        // the line of the offset starts inside the string, and layout tokens cover nothing
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[], false);
+        for offside in [false, true] {
+            lexer.offside = offside;
+            let (token, range) = lexer.token_at("\"\r\n\"\t", 4).ok_or("no token at 4")?;
+            assert_eq!(token.is_ident()?, "\t");
+            assert_eq!(range, 4..5);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn token_at_spanned_test() -> Result<(), Box<dyn std::error::Error>> {
+        let symbols = [('=', "equal".to_string())];
+        // only the second has sections that can be open at the start of a line
+        let lexers = [
+            Lexer::new(&[], &[Section::line("line", "#"), Section::new("string", "\"", "\"").single_line()], &symbols, false),
+            Lexer::new(&[], &[Section::line("line", "#"), Section::new("string", "\"", "\""), Section::new("comment", "/*", "*/")], &symbols, false),
+        ];
+
+        // every source of up to four of these pieces, against the tokens of the whole source
+        let pieces = ["\"", "/*", "*/", "#", "\n", "\r\n", " ", "\t", "a", "="];
+        let mut sources = vec![String::new()];
+        for _ in 0..4 {
+            sources = sources.iter().flat_map(|source| pieces.iter().map(move |piece| format!("{}{}", source, piece))).collect();
+            for (lexer, offside) in lexers.iter().flat_map(|lexer| [(lexer, false), (lexer, true)]) {
+                let mut lexer = lexer.clone();
+                lexer.offside = offside;
+                for source in &sources {
+                    lexer.load_str(source);
+                    let Ok(spanned) = lexer.tokenize_spanned() else {
+                        continue;
+                    };
+                    let cache = TokenCache::new(&lexer, source)?;
+                    for offset in (0..=source.len()).filter(|offset| source.is_char_boundary(*offset)) {
+                        let expected = spanned.iter().find(|(_, span)| span.range.contains(&offset)).map(|(token, span)| (token.clone(), span.range.clone()));
+                        assert_eq!(lexer.token_at(source, offset), expected, "{:?} at {}", source, offset);
+                        assert_eq!(lexer.cached_token_at(&cache, offset), expected, "{:?} at {}", source, offset);
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{Escapes, Lexer, Section, SymbolInfo};
+
+// a read only description of the rules of a lexer for tools that generate documentation or editor
+// grammars, see Lexer::describe. unlike LexerConfig it resolves tags, aliases and comments per rule
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LexerSpec {
+    pub keywords: Vec<KeywordSpec>,
+    pub symbols: Vec<SymbolSpec>,
+    pub sections: Vec<SectionSpec>,
+    pub modes: Vec<ModeSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeywordSpec {
+    pub keyword: String,
+    pub aliases: Vec<String>,
+    pub tags: Vec<String>,
+    // lexed as an identifier, see Lexer::add_contextual_keyword
+    pub contextual: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolSpec {
+    pub symbol: char,
+    pub name: String,
+    pub info: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionSpec {
+    pub name: String,
+    pub start: String,
+    pub end: String,
+    pub comment: bool,
+    pub escapes: Escapes,
+    pub allow_newlines: bool,
+    pub line: bool,
+    pub heredoc: bool,
+    pub interpolation: Option<(String, String)>,
+    pub language: Option<String>,
+}
+
+// the rules that replace the base rules while the mode is active
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModeSpec {
+    pub name: String,
+    pub keywords: Vec<KeywordSpec>,
+    pub symbols: Vec<SymbolSpec>,
+    pub sections: Vec<SectionSpec>,
+}
+
+impl Lexer {
+    fn describe_keywords(&self, keywords: &[String]) -> Vec<KeywordSpec> {
+        return keywords.iter().map(|keyword| KeywordSpec {
+            keyword: keyword.clone(),
+            aliases: self.keyword_aliases.iter().filter(|(_, canonical)| canonical == keyword).map(|(alias, _)| alias.clone()).collect(),
+            tags: self.keyword_tags.iter().filter(|(tagged, _)| tagged == keyword).filter_map(|(_, tag)| tag.as_str()).map(str::to_string).collect(),
+            contextual: !self.keywords.contains(keyword) && self.contextual_keywords.contains(keyword),
+        }).collect();
+    }
+
+    fn describe_symbols(&self, symbols: &[(char, String)]) -> Vec<SymbolSpec> {
+        return symbols.iter().map(|(symbol, name)| SymbolSpec {
+            symbol: *symbol,
+            name: name.clone(),
+            info: self.symbol_info.iter().filter(|(described, _)| described == name).map(|(_, info)| *info).collect(),
+        }).collect();
+    }
+
+    fn describe_sections(&self, sections: &[Section]) -> Vec<SectionSpec> {
+        return sections.iter().map(|section| SectionSpec {
+            name: section.name.clone(),
+            start: section.start.clone(),
+            end: section.end.clone(),
+            comment: self.comment_sections.contains(&section.name),
+            escapes: section.escapes,
+            allow_newlines: section.allow_newlines,
+            line: section.line,
+            heredoc: section.heredoc.is_some(),
+            interpolation: section.interpolation.clone(),
+            language: section.language.clone(),
+        }).collect();
+    }
+
+    pub fn describe(&self) -> LexerSpec {
+        return LexerSpec {
+            keywords: self.describe_keywords(&[self.keywords.as_slice(), self.contextual_keywords.as_slice()].concat()),
+            symbols: self.describe_symbols(&self.symbols),
+            sections: self.describe_sections(&self.sections),
+            modes: self.modes.iter().map(|mode| ModeSpec {
+                name: mode.name.clone(),
+                keywords: self.describe_keywords(&mode.keywords),
+                symbols: self.describe_symbols(&mode.symbols),
+                sections: self.describe_sections(&mode.sections),
+            }).collect(),
+        };
+    }
+}
+
+#[cfg(feature = "json")]
+impl LexerSpec {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        return serde_json::to_string_pretty(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Associativity, Lexer, Section, SymbolInfo};
+
+    #[test]
+    fn describe_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\""), Section::line("comment", "//")], &[('+', "plus".to_string())], false);
+        lexer.add_keyword_tagged("fn", &["item"]);
+        lexer.add_keyword_alias("func", "fn");
+        lexer.add_symbol_info("plus", SymbolInfo::binary(10, Associativity::Left));
+        lexer.comment_sections.push("comment".to_string());
+        lexer.add_contextual_keyword("get");
+
+        let spec = lexer.describe();
+        assert_eq!(spec.keywords[0].aliases, ["func"]);
+        assert_eq!(spec.keywords[0].tags, ["item"]);
+        assert!(!spec.keywords[0].contextual && spec.keywords[1].contextual);
+        assert_eq!(spec.symbols[0].info, [SymbolInfo::binary(10, Associativity::Left)]);
+        assert!(!spec.sections[0].comment);
+        assert!(spec.sections[1].comment && spec.sections[1].line);
+        assert!(spec.modes.is_empty());
+
+        #[cfg(feature = "json")]
+        assert_eq!(serde_json::from_str::<crate::LexerSpec>(&spec.to_json()?)?, spec);
+        return Ok(());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{Lexer, Token, TokenKind};
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// aggregate counts over a token stream, bytes and lengths count the text given by as_string
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TokenStats {
+    pub tokens: usize,
+    pub bytes: usize,
+    pub kinds: HashMap<TokenKind, usize>,
+    pub keywords: HashMap<String, usize>,
+    pub idents: HashMap<String, usize>,
+    // the first of the tokens with the longest text
+    pub longest: Option<Token>,
+}
+
+impl TokenStats {
+    pub fn count(&self, kind: TokenKind) -> usize {
+        return self.kinds.get(&kind).copied().unwrap_or_default();
+    }
+
+    // the n most frequent identifiers, ties in alphabetical order
+    pub fn top_idents(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut idents = self.idents.iter().map(|(ident, count)| (ident.as_str(), *count)).collect::<Vec<(&str, usize)>>();
+        idents.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        idents.truncate(n);
+        return idents;
+    }
+}
+
+impl From<&[Token]> for TokenStats {
+    fn from(tokens: &[Token]) -> TokenStats {
+        let mut stats = TokenStats::default();
+        let mut longest = 0;
+        for token in tokens {
+            let length = token.as_string().len();
+            stats.tokens += 1;
+            stats.bytes += length;
+            *stats.kinds.entry(token.kind()).or_default() += 1;
+
+            match token {
+                Token::Keyword(keyword, _) => *stats.keywords.entry(keyword.clone()).or_default() += 1,
+                Token::Ident(ident, _) => *stats.idents.entry(ident.clone()).or_default() += 1,
+                _ => {},
+            }
+
+            if stats.longest.is_none() || length > longest {
+                longest = length;
+                stats.longest = Some(token.clone());
+            }
+        }
+        return stats;
+    }
+}
+
+impl Lexer {
+    // tokenize along with the time it took
+    pub fn tokenize_timed(&self) -> Result<(Vec<Token>, Duration), Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        let tokens = self.tokenize()?;
+        return Ok((tokens, start.elapsed()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, TokenKind, TokenStats};
+
+    #[test]
+    fn stats_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = b\nlet b = long_name\nlet c = b");
+
+        let (tokens, _) = lexer.tokenize_timed()?;
+        let stats = TokenStats::from(tokens.as_slice());
+        assert_eq!(stats.tokens, 12);
+        assert_eq!(stats.bytes, 6 + 14 + 6);
+        assert_eq!(stats.count(TokenKind::Keyword), 3);
+        assert_eq!(stats.count(TokenKind::Integer), 0);
+        assert_eq!(stats.keywords["let"], 3);
+        assert_eq!(stats.top_idents(2), vec![("b", 3), ("a", 1)]);
+        assert_eq!(stats.longest.map(|token| token.as_string()).as_deref(), Some("long_name"));
+        return Ok(());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{Token, TokenFile};
+
+// a cursor over lexed tokens, the expect_* methods only advance when the token matches
+#[derive(Debug, Clone)]
+pub struct TokenStream<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+impl<'a> TokenStream<'a> {
+    pub fn new(tokens: &'a [Token]) -> TokenStream<'a> {
+        return TokenStream {
+            tokens,
+            position: 0,
+        };
+    }
+
+    pub fn peek(&self) -> Option<&'a Token> {
+        return self.tokens.get(self.position);
+    }
+
+    pub fn peek_nth(&self, n: usize) -> Option<&'a Token> {
+        return self.tokens.get(self.position + n);
+    }
+
+    pub fn position(&self) -> usize {
+        return self.position;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.position >= self.tokens.len();
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        return Checkpoint(self.position);
+    }
+
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.position = checkpoint.0;
+    }
+
+    // runs one alternative of a speculative parse, rewinding to where it started when it fails
+    pub fn attempt<T, E>(&mut self, f: impl FnOnce(&mut TokenStream<'a>) -> Result<T, E>) -> Result<T, E> {
+        let checkpoint = self.checkpoint();
+        let result = f(self);
+        if result.is_err() {
+            self.rewind(checkpoint);
+        }
+        return result;
+    }
+
+    // advances past the next token when f accepts it
+    fn expect<T>(&mut self, f: impl FnOnce(&'a Token) -> Result<T, Box<dyn std::error::Error>>) -> Result<T, Box<dyn std::error::Error>> {
+        let token = self.peek().ok_or("unexpected end of input")?;
+        let value = f(token)?;
+        self.position += 1;
+        return Ok(value);
+    }
+
+    pub fn expect_keyword(&mut self, keyword: &str) -> Result<&'a Token, Box<dyn std::error::Error>> {
+        return self.expect(|token| token.is_keyword(keyword).map(|_| token));
+    }
+
+    pub fn expect_symbol(&mut self, name: &str) -> Result<&'a Token, Box<dyn std::error::Error>> {
+        return self.expect(|token| token.is_symbol(name).map(|_| token));
+    }
+
+    pub fn expect_section(&mut self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        return self.expect(|token| token.is_section(name));
+    }
+
+    pub fn expect_ident(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        return self.expect(Token::is_ident);
+    }
+
+    pub fn expect_integer(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
+        return self.expect(Token::is_integer);
+    }
+
+    pub fn expect_float(&mut self) -> Result<f64, Box<dyn std::error::Error>> {
+        return self.expect(Token::is_float);
+    }
+
+    pub fn expect_char(&mut self) -> Result<char, Box<dyn std::error::Error>> {
+        return self.expect(Token::is_char);
+    }
+
+    pub fn expect_custom(&mut self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        return self.expect(|token| token.is_custom(name));
+    }
+
+    // without emit_eof the end of the tokens counts as the end of input
+    pub fn expect_eof(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        return self.expect(Token::is_eof);
+    }
+
+    // consumes the keyword if it is next, for optional syntax
+    pub fn eat_keyword(&mut self, keyword: &str) -> bool {
+        return self.expect_keyword(keyword).is_ok();
+    }
+
+    pub fn eat_symbol(&mut self, name: &str) -> bool {
+        return self.expect_symbol(name).is_ok();
+    }
+}
+
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = &'a Token;
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.peek()?;
+        self.position += 1;
+        return Some(token);
+    }
+}
+
+impl TokenFile {
+    pub fn stream(&self) -> TokenStream<'_> {
+        return TokenStream::new(self.tokens());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Lexer, Section};
+
+    #[test]
+    fn stream_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &["if".to_string()],
+            &[Section::new("string", "\"", "\"")],
+            &[('(', "openbrace".to_string()), (')', "closebrace".to_string())],
+            false,
+        );
+        lexer.load_str("if (x) \"y\" 10");
+
+        let file = lexer.tokenize_file()?;
+        let mut stream = file.stream();
+        stream.expect_keyword("if")?;
+
+        let checkpoint = stream.checkpoint();
+        assert!(stream.expect_keyword("if").is_err());
+        assert_eq!(stream.position(), 1);
+        stream.expect_symbol("openbrace")?;
+        assert_eq!(stream.expect_ident()?, "x");
+        stream.rewind(checkpoint);
+        assert!(stream.eat_symbol("openbrace"));
+        assert!(!stream.eat_symbol("openbrace"));
+
+        let call = stream.attempt(|stream| {
+            stream.expect_ident()?;
+            return stream.expect_symbol("openbrace");
+        });
+        assert!(call.is_err());
+        assert_eq!(stream.position(), 2);
+        assert!(stream.attempt(|stream| stream.expect_integer()).is_err());
+        assert_eq!(stream.position(), 2);
+
+        assert!(stream.peek().is_some());
+        stream.next();
+        stream.expect_symbol("closebrace")?;
+        assert_eq!(stream.expect_section("string")?, "y");
+        assert_eq!(stream.expect_integer()?, 10);
+        assert!(stream.is_empty());
+        assert!(stream.expect_ident().is_err());
+        stream.expect_eof()?;
+
+        lexer.emit_eof = true;
+        let file = lexer.tokenize_file()?;
+        let mut stream = file.stream();
+        assert!(stream.expect_eof().is_err());
+        assert_eq!(stream.by_ref().take(file.len() - 1).count(), 6);
+        stream.expect_eof()?;
+        assert!(stream.is_empty());
+        return Ok(());
+    }
+
+    // a call `f(a, g(b))` or a plain ident, tried in that order
+    fn expr(stream: &mut TokenStream) -> Result<String, Box<dyn std::error::Error>> {
+        let call = stream.attempt(|stream| {
+            let name = stream.expect_ident()?;
+            stream.expect_symbol("openbrace")?;
+            let mut args = Vec::new();
+            while !stream.eat_symbol("closebrace") {
+                if !args.is_empty() {
+                    stream.expect_symbol("comma")?;
+                }
+                args.push(expr(stream)?);
+            }
+            return Ok::<String, Box<dyn std::error::Error>>(format!("{}[{}]", name, args.join(" ")));
+        });
+        return call.or_else(|_| stream.expect_ident());
+    }
+
+    #[test]
+    fn attempt_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[('(', "openbrace".to_string()), (')', "closebrace".to_string()), (',', "comma".to_string())], false);
+        lexer.load_str("f(a, g(b)) x h(c d");
+        let tokens = lexer.tokenize()?;
+        let mut stream = TokenStream::new(&tokens);
+
+        // a successful attempt keeps what it consumed
+        assert_eq!(expr(&mut stream)?, "f[a g[b]]");
+        assert_eq!(stream.position(), 9);
+        assert_eq!(expr(&mut stream)?, "x");
+        assert_eq!(stream.position(), 10);
+
+        // the call fails after consuming `h(c`, so only the ident is taken
+        assert_eq!(expr(&mut stream)?, "h");
+        assert_eq!(stream.position(), 11);
+        assert_eq!(stream.attempt(|stream| stream.expect_ident()).ok(), None);
+        assert_eq!(stream.position(), 11);
+        return Ok(());
+    }
+}
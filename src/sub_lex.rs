@@ -0,0 +1,63 @@
+use crate::{FragmentRef, LexError, Lexer, Loc, Section, SubLexer, TokenRef};
+
+impl Lexer {
+    // the section token for body lexed by sub_lexer. start is the loc of the start delimiter and
+    // loc the one of the section token
+    pub(crate) fn sub_lexed<'a>(&self, section: &'a Section, sub_lexer: SubLexer, body: &str, start: Loc, loc: Loc) -> Result<TokenRef<'a>, LexError> {
+        let mut at = start;
+        self.advance(&mut at, &section.start);
+        let tokens = sub_lexer(body).map_err(|err| LexError::SubLexer(section.name.clone(), err.to_string(), at))?;
+
+        // the offsets of the tokens are into body, which starts after the start delimiter
+        let tokens = tokens.into_iter().map(|token| token.map_loc(&|inner: Loc| {
+            let mut moved = at;
+            self.advance(&mut moved, body.get(..inner.offset).unwrap_or(body));
+            return moved;
+        })).collect();
+        return Ok(TokenRef::Interpolated(&section.name, vec![FragmentRef::Owned(tokens)], loc));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Fragment, LexError, Lexer, Section, Token};
+
+    fn sql(body: &str) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["select".to_string(), "from".to_string()], &[], &[('*', "star".to_string())], false);
+        lexer.case_insensitive_keywords = true;
+        lexer.load_str(body);
+        return lexer.tokenize();
+    }
+
+    fn failing(_: &str) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        return Err("no".into());
+    }
+
+    #[test]
+    fn sub_lex_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("sql", "sql\"", "\"").sub_lex(sql)], &[('=', "equal".to_string())], false);
+        lexer.load_str("let q =\n  sql\"SELECT *\n  FROM t\" q");
+        let tokens = lexer.tokenize()?;
+
+        let Token::Interpolated(name, fragments, loc) = &tokens[3] else {
+            return Err(format!("expected a sub lexed section: {:?}", tokens[3]).into());
+        };
+        assert_eq!(name, "sql");
+        assert_eq!((loc.line, loc.col), (3, 9));
+        let [Fragment::Tokens(inner)] = fragments.as_slice() else {
+            return Err("expected one tokens fragment".into());
+        };
+        inner[0].is_keyword("select")?;
+        assert_eq!((inner[0].loc().line, inner[0].loc().col, inner[0].loc().offset), (2, 12, 19));
+        assert_eq!(inner[2].loc().line, 3);
+        assert_eq!(inner[3].is_ident()?, "t");
+        assert_eq!((inner[3].loc().col, inner[3].loc().offset), (8, 30));
+        assert_eq!(tokens[4].is_ident()?, "q");
+
+        lexer.sections[0] = Section::new("sql", "sql\"", "\"").sub_lex(failing);
+        lexer.load_str("sql\"x\"");
+        let err = lexer.tokenize().err().and_then(|err| err.downcast::<LexError>().ok());
+        assert!(matches!(err.as_deref(), Some(LexError::SubLexer(section, message, loc)) if section == "sql" && message == "no" && loc.col == 5));
+        return Ok(());
+    }
+}
@@ -0,0 +1,164 @@
+// support for tests of lexers built on this crate, see assert_tokens! and roundtrip_property
+use crate::{Lexer, LexerConfig, Loc, RoundtripDiff, Token, TokenKind};
+
+use std::fmt;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+
+// an invariant roundtrip_property found broken, token is the index of the offending token
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyViolation {
+    Panicked(String),
+    SpanOutOfBounds(usize, Range<usize>),
+    SpansOverlap(usize, Range<usize>),
+    Roundtrip(RoundtripDiff),
+}
+
+impl fmt::Display for PropertyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            PropertyViolation::Panicked(message) => write!(f, "lexing panicked: {}", message),
+            PropertyViolation::SpanOutOfBounds(token, span) => write!(f, "span {:?} of token {} is outside of the input", span, token),
+            PropertyViolation::SpansOverlap(token, span) => write!(f, "span {:?} of token {} overlaps the token before it", span, token),
+            PropertyViolation::Roundtrip(diff) => write!(f, "lossless tokens differ from the input, {}", diff),
+        };
+    }
+}
+
+impl std::error::Error for PropertyViolation {}
+
+// what an expected token is compared on, the name followed by the text. symbols and interpolated
+// sections are compared on their name alone and tokens without text on their kind alone
+pub fn token_fields(token: &Token) -> Vec<String> {
+    let mut fields = token.name().map(str::to_string).into_iter().collect::<Vec<String>>();
+    match token {
+        Token::Symbol(..) | Token::Interpolated(..) | Token::Indent(_) | Token::Dedent(_) | Token::Newline(_) | Token::Eof(_) => {},
+        _ => fields.push(token.as_string()),
+    }
+    return fields;
+}
+
+fn expected_string(kind: TokenKind, fields: &[String]) -> String {
+    return format!("{}({})", kind, fields.iter().map(|field| format!("{:?}", field)).collect::<Vec<String>>().join(", "));
+}
+
+// one line per token with the differing ones marked, None when the tokens match
+pub fn token_diff(expected: &[(TokenKind, Vec<String>)], actual: &[Token]) -> Option<String> {
+    let mut diff = String::new();
+    let mut differs = false;
+    for index in 0..expected.len().max(actual.len()) {
+        let want = expected.get(index);
+        let got = actual.get(index);
+        let matches = match (want, got) {
+            (Some((kind, fields)), Some(token)) => token.kind() == *kind && token_fields(token) == *fields,
+            _ => false,
+        };
+        differs |= !matches;
+
+        let want = want.map_or("<none>".to_string(), |(kind, fields)| expected_string(*kind, fields));
+        let got = got.map_or("<none>".to_string(), |token| format!("{} at {}", expected_string(token.kind(), &token_fields(token)), token.loc()));
+        diff.push_str(&format!("{} {:>3}  {:<32} {}\n", if matches { ' ' } else { '!' }, index, want, got));
+    }
+    return differs.then_some(diff);
+}
+
+// lexes the input and panics with a diff of the tokens unless they match the expected ones, each
+// written as a TokenKind variant with the fields token_fields compares it on
+//
+// assert_tokens!(lexer, "def x = \"y\"", [Keyword("def"), Ident("x"), Symbol("Equal"), Section("string", "y")]);
+#[macro_export]
+macro_rules! assert_tokens {
+    ($lexer:expr, $input:expr, [$($kind:ident $(($($field:expr),* $(,)?))?),* $(,)?] $(,)?) => {{
+        let lexer: &mut $crate::Lexer = &mut $lexer;
+        let input: &str = $input;
+        lexer.load_str(input);
+        let tokens = match lexer.tokenize() {
+            Ok(tokens) => tokens,
+            Err(err) => panic!("lexing {:?} failed: {}", input, err),
+        };
+
+        let expected: Vec<($crate::TokenKind, Vec<String>)> = vec![$(($crate::TokenKind::$kind, vec![$($($field.to_string()),*)?])),*];
+        if let Some(diff) = $crate::testing::token_diff(&expected, &tokens) {
+            panic!("tokens of {:?} differ, expected on the left\n{}", input, diff);
+        }
+    }};
+}
+
+fn check_spans(lexer: &Lexer, input: &str) -> Result<(), PropertyViolation> {
+    let mut run = lexer.new_run();
+    run.recover = true;
+    // failing outright, like on a limit, breaks none of the invariants
+    let Ok((scanned, _)) = lexer.scan_until(input, input.len(), &mut run, &mut Loc::new(lexer.file), true) else {
+        return Ok(());
+    };
+
+    let mut end = 0;
+    for (index, span) in scanned.spans.into_iter().enumerate() {
+        if span.start > span.end || input.get(span.clone()).is_none() {
+            return Err(PropertyViolation::SpanOutOfBounds(index, span));
+        } else if !span.is_empty() && span.start < end {
+            return Err(PropertyViolation::SpansOverlap(index, span));
+        }
+        end = end.max(span.end);
+    }
+    return Ok(());
+}
+
+// checks the invariants every lexer should keep on any input: lexing doesn't panic, the spans of
+// the tokens lie in the input without overlapping, and the lossless tokens give the input back
+// whenever it lexes without errors. meant to be driven by a fuzzer or a property testing crate
+pub fn roundtrip_property(config: &LexerConfig, input: &str) -> Result<(), PropertyViolation> {
+    let mut lexer = Lexer::from(config.clone());
+    lexer.load_str(input);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        check_spans(&lexer, input)?;
+        if let Ok(tokens) = lexer.tokenize_lossless() {
+            lexer.verify_roundtrip(&tokens).map_err(PropertyViolation::Roundtrip)?;
+        }
+        return Ok(());
+    }));
+    return result.unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|message| message.to_string()).or_else(|| payload.downcast_ref::<String>().cloned());
+        return Err(PropertyViolation::Panicked(message.unwrap_or_default()));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Lexer, Section};
+
+    #[test]
+    fn assert_tokens_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["def".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "Equal".to_string())], false);
+        assert_tokens!(lexer, "def x = \"y\" 2", [Keyword("def"), Ident("x"), Symbol("Equal"), Section("string", "y"), Integer(2)]);
+
+        lexer.load_str("def y");
+        let tokens = lexer.tokenize()?;
+        let diff = token_diff(&[(TokenKind::Keyword, vec!["def".to_string()]), (TokenKind::Ident, vec!["x".to_string()])], &tokens);
+        assert_eq!(diff.as_deref().and_then(|diff| diff.lines().nth(1)).map(str::trim_end), Some(format!("! {:>3}  {:<32} Ident(\"y\") at 1:5", 1, "Ident(\"x\")")).as_deref());
+        assert_eq!(token_diff(&[], &tokens).map(|diff| diff.lines().count()), Some(2));
+
+        let result = std::panic::catch_unwind(|| {
+            let mut lexer = Lexer::new(&[], &[], &[], false);
+            assert_tokens!(lexer, "a", [Ident("b")]);
+        });
+        assert!(result.is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn roundtrip_property_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\""), Section::line("comment", "//")], &[('=', "equal".to_string())], false);
+        for input in ["let a = \"b\" // c\n", "\"open", "", "\u{feff}a\r\n"] {
+            roundtrip_property(&lexer.config(), input)?;
+        }
+
+        // the line break ending a line comment is the Newline token's alone
+        lexer.emit_newlines = true;
+        roundtrip_property(&lexer.config(), "a // b\nc\n")?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{Embedded, Token};
+
+use std::ops::Index;
+use std::slice;
+
+// the leading whitespace of a line, width expands tabs to the next tab stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Indent {
+    pub width: usize,
+    pub tabs: usize,
+    pub spaces: usize,
+}
+
+impl Indent {
+    pub fn is_mixed(&self) -> bool {
+        return self.tabs > 0 && self.spaces > 0;
+    }
+}
+
+// material around the tokens that formatters and generators should carry over unchanged
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FileMetadata {
+    pub bom: bool,
+    pub shebang: Option<String>,
+    pub banner: Vec<String>,
+    pub trailing_newline: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenFile {
+    tokens: Vec<Token>,
+    source: String,
+    line_starts: Vec<usize>,
+    first_line: usize,
+    indents: Vec<Indent>,
+    embedded: Vec<Embedded>,
+    metadata: FileMetadata,
+}
+
+impl TokenFile {
+    pub fn new(tokens: Vec<Token>, source: String) -> TokenFile {
+        return TokenFile::nested(tokens, source, 1, 4, Vec::new());
+    }
+
+    // first_line is the line the source starts on in the enclosing file
+    pub(crate) fn nested(tokens: Vec<Token>, source: String, first_line: usize, tab_width: usize, embedded: Vec<Embedded>) -> TokenFile {
+        let mut line_starts = vec![0];
+        for (index, byte) in source.bytes().enumerate() {
+            if byte == b'\n' {
+                line_starts.push(index + 1);
+            }
+        }
+
+        let indents = line_starts.iter().map(|start| {
+            let mut indent = Indent::default();
+            for byte in source[*start..].bytes() {
+                match byte {
+                    b' ' => {
+                        indent.spaces += 1;
+                        indent.width += 1;
+                    },
+                    b'\t' => {
+                        indent.tabs += 1;
+                        indent.width += tab_width.max(1) - indent.width % tab_width.max(1);
+                    },
+                    _ => break,
+                }
+            }
+            return indent;
+        }).collect();
+
+        return TokenFile {
+            tokens,
+            source,
+            line_starts,
+            first_line,
+            indents,
+            embedded,
+            metadata: FileMetadata::default(),
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.tokens.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.tokens.is_empty();
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Token> {
+        return self.tokens.get(index);
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Token> {
+        return self.tokens.iter();
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        return &self.tokens;
+    }
+
+    pub fn source(&self) -> &str {
+        return &self.source;
+    }
+
+    pub(crate) fn with_metadata(mut self, metadata: FileMetadata) -> TokenFile {
+        self.metadata = metadata;
+        return self;
+    }
+
+    pub fn metadata(&self) -> &FileMetadata {
+        return &self.metadata;
+    }
+
+    // the embedded language regions found in this file
+    pub fn embedded(&self) -> &[Embedded] {
+        return &self.embedded;
+    }
+
+    pub fn line_count(&self) -> usize {
+        return self.line_starts.len();
+    }
+
+    // lines are 1-indexed to match token locations
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let index = line.checked_sub(self.first_line)?;
+        let start = *self.line_starts.get(index)?;
+        let end = self.line_starts.get(index + 1).map_or(self.source.len(), |end| end - 1);
+        return Some(self.source[start..end].trim_end_matches('\r'));
+    }
+
+    pub fn line_indent(&self, line: usize) -> Option<Indent> {
+        return self.indents.get(line.checked_sub(self.first_line)?).copied();
+    }
+
+    // the indentation of the line the token was lexed on
+    pub fn indent(&self, index: usize) -> Option<Indent> {
+        return self.line_indent(self.tokens.get(index)?.loc().line as usize);
+    }
+
+    pub fn tokens_on_line(&self, line: usize) -> impl Iterator<Item = &Token> {
+        return self.tokens.iter().filter(move |token| token.loc().line as usize == line);
+    }
+
+    pub fn into_tokens(self) -> Vec<Token> {
+        return self.tokens;
+    }
+}
+
+impl Index<usize> for TokenFile {
+    type Output = Token;
+
+    fn index(&self, index: usize) -> &Token {
+        return &self.tokens[index];
+    }
+}
+
+impl<'a> IntoIterator for &'a TokenFile {
+    type Item = &'a Token;
+    type IntoIter = slice::Iter<'a, Token>;
+
+    fn into_iter(self) -> slice::Iter<'a, Token> {
+        return self.tokens.iter();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Indent, Lexer, Section};
+
+    #[test]
+    fn token_file_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[], false);
+        lexer.load_str("let a\r\nlet \"b\"\n");
+
+        let file = lexer.tokenize_file()?;
+        assert_eq!(file.len(), 4);
+        assert_eq!(file.line(1), Some("let a"));
+        assert_eq!(file.line(2), Some("let \"b\""));
+        assert_eq!(file.line(3), Some(""));
+        assert_eq!(file.line(4), None);
+        assert_eq!(file.tokens_on_line(2).count(), 2);
+        file[0].is_keyword("let")?;
+        return Ok(());
+    }
+
+    #[test]
+    fn indent_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[], &[], false);
+        lexer.tab_width = 4;
+        lexer.load_str("a\n  \tb\n\t c");
+
+        let file = lexer.tokenize_file()?;
+        assert_eq!(file.indent(0), Some(Indent::default()));
+        assert_eq!(file.line_indent(2), Some(Indent { width: 4, tabs: 1, spaces: 2 }));
+        assert_eq!(file.line_indent(3), Some(Indent { width: 5, tabs: 1, spaces: 1 }));
+        assert_eq!(file.indent(file.len() - 1), file.line_indent(3));
+        assert!(file.line_indent(3).is_some_and(|indent| indent.is_mixed()));
+        assert_eq!(file.line_indent(4), None);
+        assert_eq!(file.indent(file.len()), None);
+        return Ok(());
+    }
+
+    #[test]
+    fn metadata_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("comment", "//", "\n")], &[], false);
+        lexer.comment_sections.push("comment".to_string());
+        lexer.shebang = true;
+        lexer.load_str("\u{feff}#!/usr/bin/env run\r\n// MIT\n// banner\nlet a");
+
+        let file = lexer.tokenize_file()?;
+        let metadata = file.metadata();
+        assert!(metadata.bom);
+        assert_eq!(metadata.shebang.as_deref(), Some("#!/usr/bin/env run"));
+        assert_eq!(metadata.banner, [" MIT", " banner"]);
+        assert!(!metadata.trailing_newline);
+        assert_eq!(file.line(2), Some("// MIT"));
+        assert_eq!(file.line(1), None);
+        file[2].is_keyword("let")?;
+        assert_eq!(file[2].loc().line, 4);
+        // the same tokens as tokenize, which skips the preamble the same way
+        assert_eq!(file.tokens(), lexer.tokenize()?.as_slice());
+
+        lexer.load_str("#![attr]\n");
+        let file = lexer.tokenize_file()?;
+        assert_eq!(file.metadata().shebang, None);
+        assert!(file.metadata().trailing_newline);
+        return Ok(());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{Fragment, Loc, Span, Token, TokenKind};
+
+use serde_json::{json, Map, Value};
+
+// the version written to every dump, raised whenever the schema below changes
+pub const TOKEN_JSON_VERSION: u64 = 1;
+
+// a dump is an object {"version": 1, "tokens": [...]} with one token object per line. a token has
+//   "kind"      the TokenKind, like "Ident" or "Symbol"
+//   "name"      the section, symbol, custom or comment name, null for the other kinds
+//   "text"      Token::as_string, except for interpolated sections where it is ""
+//   "line", "col", "offset"    the loc of the token
+//   "span"      [start, end] byte offsets, only when dumped with spanned_tokens_to_json
+//   "fragments" for interpolated sections, a list of {"text": ...} and {"tokens": [...]}
+// keys are written in sorted order so the same tokens always give the same text
+fn token_value(token: &Token, span: Option<&Span>) -> Value {
+    let loc = token.loc();
+    let mut object = Map::new();
+    object.insert("kind".to_string(), json!(token.kind().to_string()));
+    object.insert("name".to_string(), json!(token.name()));
+    object.insert("line".to_string(), json!(loc.line));
+    object.insert("col".to_string(), json!(loc.col));
+    object.insert("offset".to_string(), json!(loc.offset));
+    match token {
+        Token::Interpolated(_, fragments, _) => {
+            object.insert("text".to_string(), json!(""));
+            object.insert("fragments".to_string(), fragments.iter().map(|fragment| match fragment {
+                Fragment::Text(text) => json!({ "text": text }),
+                Fragment::Tokens(tokens) => json!({ "tokens": tokens.iter().map(|token| token_value(token, None)).collect::<Vec<Value>>() }),
+            }).collect());
+        },
+        token => {
+            object.insert("text".to_string(), json!(token.as_string()));
+        },
+    }
+    if let Some(span) = span {
+        object.insert("span".to_string(), json!([span.range.start, span.range.end]));
+    }
+    return Value::Object(object);
+}
+
+fn dump(values: impl Iterator<Item = Value>) -> String {
+    let lines = values.map(|value| value.to_string()).collect::<Vec<String>>();
+    if lines.is_empty() {
+        return format!("{{\"version\":{},\"tokens\":[]}}\n", TOKEN_JSON_VERSION);
+    }
+    return format!("{{\"version\":{},\"tokens\":[\n{}\n]}}\n", TOKEN_JSON_VERSION, lines.join(",\n"));
+}
+
+pub fn tokens_to_json(tokens: &[Token]) -> String {
+    return dump(tokens.iter().map(|token| token_value(token, None)));
+}
+
+// like tokens_to_json with the byte span of every token, see Lexer::tokenize_spanned
+pub fn spanned_tokens_to_json(tokens: &[(Token, Span)]) -> String {
+    return dump(tokens.iter().map(|(token, span)| token_value(token, Some(span))));
+}
+
+fn kind_from_name(name: &str) -> Option<TokenKind> {
+    return match name {
+        "Keyword" => Some(TokenKind::Keyword),
+        "Section" => Some(TokenKind::Section),
+        "Integer" => Some(TokenKind::Integer),
+        "BigInteger" => Some(TokenKind::BigInteger),
+        "Float" => Some(TokenKind::Float),
+        "Char" => Some(TokenKind::Char),
+        "Symbol" => Some(TokenKind::Symbol),
+        "Ident" => Some(TokenKind::Ident),
+        "Pragma" => Some(TokenKind::Pragma),
+        "Shebang" => Some(TokenKind::Shebang),
+        "Custom" => Some(TokenKind::Custom),
+        "Comment" => Some(TokenKind::Comment),
+        "Error" => Some(TokenKind::Error),
+        "Indent" => Some(TokenKind::Indent),
+        "Dedent" => Some(TokenKind::Dedent),
+        "Newline" => Some(TokenKind::Newline),
+        "Interpolated" => Some(TokenKind::Interpolated),
+        "Eof" => Some(TokenKind::Eof),
+        _ => None,
+    };
+}
+
+fn field<'a>(object: &'a Map<String, Value>, key: &str) -> Result<&'a Value, Box<dyn std::error::Error>> {
+    return object.get(key).ok_or_else(|| format!("token without {:?}: {}", key, Value::Object(object.clone())).into());
+}
+
+fn number(object: &Map<String, Value>, key: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    return field(object, key)?.as_u64().ok_or_else(|| format!("{:?} is not a number", key).into());
+}
+
+fn single_char(text: &str) -> Result<char, Box<dyn std::error::Error>> {
+    let mut chars = text.chars();
+    return match (chars.next(), chars.next()) {
+        (Some(character), None) => Ok(character),
+        _ => Err(format!("expected a single character: {:?}", text).into()),
+    };
+}
+
+fn token_from_value(value: &Value) -> Result<Token, Box<dyn std::error::Error>> {
+    let object = value.as_object().ok_or("token is not an object")?;
+    let kind = field(object, "kind")?.as_str().and_then(kind_from_name).ok_or("unknown token kind")?;
+    let text = field(object, "text")?.as_str().ok_or("\"text\" is not a string")?.to_string();
+    let name = || object.get("name").and_then(Value::as_str).map(str::to_string).ok_or("token without a name");
+    // the file is not part of the dump
+    let loc = Loc {
+        line: number(object, "line")? as u32,
+        col: number(object, "col")? as u32,
+        offset: number(object, "offset")? as usize,
+        ..Loc::default()
+    };
+
+    return Ok(match kind {
+        TokenKind::Keyword => Token::Keyword(text, loc),
+        TokenKind::Section => Token::Section(name()?, text, loc),
+        TokenKind::Integer => Token::Integer(text.parse()?, loc),
+        TokenKind::BigInteger => Token::BigInteger(text, loc),
+        TokenKind::Float => Token::Float(text.parse()?, loc),
+        TokenKind::Char => Token::Char(single_char(&text)?, loc),
+        TokenKind::Symbol => Token::Symbol(single_char(&text)?, name()?, loc),
+        TokenKind::Ident => Token::Ident(text, loc),
+        TokenKind::Pragma => Token::Pragma(text, loc),
+        TokenKind::Shebang => Token::Shebang(text, loc),
+        TokenKind::Custom => Token::Custom(name()?, text, loc),
+        TokenKind::Comment => Token::Comment(name()?, text, loc),
+        TokenKind::Error => Token::Error(text, loc),
+        TokenKind::Indent => Token::Indent(loc),
+        TokenKind::Dedent => Token::Dedent(loc),
+        TokenKind::Newline => Token::Newline(loc),
+        TokenKind::Interpolated => {
+            let fragments = field(object, "fragments")?.as_array().ok_or("\"fragments\" is not a list")?.iter().map(|fragment| {
+                if let Some(text) = fragment.get("text").and_then(Value::as_str) {
+                    return Ok(Fragment::Text(text.to_string()));
+                }
+                let tokens = fragment.get("tokens").and_then(Value::as_array).ok_or("fragment without text or tokens")?;
+                return Ok(Fragment::Tokens(tokens.iter().map(token_from_value).collect::<Result<Vec<Token>, Box<dyn std::error::Error>>>()?));
+            }).collect::<Result<Vec<Fragment>, Box<dyn std::error::Error>>>()?;
+            Token::Interpolated(name()?, fragments, loc)
+        },
+        TokenKind::Eof => Token::Eof(loc),
+    });
+}
+
+// reads a dump of either function back, spans are dropped and locs have the default file
+pub fn tokens_from_json(json: &str) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+    let dump = serde_json::from_str::<Value>(json)?;
+    let version = dump.get("version").and_then(Value::as_u64).ok_or("dump without a version")?;
+    if version > TOKEN_JSON_VERSION {
+        return Err(format!("token dump version {} is newer than {}", version, TOKEN_JSON_VERSION).into());
+    }
+    return dump.get("tokens").and_then(Value::as_array).ok_or("dump without tokens")?.iter().map(token_from_value).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{spanned_tokens_to_json, tokens_from_json, tokens_to_json, Lexer, Section};
+
+    #[test]
+    fn token_json_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &["let".to_string()],
+            &[Section::new("string", "\"", "\"").interpolate("${", "}"), Section::new("comment", "//", "\n")],
+            &[('=', "equal".to_string())],
+            false,
+        );
+        lexer.comment_sections = vec!["comment".to_string()];
+        lexer.load_str("let a = 1.5 // x\nb = \"v ${a} \\u\" 12");
+        let tokens = lexer.tokenize()?;
+
+        let json = tokens_to_json(&tokens);
+        assert_eq!(json.lines().nth(1), Some("{\"col\":3,\"kind\":\"Keyword\",\"line\":1,\"name\":null,\"offset\":2,\"text\":\"let\"},"));
+        assert_eq!(json.lines().count(), tokens.len() + 2);
+        assert_eq!(tokens_from_json(&json)?, tokens);
+        assert_eq!(tokens_to_json(&tokens_from_json(&json)?), json);
+
+        let spanned = spanned_tokens_to_json(&lexer.tokenize_spanned()?);
+        assert!(spanned.lines().nth(4).is_some_and(|line| line.contains("\"span\":[8,11]")));
+        assert_eq!(tokens_from_json(&spanned)?, tokens);
+
+        assert!(tokens_from_json("{\"version\":2,\"tokens\":[]}").is_err());
+        assert!(tokens_from_json("{\"version\":1,\"tokens\":[{\"kind\":\"Nothing\"}]}").is_err());
+        assert_eq!(tokens_to_json(&[]), "{\"version\":1,\"tokens\":[]}\n");
+        return Ok(());
+    }
+}
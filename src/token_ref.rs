@@ -0,0 +1,102 @@
+use crate::{Fragment, Loc, Token};
+
+use std::borrow::Cow;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenRef<'src> {
+    Keyword(Cow<'src, str>, Loc),
+    Section(&'src str, Cow<'src, str>, Loc),
+    Integer(usize, Loc),
+    BigInteger(Cow<'src, str>, Loc),
+    Float(f64, Loc),
+    Symbol(char, &'src str, Loc),
+    Ident(&'src str, Loc),
+    Pragma(&'src str, Loc),
+    Shebang(&'src str, Loc),
+    Custom(&'src str, &'src str, Loc),
+    Comment(&'src str, Cow<'src, str>, Loc),
+    Error(&'src str, Loc),
+    Char(char, Loc),
+    Indent(Loc),
+    Dedent(Loc),
+    Newline(Loc),
+    Interpolated(&'src str, Vec<FragmentRef<'src>>, Loc),
+    Eof(Loc),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FragmentRef<'src> {
+    Text(Cow<'src, str>),
+    Tokens(Vec<TokenRef<'src>>),
+    // the tokens of a sub lexer, see Section::sub_lex
+    Owned(Vec<Token>),
+}
+
+impl From<FragmentRef<'_>> for Fragment {
+    fn from(fragment: FragmentRef<'_>) -> Fragment {
+        return match fragment {
+            FragmentRef::Text(text) => Fragment::Text(text.into_owned()),
+            FragmentRef::Tokens(tokens) => Fragment::Tokens(tokens.into_iter().map(Token::from).collect()),
+            FragmentRef::Owned(tokens) => Fragment::Tokens(tokens),
+        };
+    }
+}
+
+impl TokenRef<'_> {
+    pub fn loc(&self) -> Loc {
+        return match self {
+            TokenRef::Keyword(_, loc) => *loc,
+            TokenRef::Section(_, _, loc) => *loc,
+            TokenRef::Integer(_, loc) => *loc,
+            TokenRef::BigInteger(_, loc) => *loc,
+            TokenRef::Float(_, loc) => *loc,
+            TokenRef::Symbol(_, _, loc) => *loc,
+            TokenRef::Ident(_, loc) => *loc,
+            TokenRef::Pragma(_, loc) => *loc,
+            TokenRef::Shebang(_, loc) => *loc,
+            TokenRef::Custom(_, _, loc) => *loc,
+            TokenRef::Comment(_, _, loc) => *loc,
+            TokenRef::Error(_, loc) => *loc,
+            TokenRef::Char(_, loc) => *loc,
+            TokenRef::Indent(loc) => *loc,
+            TokenRef::Dedent(loc) => *loc,
+            TokenRef::Newline(loc) => *loc,
+            TokenRef::Interpolated(_, _, loc) => *loc,
+            TokenRef::Eof(loc) => *loc,
+        };
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn to_owned(&self) -> Token {
+        return match self {
+            TokenRef::Keyword(keyword, loc) => Token::Keyword(keyword.to_string(), *loc),
+            TokenRef::Section(name, value, loc) => Token::Section(name.to_string(), value.to_string(), *loc),
+            TokenRef::Integer(integer, loc) => Token::Integer(*integer, *loc),
+            TokenRef::BigInteger(digits, loc) => Token::BigInteger(digits.to_string(), *loc),
+            TokenRef::Float(float, loc) => Token::Float(*float, *loc),
+            TokenRef::Symbol(value, name, loc) => Token::Symbol(*value, name.to_string(), *loc),
+            TokenRef::Ident(ident, loc) => Token::Ident(ident.to_string(), *loc),
+            TokenRef::Pragma(pragma, loc) => Token::Pragma(pragma.to_string(), *loc),
+            TokenRef::Shebang(line, loc) => Token::Shebang(line.to_string(), *loc),
+            TokenRef::Custom(name, text, loc) => Token::Custom(name.to_string(), text.to_string(), *loc),
+            TokenRef::Comment(kind, text, loc) => Token::Comment(kind.to_string(), text.to_string(), *loc),
+            TokenRef::Error(text, loc) => Token::Error(text.to_string(), *loc),
+            TokenRef::Char(character, loc) => Token::Char(*character, *loc),
+            TokenRef::Indent(loc) => Token::Indent(*loc),
+            TokenRef::Dedent(loc) => Token::Dedent(*loc),
+            TokenRef::Newline(loc) => Token::Newline(*loc),
+            TokenRef::Interpolated(name, fragments, loc) => Token::Interpolated(name.to_string(), fragments.iter().cloned().map(Fragment::from).collect(), *loc),
+            TokenRef::Eof(loc) => Token::Eof(*loc),
+        };
+    }
+}
+
+impl From<TokenRef<'_>> for Token {
+    fn from(token: TokenRef<'_>) -> Token {
+        return match token {
+            TokenRef::Section(name, value, loc) => Token::Section(name.to_string(), value.into_owned(), loc),
+            TokenRef::Interpolated(name, fragments, loc) => Token::Interpolated(name.to_string(), fragments.into_iter().map(Fragment::from).collect(), loc),
+            token => token.to_owned(),
+        };
+    }
+}
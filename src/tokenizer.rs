@@ -0,0 +1,118 @@
+use crate::{FileId, LexError, Lexer, Loc, Token, TokenRef};
+
+// one input lexed with a lexer that is only read, the state of the run lives here. a configured
+// lexer behind an Arc is shared by any number of threads, each lexing its own inputs
+#[derive(Debug, Clone, Copy)]
+pub struct Tokenizer<'a> {
+    lexer: &'a Lexer,
+    source: &'a str,
+    file: FileId,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(lexer: &'a Lexer, source: &'a str) -> Tokenizer<'a> {
+        return Tokenizer {
+            lexer,
+            source,
+            file: lexer.file,
+        };
+    }
+
+    // the file the tokens are located in, the one of the lexer by default
+    pub fn in_file(mut self, file: FileId) -> Tokenizer<'a> {
+        self.file = file;
+        return self;
+    }
+
+    pub fn source(&self) -> &'a str {
+        return self.source;
+    }
+
+    pub fn tokenize_ref(&self) -> Result<Vec<TokenRef<'a>>, LexError> {
+        let mut run = self.lexer.new_run();
+        run.skip_spans = true;
+        let (scanned, _) = self.lexer.scan_until(self.source, self.source.len(), &mut run, &mut Loc::new(self.file), true)?;
+        return Ok(scanned.tokens);
+    }
+
+    pub fn tokenize(&self) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+        let mut tokens = Vec::new();
+        self.tokenize_into(&mut tokens)?;
+        return Ok(tokens);
+    }
+
+    pub fn tokenize_into(&self, tokens: &mut Vec<Token>) -> Result<(), Box<dyn std::error::Error>> {
+        tokens.clear();
+        tokens.extend(self.tokenize_ref()?.into_iter().filter_map(|token| self.lexer.finish_token(token)));
+        if let Some(error) = self.lexer.check_pairs.then(|| self.lexer.unbalanced(tokens)).and_then(|errors| errors.into_iter().next()) {
+            return Err(LexError::Unbalanced(error).into());
+        }
+        return Ok(());
+    }
+
+    // never fails, see Lexer::tokenize_lossy
+    pub fn tokenize_lossy(&self) -> (Vec<Token>, Vec<LexError>) {
+        let mut run = self.lexer.new_run();
+        run.recover = true;
+
+        return match self.lexer.scan_until(self.source, self.source.len(), &mut run, &mut Loc::new(self.file), true) {
+            Ok((scanned, _)) => {
+                let tokens = scanned.tokens.into_iter().filter_map(|token| self.lexer.finish_token(token)).collect::<Vec<Token>>();
+                let mut errors = scanned.errors;
+                if self.lexer.check_pairs {
+                    errors.extend(self.lexer.unbalanced(&tokens).into_iter().map(LexError::Unbalanced));
+                }
+                (tokens, errors)
+            },
+            Err(error) => (Vec::new(), vec![error]),
+        };
+    }
+}
+
+impl Lexer {
+    // lexes source instead of the loaded input, without needing the lexer mutably
+    pub fn tokenizer<'a>(&'a self, source: &'a str) -> Tokenizer<'a> {
+        return Tokenizer::new(self, source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FileId, Lexer, Section, Token};
+
+    use std::sync::Arc;
+    use std::thread;
+
+    fn shared<T: Send + Sync>() {}
+
+    #[test]
+    fn tokenizer_test() -> Result<(), Box<dyn std::error::Error>> {
+        shared::<Lexer>();
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[('=', "equal".to_string())], false);
+        lexer.add_rule("hex", |rest: &str| rest.strip_prefix("0x").map(|digits| 2 + digits.len() - digits.trim_start_matches(|c: char| c.is_ascii_hexdigit()).len()));
+        let lexer = Arc::new(lexer);
+
+        let workers = (0..4).map(|worker| {
+            let lexer = Arc::clone(&lexer);
+            return thread::spawn(move || {
+                let documents = (0..50).map(|document| format!("let a{} = \"{}\" 0x{:x}", worker, document, document)).collect::<Vec<String>>();
+                return documents.iter().map(|document| lexer.tokenizer(document).tokenize().map_err(|err| err.to_string())).collect::<Result<Vec<Vec<Token>>, String>>();
+            });
+        }).collect::<Vec<_>>();
+
+        for (worker, handle) in workers.into_iter().enumerate() {
+            let documents = handle.join().map_err(|_| "worker panicked")??;
+            assert_eq!(documents.len(), 50);
+            assert_eq!(documents[7][1].is_ident()?, format!("a{}", worker));
+            assert_eq!(documents[7][3].is_section("string")?, "7");
+            assert!(matches!(&documents[12][4], Token::Custom(name, text, _) if name == "hex" && text == "0xc"));
+        }
+
+        let file = FileId::new("doc.src");
+        let tokenizer = lexer.tokenizer("let x = \"y").in_file(file);
+        assert_eq!(tokenizer.tokenize()?[0].loc().file, file);
+        let (tokens, errors) = lexer.tokenizer("a \u{fffd}").tokenize_lossy();
+        assert_eq!((tokens.len(), errors.len()), (2, 1));
+        return Ok(());
+    }
+}
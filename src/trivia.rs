@@ -0,0 +1,245 @@
+use crate::{Lexer, Loc, Token, TokenRef};
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+// a token with its exact source text and the trivia around it. trailing trivia runs up to and
+// including the first newline after the token, everything else leads the following token
+#[derive(Debug, Clone, PartialEq)]
+pub struct LosslessToken {
+    pub token: Token,
+    pub text: String,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+impl LosslessToken {
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+        for trivia in &self.leading {
+            source.push_str(&trivia.text);
+        }
+        source.push_str(&self.text);
+        for trivia in &self.trailing {
+            source.push_str(&trivia.text);
+        }
+        return source;
+    }
+}
+
+// where source re-emitted from lossless tokens first differs from the loaded input, see
+// Lexer::verify_roundtrip. expected and found run from there to the end of their line
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundtripDiff {
+    pub loc: Loc,
+    // the token whose source covers loc, None when the tokens end before the input
+    pub token: Option<usize>,
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for RoundtripDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}: source differs, expected {:?} but found {:?}", self.loc, self.expected, self.found);
+    }
+}
+
+impl std::error::Error for RoundtripDiff {}
+
+fn rest_of_line(text: &str) -> String {
+    return text[..text.find('\n').map_or(text.len(), |end| end + 1)].to_string();
+}
+
+// concatenates the tokens back into the source they were lexed from
+pub fn lossless_source(tokens: &[LosslessToken]) -> String {
+    return tokens.iter().map(LosslessToken::to_source).collect();
+}
+
+fn split_gap(gap: &str, trivia: &mut Vec<Trivia>) {
+    let mut rest = gap;
+    while !rest.is_empty() {
+        let (kind, length) = if rest.starts_with("\r\n") {
+            (TriviaKind::Newline, 2)
+        } else if rest.starts_with('\n') {
+            (TriviaKind::Newline, 1)
+        } else {
+            (TriviaKind::Whitespace, rest.find(['\n', '\r']).filter(|end| *end > 0).unwrap_or(rest.len()))
+        };
+
+        trivia.push(Trivia {
+            kind,
+            text: rest[..length].to_string(),
+        });
+        rest = &rest[length..];
+    }
+}
+
+// moves the pending trivia up to the first newline onto the previous token, returning the rest
+fn attach_trailing(previous: Option<&mut LosslessToken>, pending: &mut Vec<Trivia>) -> Vec<Trivia> {
+    let Some(previous) = previous else {
+        return std::mem::take(pending);
+    };
+
+    // a line comment ends the line just like a newline does
+    let split = pending.iter()
+        .position(|trivia| trivia.kind == TriviaKind::Newline || trivia.text.ends_with('\n'))
+        .map_or(pending.len(), |newline| newline + 1);
+    let leading = pending.split_off(split);
+    previous.trailing.append(pending);
+    return leading;
+}
+
+impl Lexer {
+    // lexes the loaded input keeping whitespace, newlines and comment sections as trivia, the
+    // stream always ends with an Eof token so trailing trivia has somewhere to go
+    pub fn tokenize_lossless(&self) -> Result<Vec<LosslessToken>, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let (scanned, _) = self.scan_until(source, source.len(), &mut self.new_run(), &mut Loc::new(self.file), true)?;
+
+        let mut tokens: Vec<LosslessToken> = Vec::new();
+        let mut pending = Vec::new();
+        let mut cursor = 0;
+        let mut eof = None;
+        for (token, span) in scanned.tokens.into_iter().zip(scanned.spans) {
+            if let TokenRef::Eof(loc) = token {
+                eof = Some(loc);
+                continue;
+            } else if span.is_empty() {
+                // whitespace idents from allow_whitespace, the gaps already cover them
+                continue;
+            }
+
+            split_gap(&source[cursor..span.start], &mut pending);
+            cursor = span.end;
+
+            if let TokenRef::Comment(..) = token {
+                pending.push(Trivia {
+                    kind: TriviaKind::Comment,
+                    text: source[span].to_string(),
+                });
+                continue;
+            }
+
+            let leading = attach_trailing(tokens.last_mut(), &mut pending);
+            tokens.push(LosslessToken {
+                token: Token::from(token),
+                text: source[span].to_string(),
+                leading,
+                trailing: Vec::new(),
+            });
+        }
+
+        split_gap(&source[cursor..], &mut pending);
+        let leading = attach_trailing(tokens.last_mut(), &mut pending);
+        let loc = eof.or(tokens.last().map(|token| token.token.loc())).unwrap_or(Loc::new(self.file));
+        tokens.push(LosslessToken {
+            token: Token::Eof(loc),
+            text: String::new(),
+            leading,
+            trailing: Vec::new(),
+        });
+        return Ok(tokens);
+    }
+
+    // checks that the tokens re-emit the loaded input byte for byte, reporting the first mismatch
+    pub fn verify_roundtrip(&self, tokens: &[LosslessToken]) -> Result<(), RoundtripDiff> {
+        let original = String::from_utf8_lossy(self.source_bytes());
+        let source = lossless_source(tokens);
+
+        let mut loc = Loc::new(self.file);
+        let mut remaining = (original.as_ref(), source.as_str());
+        loop {
+            let mut expected = remaining.0.chars();
+            let mut found = remaining.1.chars();
+            match (expected.next(), found.next()) {
+                (None, None) => return Ok(()),
+                (Some(a), Some(b)) if a == b => {
+                    self.advance(&mut loc, &remaining.0[..a.len_utf8()]);
+                    remaining = (expected.as_str(), found.as_str());
+                },
+                _ => break,
+            }
+        }
+
+        let mut end = 0;
+        let token = tokens.iter().position(|token| {
+            end += token.to_source().len();
+            return end > loc.offset;
+        });
+        return Err(RoundtripDiff {
+            loc,
+            token,
+            expected: rest_of_line(remaining.0),
+            found: rest_of_line(remaining.1),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lossless_source, Lexer, Loc, RoundtripDiff, Section, Token, TriviaKind};
+
+    #[test]
+    fn lossless_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(
+            &["let".to_string()],
+            &[Section::new("string", "\"", "\""), Section::new("comment", "//", "\n")],
+            &[('=', "equals".to_string()), (';', "semicolon".to_string())],
+            true,
+        );
+        lexer.comment_sections.push("comment".to_string());
+
+        let source = "  let  a = \"x  y\";  // note\r\n\n\tlet b=1.50;\n  ";
+        lexer.load_str(source);
+
+        let tokens = lexer.tokenize_lossless()?;
+        assert_eq!(lossless_source(&tokens), source);
+
+        tokens[0].token.is_keyword("let")?;
+        assert_eq!(tokens[0].leading[0].text, "  ");
+        assert_eq!(tokens[3].text, "\"x  y\"");
+        assert_eq!(tokens[4].trailing.iter().map(|trivia| trivia.kind).collect::<Vec<_>>(), [TriviaKind::Whitespace, TriviaKind::Comment]);
+        assert_eq!(tokens[5].leading[0].kind, TriviaKind::Newline);
+        assert!(tokens.iter().any(|token| token.text == "1.50"));
+        assert!(matches!(tokens.last().map(|token| &token.token), Some(Token::Eof(_))));
+
+        lexer.load_str("");
+        assert_eq!(lossless_source(&lexer.tokenize_lossless()?), "");
+        lexer.load_str(" \n ");
+        assert_eq!(lexer.tokenize_lossless()?.len(), 1);
+        return Ok(());
+    }
+
+    #[test]
+    fn roundtrip_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[], &[('=', "equal".to_string())], false);
+        lexer.load_str("let a = 1\nlet b = 2\n");
+
+        let mut tokens = lexer.tokenize_lossless()?;
+        lexer.verify_roundtrip(&tokens)?;
+
+        tokens[6].text = "==".to_string();
+        assert_eq!(lexer.verify_roundtrip(&tokens), Err(RoundtripDiff {
+            loc: Loc { file: lexer.file, line: 2, col: 8, offset: 17 },
+            token: Some(6),
+            expected: " 2\n".to_string(),
+            found: "= 2\n".to_string(),
+        }));
+
+        tokens.truncate(4);
+        assert_eq!(lexer.verify_roundtrip(&tokens).map_err(|diff| (diff.loc.offset, diff.token)), Err((10, None)));
+        return Ok(());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{Lexer, Loc, Token, TokenRef};
+
+// a caller's own token type, which Lexer::tokenize_as produces in place of Token. None drops the
+// token, like map_token does
+pub trait FromTokenRef: Sized {
+    fn from_token_ref(token: TokenRef<'_>) -> Option<Self>;
+}
+
+impl FromTokenRef for Token {
+    fn from_token_ref(token: TokenRef<'_>) -> Option<Token> {
+        return Some(Token::from(token));
+    }
+}
+
+impl Lexer {
+    // each token is turned into a T as soon as it is lexed, so no Token or list of borrowed tokens is
+    // built on the way and a custom rule's match reaches from_token_ref as TokenRef::Custom the moment
+    // the rule accepts it. like tokenize_ref the conversion sees the tokens as lexed, map_token is not
+    // applied
+    pub fn tokenize_as<T: FromTokenRef>(&self) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+        let source = std::str::from_utf8(self.source_bytes())?;
+        let mut tokens = Vec::new();
+        let mut sink = |token: TokenRef<'_>| tokens.extend(T::from_token_ref(token));
+
+        let mut run = self.new_run();
+        run.skip_spans = true;
+        self.scan_into(source, source.len(), &mut run, &mut Loc::new(self.file), true, Some(&mut sink))?;
+        return Ok(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::{FromTokenRef, Lexer, Section, Token, TokenRef};
+
+    #[derive(Debug, PartialEq)]
+    enum Json {
+        Open,
+        Close,
+        Text(String),
+        Number(usize),
+        Other(String),
+    }
+
+    impl FromTokenRef for Json {
+        fn from_token_ref(token: TokenRef<'_>) -> Option<Json> {
+            return match token {
+                TokenRef::Symbol('{', _, _) => Some(Json::Open),
+                TokenRef::Symbol('}', _, _) => Some(Json::Close),
+                TokenRef::Symbol(..) => None,
+                TokenRef::Section(_, text, _) => Some(Json::Text(text.into_owned())),
+                TokenRef::Integer(number, _) => Some(Json::Number(number)),
+                TokenRef::Custom(_, text, _) => Some(Json::Other(text.to_string())),
+                _ => None,
+            };
+        }
+    }
+
+    #[test]
+    fn tokenize_as_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&[], &[Section::new("string", "\"", "\"")], &[('{', "open".to_string()), ('}', "close".to_string()), (':', "colon".to_string()), (',', "comma".to_string())], false);
+        lexer.add_rule("null", |rest| rest.starts_with("null").then_some(4));
+        lexer.load_str("{\"a\": 1, \"b\": null}");
+
+        let tokens = lexer.tokenize_as::<Json>()?;
+        assert_eq!(tokens, vec![Json::Open, Json::Text("a".to_string()), Json::Number(1), Json::Text("b".to_string()), Json::Other("null".to_string()), Json::Close]);
+        assert_eq!(lexer.tokenize_as::<Token>()?, lexer.tokenize()?);
+        return Ok(());
+    }
+
+    static CONVERTED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted;
+
+    impl FromTokenRef for Counted {
+        fn from_token_ref(_: TokenRef<'_>) -> Option<Counted> {
+            CONVERTED.fetch_add(1, Ordering::SeqCst);
+            return Some(Counted);
+        }
+    }
+
+    #[test]
+    fn tokenize_as_streams_test() -> Result<(), Box<dyn std::error::Error>> {
+        // the tokens before a rule's match are already converted when the rule runs
+        let seen = Arc::new(AtomicUsize::new(usize::MAX));
+        let mut lexer = Lexer::new(&[], &[], &[(',', "comma".to_string())], false);
+        let at_rule = seen.clone();
+        lexer.add_rule("null", move |rest| {
+            let matched = rest.starts_with("null").then_some(4);
+            if matched.is_some() {
+                at_rule.store(CONVERTED.load(Ordering::SeqCst), Ordering::SeqCst);
+            }
+            return matched;
+        });
+        lexer.load_str("a, b, null, c");
+
+        let tokens = lexer.tokenize_as::<Counted>()?;
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(seen.load(Ordering::SeqCst), 4);
+        assert_eq!(CONVERTED.load(Ordering::SeqCst), 7);
+
+        lexer.limits.max_tokens = Some(3);
+        assert!(lexer.tokenize_as::<Token>().is_err());
+        return Ok(());
+    }
+}
@@ -0,0 +1,46 @@
+use std::borrow::Cow;
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+// the identifier character class of unicode, see Lexer::xid_idents. an underscore is allowed
+// anywhere, numbers are told apart from identifiers by their first character as usual
+pub fn is_xid_ident_char(character: char) -> bool {
+    return character == '_' || unicode_ident::is_xid_continue(character);
+}
+
+// text in normalization form c, borrowed when it already is
+pub(crate) fn nfc(text: &str) -> Cow<'_, str> {
+    return match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(text),
+        _ => Cow::Owned(text.nfc().collect()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Token, TokenRef};
+
+    #[test]
+    fn xid_idents_test() -> Result<(), Box<dyn std::error::Error>> {
+        // "café" written with a combining acute accent, which is not alphanumeric
+        let decomposed = "cafe\u{301}";
+        let mut lexer = Lexer::new(&["caf\u{e9}".to_string()], &[], &[('+', "plus".to_string())], false);
+        lexer.strict = true;
+
+        lexer.load_str(&format!("{}+x", decomposed));
+        assert!(lexer.tokenize().is_err());
+
+        lexer.xid_idents = true;
+        let tokens = lexer.tokenize()?;
+        assert_eq!(tokens[0].is_ident()?, decomposed);
+
+        lexer.normalize_idents = true;
+        lexer.load_str(&format!("{} na\u{ef}ve\u{301}", decomposed));
+        let tokens = lexer.tokenize()?;
+        tokens[0].is_keyword("caf\u{e9}")?;
+        assert_eq!(tokens[1].is_ident()?, "na\u{ef}v\u{e9}");
+        assert!(matches!(lexer.tokenize_ref()?[1], TokenRef::Ident(ident, _) if ident == "na\u{ef}ve\u{301}"));
+        assert!(matches!(&lexer.tokenize_compact()?.get(1), Some(Token::Ident(ident, _)) if ident == "na\u{ef}v\u{e9}"));
+        return Ok(());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{Loc, Token, TokenKind};
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constraint {
+    // the body of the named section must not contain the character
+    SectionExcludes(String, char),
+    // integers must be strictly below the limit
+    IntegerBelow(usize),
+    // two tokens of the kind must not follow each other
+    NoAdjacent(TokenKind),
+    // the keyword must be followed by a token of the kind
+    KeywordFollowedBy(String, TokenKind),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub token: usize,
+    pub loc: Loc,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    pub constraints: Vec<Constraint>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}: {}", self.loc, self.message);
+    }
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        return Validator::default();
+    }
+
+    pub fn add(&mut self, constraint: Constraint) {
+        self.constraints.push(constraint);
+    }
+
+    fn check(constraint: &Constraint, token: &Token, next: Option<&Token>) -> Option<String> {
+        return match (constraint, token) {
+            (Constraint::SectionExcludes(name, character), Token::Section(section, body, _)) if section == name && body.contains(*character) => {
+                Some(format!("section {} must not contain {:?}", name, character))
+            },
+            (Constraint::IntegerBelow(limit), Token::Integer(integer, _)) if integer >= limit => {
+                Some(format!("integer {} must be below {}", integer, limit))
+            },
+            (Constraint::NoAdjacent(kind), token) if token.kind() == *kind && next.is_some_and(|next| next.kind() == *kind) => {
+                Some(format!("two adjacent {} tokens", kind))
+            },
+            (Constraint::KeywordFollowedBy(keyword, kind), Token::Keyword(value, _)) if value == keyword && next.is_none_or(|next| next.kind() != *kind) => {
+                Some(format!("keyword {} must be followed by {}", keyword, kind))
+            },
+            _ => None,
+        };
+    }
+
+    // every violation in token order, constraints are checked in the order they were added
+    pub fn validate(&self, tokens: &[Token]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (index, token) in tokens.iter().enumerate() {
+            for constraint in &self.constraints {
+                if let Some(message) = Validator::check(constraint, token, tokens.get(index + 1)) {
+                    violations.push(Violation {
+                        token: index,
+                        loc: token.loc(),
+                        message,
+                    });
+                }
+            }
+        }
+        return violations;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Constraint, Lexer, Section, TokenKind, Validator};
+
+    #[test]
+    fn validate_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[], false);
+        lexer.load_str("let a b \"x\ty\" 5000000000 let 1");
+
+        let mut validator = Validator::new();
+        validator.add(Constraint::SectionExcludes("string".to_string(), '\t'));
+        validator.add(Constraint::IntegerBelow(1 << 32));
+        validator.add(Constraint::NoAdjacent(TokenKind::Ident));
+        validator.add(Constraint::KeywordFollowedBy("let".to_string(), TokenKind::Ident));
+
+        let violations = validator.validate(&lexer.tokenize()?);
+        assert_eq!(violations.iter().map(|violation| violation.token).collect::<Vec<_>>(), [1, 3, 4, 5]);
+        assert_eq!(violations[0].message, "two adjacent Ident tokens");
+        assert!(violations[3].to_string().ends_with("keyword let must be followed by Ident"));
+        return Ok(());
+    }
+}
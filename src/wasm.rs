@@ -0,0 +1,68 @@
+use crate::{Lexer, LexerConfig, Token};
+
+use wasm_bindgen::prelude::*;
+
+// a token as it is handed to javascript, name is the section, symbol, custom or comment name
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+struct JsToken {
+    kind: String,
+    text: String,
+    name: Option<String>,
+    line: u32,
+    col: u32,
+}
+
+impl From<&Token> for JsToken {
+    fn from(token: &Token) -> JsToken {
+        return JsToken {
+            kind: token.kind().to_string(),
+            text: token.as_string(),
+            name: token.name().map(str::to_string),
+            line: token.loc().line,
+            col: token.loc().col,
+        };
+    }
+}
+
+// a lexer for the browser, configured from a plain object in the LexerConfig format
+#[wasm_bindgen(js_name = Lexer)]
+pub struct WasmLexer {
+    lexer: Lexer,
+}
+
+#[wasm_bindgen(js_class = Lexer)]
+impl WasmLexer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(config: JsValue) -> Result<WasmLexer, JsError> {
+        let config: LexerConfig = serde_wasm_bindgen::from_value(config)?;
+        return Ok(WasmLexer {
+            lexer: Lexer::from(config),
+        });
+    }
+
+    // lexes input into an array of { kind, text, name, line, col } objects
+    pub fn tokenize(&mut self, input: &str) -> Result<JsValue, JsError> {
+        self.lexer.load_str(input);
+        let tokens = self.lexer.tokenize().map_err(|err| JsError::new(&err.to_string()))?;
+        return Ok(serde_wasm_bindgen::to_value(&tokens.iter().map(JsToken::from).collect::<Vec<JsToken>>())?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Section;
+
+    #[test]
+    fn js_token_test() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lexer = Lexer::new(&["let".to_string()], &[Section::new("string", "\"", "\"")], &[], false);
+        lexer.load_str("let \"a\"");
+
+        let tokens = lexer.tokenize()?.iter().map(JsToken::from).collect::<Vec<JsToken>>();
+        assert_eq!(tokens[0], JsToken { kind: "Keyword".to_string(), text: "let".to_string(), name: None, line: 1, col: 3 });
+        assert_eq!(tokens[1].name.as_deref(), Some("string"));
+        assert_eq!(tokens[1].text, "a");
+        return Ok(());
+    }
+}